@@ -0,0 +1,93 @@
+//! Benchmarks of the decoding of instructions.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use rayquaza_vm::instr::*;
+
+/// The number of instructions read by each run.
+const INSTRUCTIONS: usize = 10_000;
+
+/// Returns the fields of the instructions of a tight loop, which the virtual machine keeps
+/// decoding over and over.
+fn tight_loop() -> Vec<Instruction> {
+    let base = DecodedInstruction {
+        dst_offset: 0,
+        op0_offset: -1,
+        op1_offset: 1,
+        dst_register: DstRegister::AP,
+        op0_register: Op0Register::AP,
+        op1_source: Op1Source::PC,
+        result_logic: ResultLogic::Add,
+        pc_update: PcUpdate::Regular,
+        ap_update: ApUpdate::Increment,
+        op_code: OpCode::AssertEq,
+    };
+    let branch = DecodedInstruction {
+        dst_offset: -1,
+        op1_offset: -4,
+        op1_source: Op1Source::AP,
+        result_logic: ResultLogic::Op1,
+        pc_update: PcUpdate::ConditionalJump,
+        ap_update: ApUpdate::None,
+        op_code: OpCode::None,
+        ..base
+    };
+
+    [base.encode(), branch.encode()].repeat(INSTRUCTIONS / 2)
+}
+
+/// Reads the fields of `instruction` the way a step does, once per stage of the pipeline.
+fn read_with_accessors(instruction: Instruction) -> usize {
+    let mut acc = instruction.size().unwrap();
+    // Operands.
+    acc += instruction.dst_offset() as usize + instruction.dst_register() as usize;
+    acc += instruction.op0_offset() as usize + instruction.op0_register() as usize;
+    acc += instruction.op1_offset() as usize + instruction.op1_source().unwrap() as usize;
+    // Deduction.
+    acc += instruction.op_code().unwrap() as usize + instruction.result_logic().unwrap() as usize;
+    // Register updates.
+    acc += instruction.pc_update().unwrap() as usize + instruction.ap_update().unwrap() as usize;
+    acc += instruction.op_code().unwrap() as usize + instruction.size().unwrap();
+    acc
+}
+
+/// Reads the fields of `instruction` the way a step does, after decoding it once.
+fn read_decoded(instruction: Instruction) -> usize {
+    let decoded = instruction.decode().unwrap();
+    let mut acc = decoded.size();
+    acc += decoded.dst_offset as usize + decoded.dst_register as usize;
+    acc += decoded.op0_offset as usize + decoded.op0_register as usize;
+    acc += decoded.op1_offset as usize + decoded.op1_source as usize;
+    acc += decoded.op_code as usize + decoded.result_logic as usize;
+    acc += decoded.pc_update as usize + decoded.ap_update as usize;
+    acc += decoded.op_code as usize + decoded.size();
+    acc
+}
+
+/// Reading the fields of instructions, as done by every step.
+fn fields(c: &mut Criterion) {
+    let instructions = tight_loop();
+
+    let mut group = c.benchmark_group("fields");
+    group.bench_function("accessors", |b| {
+        b.iter(|| {
+            instructions
+                .iter()
+                .map(|&i| read_with_accessors(black_box(i)))
+                .fold(0usize, usize::wrapping_add)
+        })
+    });
+    group.bench_function("decoded", |b| {
+        b.iter(|| {
+            instructions
+                .iter()
+                .map(|&i| read_decoded(black_box(i)))
+                .fold(0usize, usize::wrapping_add)
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, fields);
+criterion_main!(benches);
@@ -0,0 +1,82 @@
+//! Benchmarks of the operations of [`Memory`].
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use rayquaza_vm::memory::{Memory, Pointer, Value, ValueRef};
+use starknet_types_core::felt::Felt;
+
+/// The number of segments allocated by each run.
+const SEGMENTS: usize = 1_000;
+
+/// The number of cells written to each segment.
+const CELLS_PER_SEGMENT: usize = 8;
+
+/// Allocates [`SEGMENTS`] small segments in `memory` and fills them.
+fn fill_small_segments(mut memory: Memory) -> Memory {
+    for _ in 0..SEGMENTS {
+        let segment = memory.allocate_segment();
+        for offset in 0..CELLS_PER_SEGMENT {
+            memory
+                .assert_eq(Pointer { segment, offset }, ValueRef::Scalar(&Felt::ONE))
+                .unwrap();
+        }
+    }
+    memory
+}
+
+/// The number of cells of the program loaded by each run.
+const PROGRAM_CELLS: usize = 100_000;
+
+/// Allocates a segment in a new memory, returning a pointer to its start.
+fn empty_segment() -> (Memory, Pointer) {
+    let mut memory = Memory::default();
+    let segment = memory.allocate_segment();
+    (memory, Pointer { segment, offset: 0 })
+}
+
+/// Programs allocating many small segments, such as dictionaries or arrays of structures.
+fn small_segments(c: &mut Criterion) {
+    let mut group = c.benchmark_group("small_segments");
+    group.bench_function("default", |b| {
+        b.iter_batched(Memory::default, fill_small_segments, BatchSize::SmallInput)
+    });
+    group.bench_function("arena", |b| {
+        b.iter_batched(
+            || Memory::with_arena(SEGMENTS * CELLS_PER_SEGMENT),
+            fill_small_segments,
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+/// Loading a large program, whose bytecode is usually available as field elements.
+fn load(c: &mut Criterion) {
+    let bytecode: Vec<Felt> = (0..PROGRAM_CELLS as u64).map(Felt::from).collect();
+
+    let mut group = c.benchmark_group("load");
+    group.bench_function("values", |b| {
+        b.iter_batched(
+            empty_segment,
+            |(mut memory, start)| {
+                let values: Vec<Value> = bytecode.iter().copied().map(Value::Scalar).collect();
+                memory.load_data(start, &values).unwrap();
+                memory
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("felts", |b| {
+        b.iter_batched(
+            empty_segment,
+            |(mut memory, start)| {
+                memory.load_felt_data(start, &bytecode).unwrap();
+                memory
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, small_segments, load);
+criterion_main!(benches);
@@ -0,0 +1,115 @@
+//! Benchmarks of the operations of [`Segment`].
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use rayquaza_vm::memory::{GrowthPolicy, Segment, Value, ValueRef};
+use starknet_types_core::felt::Felt;
+
+/// The number of cells written by each run.
+const CELLS: usize = 10_000;
+
+/// Returns a segment of [`CELLS`] cells holding `value`.
+fn filled_with(value: Felt) -> Segment {
+    let mut segment = Segment::new();
+    segment.reserve(CELLS).unwrap();
+    for index in 0..CELLS {
+        segment.set(index, ValueRef::Scalar(&value)).unwrap();
+    }
+    segment
+}
+
+/// Asserts that every cell of `segment` holds `value`.
+fn assert_all(mut segment: Segment, value: &Felt) -> Segment {
+    for index in 0..CELLS {
+        segment.assert_eq(index, ValueRef::Scalar(value)).unwrap();
+    }
+    segment
+}
+
+/// Writes [`CELLS`] cells to `segment`, one at a time.
+fn fill(mut segment: Segment) -> Segment {
+    for index in 0..CELLS {
+        segment.set(index, ValueRef::Scalar(&Felt::ONE)).unwrap();
+    }
+    segment
+}
+
+/// Asserts `values` into `segment`, one cell at a time.
+fn assert_looped(mut segment: Segment, values: &[Value]) -> Segment {
+    for (index, value) in values.iter().enumerate() {
+        segment.assert_eq(index, value.as_ref()).unwrap();
+    }
+    segment
+}
+
+/// Loading a block of data into fresh memory, such as the arguments of a program.
+fn assert_run(c: &mut Criterion) {
+    let values: Vec<Value> = (0..CELLS).map(|i| Value::Scalar(Felt::from(i))).collect();
+
+    let mut group = c.benchmark_group("assert_run");
+    group.bench_function("looped", |b| {
+        b.iter_batched(
+            Segment::new,
+            |s| assert_looped(s, &values),
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("run", |b| {
+        b.iter_batched(
+            Segment::new,
+            |mut s| {
+                s.assert_run(0, &values).unwrap();
+                s
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+/// Asserting known cells, as done when re-verifying an execution.
+///
+/// Small constants such as zero and one go through the same comparison as any other value.
+fn assert_known(c: &mut Criterion) {
+    let mut group = c.benchmark_group("assert_known");
+    for (name, value) in [
+        ("zero", Felt::ZERO),
+        ("one", Felt::ONE),
+        ("large", Felt::from(u128::MAX)),
+    ] {
+        let segment = filled_with(value);
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || segment.clone(),
+                |s| assert_all(s, &value),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+/// Filling a segment one cell at a time, under each growth policy.
+fn growth(c: &mut Criterion) {
+    let mut group = c.benchmark_group("growth");
+    for (name, policy) in [
+        ("amortized", GrowthPolicy::Amortized),
+        ("double", GrowthPolicy::Double),
+        ("exact", GrowthPolicy::Exact),
+    ] {
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || {
+                    let mut segment = Segment::new();
+                    segment.set_growth_policy(policy);
+                    segment
+                },
+                fill,
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, assert_run, assert_known, growth);
+criterion_main!(benches);
@@ -0,0 +1,109 @@
+//! Benchmarks of the execution of single instructions.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use rayquaza_vm::cpu::Cpu;
+use rayquaza_vm::hint::NoopHintProcessor;
+use rayquaza_vm::instr::*;
+use rayquaza_vm::memory::{Pointer, Value, ValueRef};
+use rayquaza_vm::trace::NoopTrace;
+use rayquaza_vm::{CairoVM, RunOptions};
+use starknet_types_core::felt::Felt;
+
+/// The number of instructions executed by each run.
+const STEPS: usize = 1000;
+
+/// Returns a program incrementing the last cell written to the stack [`STEPS`] times.
+///
+/// The increment is read from `op1`, which is either an immediate value or `[fp - 1]`.
+fn counting_program(op1_source: Op1Source) -> Vec<Value> {
+    // [ap] = [ap - 1] + op1; ap++
+    let increment = InstructionBuilder::new()
+        .op_code(OpCode::AssertEq)
+        .op0_offset(-1)
+        .op1_source(op1_source)
+        .op1_offset(if op1_source == Op1Source::PC { 1 } else { -1 })
+        .result_logic(ResultLogic::Add)
+        .ap_update(ApUpdate::Increment)
+        .build();
+
+    let mut instruction = vec![Value::Scalar(Felt::from(increment.0))];
+    if op1_source == Op1Source::PC {
+        instruction.push(Value::Scalar(Felt::ONE));
+    }
+    instruction.repeat(STEPS)
+}
+
+/// Creates a virtual machine ready to run `program`, with the increment at `[fp - 1]` and a
+/// zero on the stack.
+fn counting_vm(program: &[Value]) -> CairoVM {
+    let mut vm = CairoVM::new(program, Vec::new()).unwrap();
+    let base = vm.ap();
+    vm.assert_eq(base, ValueRef::Scalar(&Felt::ONE)).unwrap();
+    vm.assert_eq(base.wrapping_add(1), ValueRef::Scalar(&Felt::ZERO))
+        .unwrap();
+    vm.set_cpu(Cpu {
+        pc: vm.pc(),
+        ap: base.wrapping_add(2),
+        fp: base.wrapping_add(1),
+    })
+    .unwrap();
+    vm
+}
+
+/// Runs `vm` until the end of the counting program.
+fn run(mut vm: CairoVM) -> CairoVM {
+    let end = Pointer {
+        segment: CairoVM::PROGRAM_SEGMENT,
+        offset: vm
+            .memory()
+            .segment(CairoVM::PROGRAM_SEGMENT)
+            .unwrap()
+            .size(),
+    };
+    vm.run(
+        end,
+        &RunOptions::default(),
+        &mut NoopHintProcessor,
+        &mut NoopTrace,
+    )
+    .unwrap();
+    vm
+}
+
+/// Executing a program, with and without a memory already holding its operands.
+fn step(c: &mut Criterion) {
+    let program = counting_program(Op1Source::PC);
+    let registers = counting_program(Op1Source::FP);
+
+    let mut group = c.benchmark_group("step");
+    group.bench_function("deduced", |b| {
+        b.iter_batched(|| counting_vm(&program), run, BatchSize::SmallInput)
+    });
+    // The same program, reading its increment from the stack rather than from an immediate
+    // as `step/deduced` does.
+    group.bench_function("registers", |b| {
+        b.iter_batched(|| counting_vm(&registers), run, BatchSize::SmallInput)
+    });
+
+    // The memory of a previous run holds every operand: only the fast path is taken.
+    for (name, program) in [
+        ("asserted", &program),
+        // Every operand is read from memory, with no immediate fetched along the instruction.
+        ("asserted_registers", &registers),
+    ] {
+        let fresh = counting_vm(program);
+        let initial = fresh.cpu().clone();
+        let done = run(fresh);
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || CairoVM::from_parts(done.memory().clone(), initial.clone()).unwrap(),
+                run,
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, step);
+criterion_main!(benches);
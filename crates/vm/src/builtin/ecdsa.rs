@@ -0,0 +1,205 @@
+//! Defines the [`Ecdsa`] builtin.
+
+use alloc::collections::BTreeMap;
+
+use starknet_types_core::felt::Felt;
+
+use crate::error::Error;
+use crate::memory::{Segment, Value, ValueRef};
+
+use super::{Builtin, BuiltinName, CannotDeduce};
+
+/// An ECDSA signature over the Stark curve.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Signature {
+    /// The `r` component of the signature.
+    pub r: Felt,
+    /// The `s` component of the signature.
+    pub s: Felt,
+}
+
+/// Verifies ECDSA signatures on behalf of the [`Ecdsa`] builtin.
+pub trait SignatureVerifier {
+    /// Returns whether `signature` is a valid signature of `message` by `public_key`.
+    fn verify(&self, public_key: &Felt, message: &Felt, signature: &Signature) -> bool;
+}
+
+/// The `ecdsa` builtin, asserting that messages have been signed by specific public keys.
+///
+/// # Instances
+///
+/// Each instance of the builtin is made of two cells: the public key (at offset `2k`),
+/// followed by the signed message (at offset `2k + 1`). The signatures themselves are not part
+/// of the memory: they are provided by the program (usually through a hint) using
+/// [`add_signature`](Self::add_signature), on the builtin returned by
+/// [`CairoVM::builtin_mut`](crate::CairoVM::builtin_mut).
+///
+/// No cell can be deduced by this builtin. Instead, every instance is verified once the
+/// program has finished running.
+#[derive(Debug, Default, Clone)]
+pub struct Ecdsa<V> {
+    /// The verifier used to check the signatures.
+    verifier: V,
+    /// The signature of each instance, indexed by instance.
+    signatures: BTreeMap<usize, Signature>,
+}
+
+impl<V> Ecdsa<V> {
+    /// The number of cells making up a single instance of the builtin.
+    pub const INSTANCE_SIZE: usize = 2;
+
+    /// Creates a new [`Ecdsa`] builtin verifying signatures with the provided verifier.
+    pub fn new(verifier: V) -> Self {
+        Self {
+            verifier,
+            signatures: BTreeMap::new(),
+        }
+    }
+
+    /// Registers the signature of the instance at index `index` (whose public key is located
+    /// at offset `2 * index` of the builtin segment).
+    ///
+    /// Any signature previously registered for that instance is replaced.
+    pub fn add_signature(&mut self, index: usize, r: Felt, s: Felt) {
+        self.signatures.insert(index, Signature { r, s });
+    }
+
+    /// Returns the signature registered for the instance at index `index`, if any.
+    #[inline]
+    pub fn signature(&self, index: usize) -> Option<&Signature> {
+        self.signatures.get(&index)
+    }
+}
+
+impl<V: SignatureVerifier + 'static> Builtin for Ecdsa<V> {
+    fn deduce(
+        &self,
+        _offset: usize,
+        _segment: &Segment,
+        _result: &mut Value,
+    ) -> Result<(), CannotDeduce> {
+        Err(CannotDeduce)
+    }
+
+    /// Verifies the signature of every complete instance of the segment.
+    ///
+    /// Instances whose public key or message is unknown are skipped. Instances holding a
+    /// pointer, lacking a registered signature, or whose signature is invalid are rejected.
+    fn finalize(&self, segment: &Segment) -> Result<(), Error> {
+        let instances = segment.highest_known_cell().div_ceil(Self::INSTANCE_SIZE);
+
+        for index in 0..instances {
+            let base = index * Self::INSTANCE_SIZE;
+
+            let (public_key, message) = match (segment.get(base), segment.get(base + 1)) {
+                (Some(ValueRef::Scalar(public_key)), Some(ValueRef::Scalar(message))) => {
+                    (public_key, message)
+                }
+                (Some(ValueRef::Pointer(_)), _) | (_, Some(ValueRef::Pointer(_))) => {
+                    return Err(Error::Builtin)
+                }
+                _ => continue,
+            };
+
+            let signature = self.signatures.get(&index).ok_or(Error::Builtin)?;
+            if !self.verifier.verify(public_key, message, signature) {
+                return Err(Error::Builtin);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> Option<BuiltinName> {
+        Some(BuiltinName::Ecdsa)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A verifier accepting the signatures whose `r` is the message and `s` the public key.
+    struct Echo;
+
+    impl SignatureVerifier for Echo {
+        fn verify(&self, public_key: &Felt, message: &Felt, signature: &Signature) -> bool {
+            signature.r == *message && signature.s == *public_key
+        }
+    }
+
+    /// A verifier checking signatures over the Stark curve.
+    struct StarkCurve;
+
+    impl SignatureVerifier for StarkCurve {
+        fn verify(&self, public_key: &Felt, message: &Felt, signature: &Signature) -> bool {
+            let convert = |felt: &Felt| {
+                starknet_crypto::FieldElement::from_bytes_be(&felt.to_bytes_be()).unwrap()
+            };
+            starknet_crypto::verify(
+                &convert(public_key),
+                &convert(message),
+                &convert(&signature.r),
+                &convert(&signature.s),
+            )
+            .unwrap_or(false)
+        }
+    }
+
+    /// Returns a segment holding the provided `(public_key, message)` instances.
+    fn instances(instances: &[(u64, u64)]) -> Segment {
+        let mut segment = Segment::new();
+        for (index, &(public_key, message)) in instances.iter().enumerate() {
+            let values = [
+                Value::Scalar(Felt::from(public_key)),
+                Value::Scalar(Felt::from(message)),
+            ];
+            segment.assert_run(index * 2, &values).unwrap();
+        }
+        segment
+    }
+
+    #[test]
+    fn valid_and_invalid_signatures() {
+        let segment = instances(&[(10, 20), (11, 21)]);
+
+        let mut ecdsa = Ecdsa::new(Echo);
+        ecdsa.add_signature(0, Felt::from(20), Felt::from(10));
+        ecdsa.add_signature(1, Felt::from(21), Felt::from(11));
+        assert!(ecdsa.finalize(&segment).is_ok());
+
+        // The second signature does not match its public key.
+        ecdsa.add_signature(1, Felt::from(21), Felt::from(10));
+        assert!(matches!(ecdsa.finalize(&segment), Err(Error::Builtin)));
+
+        // Every complete instance needs a signature.
+        let mut ecdsa = Ecdsa::new(Echo);
+        ecdsa.add_signature(0, Felt::from(20), Felt::from(10));
+        assert!(matches!(ecdsa.finalize(&segment), Err(Error::Builtin)));
+    }
+
+    #[test]
+    fn stark_curve_signature() {
+        let public_key =
+            Felt::from_hex("0x1ef15c18599971b7beced415a40f0c7deacfd9b0d1819e03d723d8bc943cfca")
+                .unwrap();
+        let r = Felt::from_hex("0x411494b501a98abd8262b0da1351e17899a0c4ef23dd2f96fec5ba847310b20")
+            .unwrap();
+        let s = Felt::from_hex("0x405c3191ab3883ef2b763af35bc5f5d15b3b4e99461d70e84c654a351a7c81b")
+            .unwrap();
+
+        let mut segment = Segment::new();
+        let values = [Value::Scalar(public_key), Value::Scalar(Felt::TWO)];
+        segment.assert_run(0, &values).unwrap();
+
+        let mut ecdsa = Ecdsa::new(StarkCurve);
+        ecdsa.add_signature(0, r, s);
+        assert!(ecdsa.finalize(&segment).is_ok());
+
+        // The same signature does not sign another message.
+        let mut segment = Segment::new();
+        let values = [Value::Scalar(public_key), Value::Scalar(Felt::THREE)];
+        segment.assert_run(0, &values).unwrap();
+        assert!(matches!(ecdsa.finalize(&segment), Err(Error::Builtin)));
+    }
+}
@@ -0,0 +1,159 @@
+//! Defines the [`Layout`] and [`BuiltinSet`] types, describing which builtins are available to
+//! a program and in which order.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::error::Error;
+
+use super::Builtin;
+
+/// The name of a standard builtin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BuiltinName {
+    /// The `output` builtin, used to write the public output of a program.
+    Output,
+    /// The `pedersen` builtin, computing Pedersen hashes.
+    Pedersen,
+    /// The `range_check` builtin, asserting that values are within `[0, 2^128)`.
+    RangeCheck,
+    /// The `ecdsa` builtin, verifying ECDSA signatures.
+    Ecdsa,
+    /// The `bitwise` builtin, computing bitwise operations on field elements.
+    Bitwise,
+    /// The `ec_op` builtin, computing elliptic curve operations.
+    EcOp,
+    /// The `keccak` builtin, computing Keccak hashes.
+    Keccak,
+    /// The `poseidon` builtin, computing Poseidon hashes.
+    Poseidon,
+    /// The `range_check96` builtin, asserting that values are within `[0, 2^96)`.
+    RangeCheck96,
+}
+
+impl BuiltinName {
+    /// Returns the name of the builtin, as used in Cairo programs.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Output => "output",
+            Self::Pedersen => "pedersen",
+            Self::RangeCheck => "range_check",
+            Self::Ecdsa => "ecdsa",
+            Self::Bitwise => "bitwise",
+            Self::EcOp => "ec_op",
+            Self::Keccak => "keccak",
+            Self::Poseidon => "poseidon",
+            Self::RangeCheck96 => "range_check96",
+        }
+    }
+}
+
+impl fmt::Display for BuiltinName {
+    #[inline(always)]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A named layout, bundling a fixed set of builtins in a fixed order.
+///
+/// Programs compiled for a specific layout expect their builtin segments to be allocated in
+/// the order defined by that layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Layout {
+    /// The `small` layout.
+    Small,
+    /// The `recursive` layout.
+    Recursive,
+    /// The `starknet` layout.
+    Starknet,
+}
+
+impl Layout {
+    /// Returns the [`Layout`] with the provided name, if any.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "small" => Some(Self::Small),
+            "recursive" => Some(Self::Recursive),
+            "starknet" => Some(Self::Starknet),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the layout.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Small => "small",
+            Self::Recursive => "recursive",
+            Self::Starknet => "starknet",
+        }
+    }
+
+    /// Returns the builtins included in the layout, in order.
+    pub fn builtins(self) -> &'static [BuiltinName] {
+        use BuiltinName::*;
+
+        match self {
+            Self::Small => &[Output, Pedersen, RangeCheck, Ecdsa],
+            Self::Recursive => &[Output, Pedersen, RangeCheck, Bitwise],
+            Self::Starknet => &[Output, Pedersen, RangeCheck, Ecdsa, Bitwise, EcOp, Poseidon],
+        }
+    }
+}
+
+/// Assembles the builtins of a [`Layout`] in the order expected by programs compiled for it.
+///
+/// The virtual machine does not provide an implementation for every standard builtin. The
+/// implementations are therefore supplied by the caller, while the [`BuiltinSet`] takes care
+/// of selecting and ordering them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BuiltinSet {
+    /// The layout of the set.
+    layout: Layout,
+}
+
+impl BuiltinSet {
+    /// Creates a new [`BuiltinSet`] for the provided [`Layout`].
+    #[inline(always)]
+    pub fn new(layout: Layout) -> Self {
+        Self { layout }
+    }
+
+    /// Creates a new [`BuiltinSet`] for the layout with the provided name, if any.
+    #[inline]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Layout::from_name(name).map(Self::new)
+    }
+
+    /// Returns the [`Layout`] of the set.
+    #[inline(always)]
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    /// Returns the names of the builtins of the set, in order.
+    #[inline(always)]
+    pub fn names(&self) -> &'static [BuiltinName] {
+        self.layout.builtins()
+    }
+
+    /// Builds the builtins of the set, in order.
+    ///
+    /// `provide` is called once for each builtin of the layout, in order, and must return its
+    /// implementation.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with [`Error::MissingBuiltin`] if `provide` returns `None` for one
+    /// of the builtins of the layout.
+    pub fn build<F>(&self, mut provide: F) -> Result<Vec<Box<dyn Builtin>>, Error>
+    where
+        F: FnMut(BuiltinName) -> Option<Box<dyn Builtin>>,
+    {
+        self.names()
+            .iter()
+            .map(|&name| provide(name).ok_or(Error::MissingBuiltin))
+            .collect()
+    }
+}
@@ -0,0 +1,136 @@
+//! Defines the [`Builtin`] trait responsible for executing built-in pre-defined functions.
+//!
+//! The field element library used by the virtual machine does not provide any signature
+//! scheme or hash function. The types relying on one, such as the [`Ecdsa`] builtin or
+//! [`OutputHash`], get it from the embedding through a trait ([`SignatureVerifier`],
+//! [`OutputHasher`]), which is usually implemented on top of a Starknet cryptography library.
+
+use core::any::Any;
+
+use crate::error::Error;
+use crate::memory::{Segment, Value};
+
+mod ecdsa;
+mod layout;
+mod output;
+mod output_hash;
+mod range_check;
+mod segment_arena;
+
+pub use self::ecdsa::*;
+pub use self::layout::*;
+pub use self::output::*;
+pub use self::output_hash::*;
+pub use self::range_check::*;
+pub use self::segment_arena::*;
+
+/// An error that occurs when a [`Builtin`] is not able to deduce the value of a memory cell
+/// from the given segment.
+#[derive(Debug, Clone, Copy)]
+pub struct CannotDeduce;
+
+impl From<CannotDeduce> for Error {
+    fn from(_value: CannotDeduce) -> Self {
+        Error::Builtin
+    }
+}
+
+/// A built that may be executed by the virtual machine.
+///
+/// Builtins are [`Any`] so that hints can retrieve them by type with
+/// [`CairoVM::builtin_mut`](crate::CairoVM::builtin_mut).
+pub trait Builtin: Any {
+    /// Attempts to deduce the value of a specific memory cell from the given segment.
+    ///
+    /// # Returns
+    ///
+    /// If the value could be successfully deduced, `Ok(_)` is returned and the value is written
+    /// to `result`.
+    ///
+    /// Otherwise, [`CannotDeduce`] is returned.
+    fn deduce(
+        &self,
+        offset: usize,
+        segment: &Segment,
+        result: &mut Value,
+    ) -> Result<(), CannotDeduce>;
+
+    /// Validates the content of the segment assigned to this builtin once the program has
+    /// finished running.
+    ///
+    /// This is where builtins enforce their global invariants (e.g. that every cell of a
+    /// range-check segment is within bounds), which cannot be checked while the segment is
+    /// still being written to.
+    ///
+    /// The default implementation does nothing.
+    fn finalize(&self, segment: &Segment) -> Result<(), Error> {
+        let _ = segment;
+        Ok(())
+    }
+
+    /// Returns the number of cells to reserve in the segment assigned to this builtin when
+    /// the virtual machine is created.
+    ///
+    /// Builtins whose number of instances is known in advance can use this to avoid growing
+    /// their segment repeatedly during execution. Only capacity is reserved: the cells remain
+    /// unknown, and writing them (for example with [`Segment::assert_run`]) won't need to
+    /// reallocate the segment as long as they fit in the reserved capacity.
+    ///
+    /// The default implementation returns `0`.
+    fn initial_segment_size(&self) -> usize {
+        0
+    }
+
+    /// Returns the cost charged to the [`ResourceTracker`] each time an operand located in the
+    /// segment of this builtin is deduced.
+    ///
+    /// The default implementation returns `0`.
+    ///
+    /// [`ResourceTracker`]: crate::resource::ResourceTracker
+    fn cost(&self) -> u64 {
+        0
+    }
+
+    /// Returns the name of the standard builtin implemented by this [`Builtin`], if any.
+    ///
+    /// This is used to find specific builtins among the ones registered in the virtual
+    /// machine, such as the `output` builtin for [`CairoVM::output`].
+    ///
+    /// The default implementation returns `None`.
+    ///
+    /// [`CairoVM::output`]: crate::CairoVM::output
+    fn name(&self) -> Option<BuiltinName> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet_types_core::felt::Felt;
+
+    use super::*;
+    use crate::memory::{Pointer, ValueRef};
+
+    /// A builtin that deduces nothing, and keeps the default validation.
+    struct Inert;
+
+    impl Builtin for Inert {
+        fn deduce(&self, _: usize, _: &Segment, _: &mut Value) -> Result<(), CannotDeduce> {
+            Err(CannotDeduce)
+        }
+    }
+
+    #[test]
+    fn finalize_accepts_any_segment_by_default() {
+        let pointer = Pointer {
+            segment: 0,
+            offset: 0,
+        };
+        let mut segment = Segment::new();
+        segment.set(0, ValueRef::Pointer(&pointer)).unwrap();
+        segment.set(2, ValueRef::Scalar(&Felt::ONE)).unwrap();
+
+        assert!(Inert.finalize(&Segment::new()).is_ok());
+        assert!(Inert.finalize(&segment).is_ok());
+    }
+}
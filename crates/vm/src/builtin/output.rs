@@ -0,0 +1,30 @@
+//! Defines the [`OutputBuiltin`] type.
+
+use crate::memory::{Segment, Value};
+
+use super::{Builtin, BuiltinName, CannotDeduce};
+
+/// The `output` builtin, holding the public output of a program.
+///
+/// Programs write their outputs to its segment as a dense array of scalars, which can be read
+/// back with [`CairoVM::output`](crate::CairoVM::output) once the program has finished
+/// running.
+///
+/// No cell can be deduced by this builtin.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct OutputBuiltin;
+
+impl Builtin for OutputBuiltin {
+    fn deduce(
+        &self,
+        _offset: usize,
+        _segment: &Segment,
+        _result: &mut Value,
+    ) -> Result<(), CannotDeduce> {
+        Err(CannotDeduce)
+    }
+
+    fn name(&self) -> Option<BuiltinName> {
+        Some(BuiltinName::Output)
+    }
+}
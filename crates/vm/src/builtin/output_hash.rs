@@ -0,0 +1,145 @@
+//! Defines the [`OutputHash`] type.
+
+use alloc::vec::Vec;
+
+use starknet_types_core::felt::Felt;
+
+use crate::error::Error;
+use crate::memory::Segment;
+
+/// A hash function over sequences of field elements, used to compute an [`OutputHash`].
+///
+/// Bootloaders use either Poseidon (`poseidon_hash_many`) or Pedersen (`compute_hash_on_elements`)
+/// depending on the version of the proof system.
+pub trait OutputHasher {
+    /// Hashes the provided sequence of field elements.
+    fn hash_many(&self, elements: &[Felt]) -> Felt;
+}
+
+/// The hash of the output segment of a program, as computed by bootloaders to commit to the
+/// outputs of the tasks they run.
+///
+/// The hash covers the number of outputs followed by the outputs themselves, so that two
+/// output segments only hash to the same value if they have the same length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OutputHash(pub Felt);
+
+impl OutputHash {
+    /// Computes the hash of the provided output segment.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with [`Error::Builtin`] if any cell of the output segment is a
+    /// pointer, or is unknown while cells after it are known.
+    pub fn compute<H>(hasher: &H, output: &Segment) -> Result<Self, Error>
+    where
+        H: ?Sized + OutputHasher,
+    {
+        let outputs = output.raw_felt_slice().ok_or(Error::Builtin)?;
+
+        let mut elements = Vec::with_capacity(outputs.len() + 1);
+        elements.push(Felt::from(outputs.len()));
+        elements.extend_from_slice(outputs);
+
+        Ok(Self(hasher.hash_many(&elements)))
+    }
+
+    /// Returns the hash as a field element.
+    #[inline(always)]
+    pub const fn value(&self) -> Felt {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::RefCell;
+
+    use super::*;
+    use crate::memory::{Pointer, ValueRef};
+
+    /// A hasher recording its input, and returning the number of hashed elements.
+    #[derive(Default)]
+    struct Recording(RefCell<Vec<Felt>>);
+
+    impl OutputHasher for Recording {
+        fn hash_many(&self, elements: &[Felt]) -> Felt {
+            self.0.borrow_mut().extend_from_slice(elements);
+            Felt::from(elements.len())
+        }
+    }
+
+    #[test]
+    fn length_prefixed_layout() {
+        let outputs = [Felt::from(7), Felt::from(8), Felt::from(9)];
+        let mut segment = Segment::new();
+        segment.assert_felt_run(0, &outputs).unwrap();
+
+        let hasher = Recording::default();
+        let hash = OutputHash::compute(&hasher, &segment).unwrap();
+
+        assert_eq!(hash.value(), Felt::from(4));
+        assert_eq!(
+            *hasher.0.borrow(),
+            [Felt::from(3), Felt::from(7), Felt::from(8), Felt::from(9)]
+        );
+    }
+
+    /// The Poseidon hash function used by recent bootloaders.
+    struct Poseidon;
+
+    impl OutputHasher for Poseidon {
+        fn hash_many(&self, elements: &[Felt]) -> Felt {
+            let elements: Vec<_> = elements
+                .iter()
+                .map(|e| starknet_crypto::FieldElement::from_bytes_be(&e.to_bytes_be()).unwrap())
+                .collect();
+            Felt::from_bytes_be(&starknet_crypto::poseidon_hash_many(&elements).to_bytes_be())
+        }
+    }
+
+    #[test]
+    fn poseidon_output_hash() {
+        let outputs = [Felt::from(7), Felt::from(8), Felt::from(9)];
+        let mut segment = Segment::new();
+        segment.assert_felt_run(0, &outputs).unwrap();
+
+        // `poseidon_hash_many([3, 7, 8, 9])`
+        let expected =
+            Felt::from_hex("0x7be3f147ed4ae58f5f730c4e1aee6baa665b4aad98a99dbfcb6a5d9ec719fbe")
+                .unwrap();
+        assert_eq!(
+            OutputHash::compute(&Poseidon, &segment).unwrap(),
+            OutputHash(expected)
+        );
+    }
+
+    #[test]
+    fn empty_output() {
+        let hasher = Recording::default();
+        OutputHash::compute(&hasher, &Segment::new()).unwrap();
+        assert_eq!(*hasher.0.borrow(), [Felt::ZERO]);
+    }
+
+    #[test]
+    fn rejects_pointers_and_gaps() {
+        let pointer = Pointer {
+            segment: 0,
+            offset: 0,
+        };
+        let mut with_pointer = Segment::new();
+        with_pointer.set(0, ValueRef::Pointer(&pointer)).unwrap();
+
+        let mut with_gap = Segment::new();
+        with_gap.set(1, ValueRef::Scalar(&Felt::ONE)).unwrap();
+
+        for segment in [with_pointer, with_gap] {
+            let hasher = Recording::default();
+            assert!(matches!(
+                OutputHash::compute(&hasher, &segment),
+                Err(Error::Builtin)
+            ));
+            assert!(hasher.0.borrow().is_empty());
+        }
+    }
+}
@@ -0,0 +1,162 @@
+//! Defines the [`RangeCheckBuiltin`] type.
+
+use starknet_types_core::felt::Felt;
+
+use crate::error::Error;
+use crate::memory::{Segment, Value, ValueRef};
+
+use super::{Builtin, BuiltinName, CannotDeduce};
+
+/// A range-check builtin, asserting that every cell of its segment holds a scalar within
+/// `[0, 2^bits)`.
+///
+/// Two variants are used by Cairo programs:
+///
+/// - The `range_check` builtin ([`RangeCheckBuiltin::new`]), checking values on 128 bits.
+///
+/// - The `range_check96` builtin ([`RangeCheckBuiltin::new_96`]), checking values on 96 bits.
+///
+/// This builtin never deduces any value: programs write the values they want to check to its
+/// segment, and the whole segment is validated once the program has finished running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RangeCheckBuiltin {
+    /// The number of bits a value checked by this builtin may have.
+    bits: u32,
+}
+
+impl RangeCheckBuiltin {
+    /// The number of bits a value checked by the `range_check` builtin may have.
+    pub const BITS: u32 = 128;
+
+    /// The number of bits a value checked by the `range_check96` builtin may have.
+    pub const BITS_96: u32 = 96;
+
+    /// Creates a new `range_check` builtin, checking that values are within `[0, 2^128)`.
+    #[inline(always)]
+    pub const fn new() -> Self {
+        Self { bits: Self::BITS }
+    }
+
+    /// Creates a new `range_check96` builtin, checking that values are within `[0, 2^96)`.
+    #[inline(always)]
+    pub const fn new_96() -> Self {
+        Self {
+            bits: Self::BITS_96,
+        }
+    }
+
+    /// Returns the number of bits a value checked by this builtin may have.
+    #[inline(always)]
+    pub const fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    /// Returns whether the provided field element is less than `2^bits`.
+    pub fn contains(&self, value: &Felt) -> bool {
+        bit_length(value) <= self.bits
+    }
+}
+
+impl Default for RangeCheckBuiltin {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Builtin for RangeCheckBuiltin {
+    fn deduce(
+        &self,
+        _offset: usize,
+        _segment: &Segment,
+        _result: &mut Value,
+    ) -> Result<(), CannotDeduce> {
+        Err(CannotDeduce)
+    }
+
+    /// Checks that every known cell of the segment is a scalar within `[0, 2^bits)`.
+    ///
+    /// Pointers are rejected as well: they have no meaningful numeric value until the memory
+    /// is relocated, so writing one to the segment indicates a malformed program.
+    fn finalize(&self, segment: &Segment) -> Result<(), Error> {
+        for offset in 0..segment.highest_known_cell() {
+            match segment.get(offset) {
+                None => (),
+                Some(ValueRef::Scalar(value)) if self.contains(value) => (),
+                Some(ValueRef::Scalar(_)) | Some(ValueRef::Pointer(_)) => {
+                    return Err(Error::Builtin)
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> Option<BuiltinName> {
+        match self.bits {
+            Self::BITS => Some(BuiltinName::RangeCheck),
+            Self::BITS_96 => Some(BuiltinName::RangeCheck96),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the number of bits required to represent the provided field element.
+fn bit_length(value: &Felt) -> u32 {
+    let bytes = value.to_bytes_be();
+
+    match bytes.iter().position(|&b| b != 0) {
+        Some(index) => (bytes.len() - index) as u32 * 8 - bytes[index].leading_zeros(),
+        None => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::Pointer;
+
+    #[test]
+    fn pointer_cells_are_rejected() {
+        let pointer = Pointer {
+            segment: 1,
+            offset: 0,
+        };
+        let segment = Segment::from_values(&[
+            Value::Scalar(Felt::ONE),
+            Value::Pointer(pointer),
+            Value::Scalar(Felt::TWO),
+        ]);
+
+        for builtin in [RangeCheckBuiltin::new(), RangeCheckBuiltin::new_96()] {
+            assert!(matches!(builtin.finalize(&segment), Err(Error::Builtin)));
+        }
+
+        // The same segment without the pointer is valid.
+        let segment = Segment::from_values(&[Value::Scalar(Felt::ONE), Value::Scalar(Felt::TWO)]);
+        assert!(RangeCheckBuiltin::new().finalize(&segment).is_ok());
+    }
+
+    #[test]
+    fn range_check96_bound() {
+        let bound = Felt::TWO.pow(96u32);
+        let largest = bound - Felt::ONE;
+
+        let range_check = RangeCheckBuiltin::new();
+        let range_check96 = RangeCheckBuiltin::new_96();
+        assert!(range_check96.contains(&largest));
+        assert!(!range_check96.contains(&bound));
+        assert!(range_check.contains(&bound));
+        assert!(!range_check.contains(&Felt::TWO.pow(128u32)));
+
+        let segment = Segment::from_values(&[Value::Scalar(largest), Value::Scalar(bound)]);
+        assert!(matches!(
+            range_check96.finalize(&segment),
+            Err(Error::Builtin)
+        ));
+        assert!(range_check.finalize(&segment).is_ok());
+
+        assert_eq!(range_check.name(), Some(BuiltinName::RangeCheck));
+        assert_eq!(range_check96.name(), Some(BuiltinName::RangeCheck96));
+    }
+}
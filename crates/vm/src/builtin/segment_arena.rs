@@ -0,0 +1,248 @@
+//! Defines the [`SegmentArena`] builtin.
+
+use num_traits::ToPrimitive;
+use starknet_types_core::felt::Felt;
+
+use crate::error::Error;
+use crate::memory::{Memory, Pointer, Segment, Value, ValueRef};
+
+use super::{Builtin, CannotDeduce};
+
+/// The `segment_arena` builtin, keeping track of the segments allocated dynamically by a
+/// program (for example, to back dictionaries).
+///
+/// # Instances
+///
+/// Each instance of the builtin is made of three cells:
+///
+/// 1. A pointer to the *info segment*, shared by all instances.
+/// 2. The number of segments allocated so far.
+/// 3. The number of segments that have been finalized (squashed) so far.
+///
+/// The info segment holds three cells for each allocated segment: its start, its end, and
+/// the index at which it was squashed.
+///
+/// # Segment allocation
+///
+/// The builtin does not allocate segments itself. Segments are allocated with
+/// [`Memory::allocate_segment`] (usually by the hints of the program), and the program is
+/// responsible for recording them in the info segment and writing the next instance. The
+/// info segment itself is allocated by [`SegmentArena::initialize`], before the program
+/// starts running.
+///
+/// # Deduction
+///
+/// The pointer to the info segment is the same for all instances: the first cell of an
+/// instance is deduced from the one of the previous instance. The other cells depend on the
+/// allocations performed by the program, and cannot be deduced. Instead, the consistency of
+/// the instances is validated once the program has finished running.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SegmentArena;
+
+impl SegmentArena {
+    /// The number of cells making up a single instance of the builtin.
+    pub const INSTANCE_SIZE: usize = 3;
+
+    /// Initializes the segment assigned to the builtin.
+    ///
+    /// This allocates the info segment, and writes the initial instance (with no allocated
+    /// segment) at the start of `segment`. The returned pointer, directly following the
+    /// initial instance, is the one that must be passed to the program.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with [`Error::UnallocatedSegment`] if `segment` has not been
+    /// allocated, and with [`Error::Contradiction`] if it has already been initialized
+    /// differently.
+    pub fn initialize(memory: &mut Memory, segment: usize) -> Result<Pointer, Error> {
+        if segment >= memory.num_segments() {
+            return Err(Error::UnallocatedSegment);
+        }
+
+        let infos = Pointer {
+            segment: memory.allocate_segment(),
+            offset: 0,
+        };
+        let base = Pointer { segment, offset: 0 };
+
+        memory.assert_eq(base, ValueRef::Pointer(&infos))?;
+        memory.assert_eq(base.wrapping_add(1), ValueRef::Scalar(&Felt::ZERO))?;
+        memory.assert_eq(base.wrapping_add(2), ValueRef::Scalar(&Felt::ZERO))?;
+
+        Ok(base.wrapping_add(Self::INSTANCE_SIZE))
+    }
+}
+
+impl Builtin for SegmentArena {
+    fn deduce(
+        &self,
+        offset: usize,
+        segment: &Segment,
+        result: &mut Value,
+    ) -> Result<(), CannotDeduce> {
+        if !offset.is_multiple_of(Self::INSTANCE_SIZE) || offset < Self::INSTANCE_SIZE {
+            return Err(CannotDeduce);
+        }
+
+        match segment.get(offset - Self::INSTANCE_SIZE) {
+            Some(ValueRef::Pointer(info)) => {
+                *result = Value::Pointer(*info);
+                Ok(())
+            }
+            _ => Err(CannotDeduce),
+        }
+    }
+
+    /// Checks that the instances written to the segment are consistent with each other.
+    ///
+    /// Every instance must refer to the same info segment, the number of allocated segments
+    /// can only grow, and no more segments than allocated can have been finalized.
+    fn finalize(&self, segment: &Segment) -> Result<(), Error> {
+        let mut infos = None;
+        let mut previous_allocated = 0;
+
+        let instances = segment.highest_known_cell() / Self::INSTANCE_SIZE;
+        for instance in 0..instances {
+            let base = instance * Self::INSTANCE_SIZE;
+
+            let (info, allocated, finalized) = match (
+                segment.get(base),
+                segment.get(base + 1),
+                segment.get(base + 2),
+            ) {
+                (
+                    Some(ValueRef::Pointer(info)),
+                    Some(ValueRef::Scalar(allocated)),
+                    Some(ValueRef::Scalar(finalized)),
+                ) => (*info, as_count(allocated)?, as_count(finalized)?),
+                _ => return Err(Error::Builtin),
+            };
+
+            if *infos.get_or_insert(info) != info
+                || allocated < previous_allocated
+                || finalized > allocated
+            {
+                return Err(Error::Builtin);
+            }
+
+            previous_allocated = allocated;
+        }
+
+        Ok(())
+    }
+}
+
+/// Converts a field element holding a number of segments into a `usize`.
+fn as_count(value: &Felt) -> Result<usize, Error> {
+    value.to_usize().ok_or(Error::Builtin)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a pointer to `offset` in `segment`.
+    fn ptr(segment: usize, offset: usize) -> Pointer {
+        Pointer { segment, offset }
+    }
+
+    /// Writes an instance of the builtin at index `instance` of `segment`.
+    fn write_instance(
+        segment: &mut Segment,
+        instance: usize,
+        info: Pointer,
+        allocated: u64,
+        finalized: u64,
+    ) {
+        let base = instance * SegmentArena::INSTANCE_SIZE;
+        let run = [
+            Value::Pointer(info),
+            Value::Scalar(Felt::from(allocated)),
+            Value::Scalar(Felt::from(finalized)),
+        ];
+        segment.assert_run(base, &run).unwrap();
+    }
+
+    #[test]
+    fn initialize() {
+        let mut memory = Memory::default();
+        let segment = memory.allocate_segment();
+
+        let start = SegmentArena::initialize(&mut memory, segment).unwrap();
+        assert_eq!(start, ptr(segment, 3));
+        assert_eq!(memory.num_segments(), 2);
+        assert_eq!(
+            memory.get(ptr(segment, 0)),
+            Some(ValueRef::Pointer(&ptr(1, 0)))
+        );
+        assert_eq!(
+            memory.get(ptr(segment, 1)),
+            Some(ValueRef::Scalar(&Felt::ZERO))
+        );
+        assert_eq!(
+            memory.get(ptr(segment, 2)),
+            Some(ValueRef::Scalar(&Felt::ZERO))
+        );
+
+        // A second initialization allocates another info segment.
+        assert!(matches!(
+            SegmentArena::initialize(&mut memory, segment),
+            Err(Error::Contradiction)
+        ));
+        assert!(matches!(
+            SegmentArena::initialize(&mut memory, 10),
+            Err(Error::UnallocatedSegment)
+        ));
+    }
+
+    #[test]
+    fn deduce_info_pointer() {
+        let info = ptr(7, 0);
+        let mut segment = Segment::new();
+        write_instance(&mut segment, 0, info, 0, 0);
+
+        let mut result = Value::Scalar(Felt::ZERO);
+        assert!(SegmentArena.deduce(3, &segment, &mut result).is_ok());
+        assert_eq!(result, Value::Pointer(info));
+
+        for offset in [0, 1, 2, 4, 5, 6] {
+            assert!(SegmentArena.deduce(offset, &segment, &mut result).is_err());
+        }
+    }
+
+    #[test]
+    fn finalize() {
+        let info = ptr(7, 0);
+        let mut segment = Segment::new();
+        write_instance(&mut segment, 0, info, 0, 0);
+        write_instance(&mut segment, 1, info, 2, 0);
+        write_instance(&mut segment, 2, info, 2, 2);
+        assert!(SegmentArena.finalize(&segment).is_ok());
+
+        let invalid = [
+            // The info segment changed.
+            (ptr(8, 0), 2, 2),
+            // Allocated segments can't be freed.
+            (info, 1, 1),
+            // More segments finalized than allocated.
+            (info, 2, 3),
+        ];
+        for (other, allocated, finalized) in invalid {
+            let mut segment = segment.clone();
+            write_instance(&mut segment, 3, other, allocated, finalized);
+            assert!(matches!(
+                SegmentArena.finalize(&segment),
+                Err(Error::Builtin)
+            ));
+        }
+
+        // Instances must be complete.
+        let mut segment = segment.clone();
+        segment.set(9, ValueRef::Pointer(&info)).unwrap();
+        segment.set(11, ValueRef::Scalar(&Felt::TWO)).unwrap();
+        assert!(matches!(
+            SegmentArena.finalize(&segment),
+            Err(Error::Builtin)
+        ));
+    }
+}
@@ -8,7 +8,7 @@ use crate::memory::Pointer;
 ///
 /// But itself, a [`Cpu`] is not enough to execute a Cairo program. In order to do anything
 /// useful, it has to be connected to a [`Memory`].
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Cpu {
     /// The Program Counter of the CPU, pointing to the next instruction to be fetched from
     /// working memory.
@@ -1,13 +1,23 @@
 //! Defines the [`Error`] type of the crate.
 
+use alloc::string::String;
+
 /// An error that might occur when executing a Cairo program.
+///
+/// New variants may be added in future versions. Code that only needs to tell errors apart
+/// by category should match on [`Error::kind`] instead.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum Error {
     /// The Cairo VM ran out of physical memory to represent the running program's memory.
     OutOfMemory,
-    /// The program counter is pointing to an unknown memory cell, or the memory cell was
-    /// known but contained a pointer rather than a scalar value.
+    /// The program counter is pointing to an unknown memory cell.
+    ///
+    /// This is also reported by absolute jumps to a segment that has not been allocated.
     ProgramCounterLost,
+    /// The program counter is pointing to a memory cell that contains a pointer rather than a
+    /// scalar value, which cannot be decoded as an instruction.
+    InstructionIsPointer,
     /// The value of the first operand of an instruction could not be deduced from a builtin,
     /// a hint, or a previous assertion.
     CantDeduceOp0,
@@ -19,6 +29,8 @@ pub enum Error {
     CantDeduceDst,
     /// A builtin failed to run correctly because of invalid input.
     Builtin,
+    /// A builtin required by a layout was not provided.
+    MissingBuiltin,
     /// Attempted to construct a poitner from a value that cannot be represented within a
     /// the physical memory of the Cairo VM.
     PointerTooLarge,
@@ -26,14 +38,62 @@ pub enum Error {
     InvalidPointerArithmetic,
     /// Attempted to divide by zero.
     DivideByZero,
+    /// Expected a scalar value, but found a pointer.
+    ExpectedScalar,
+    /// Expected a pointer, but found a scalar value.
+    ExpectedPointer,
     /// Tried to perform a pointer operation on two pointers that had different provenances.
     IncoherentProvenance,
+    /// A pointer referenced a segment that has not been allocated in the memory.
+    UnallocatedSegment,
+    /// The base addresses of a relocated memory were not sorted, or referenced addresses
+    /// outside of the relocated memory.
+    InvalidRelocation,
+    /// A cell marked as part of the public memory held a pointer, where only scalars are
+    /// expected.
+    ///
+    /// This is also reported when marking a cell that is still unknown.
+    InvalidPublicMemory,
+    /// A trace record was not consistent with the instruction it executed.
+    InvalidTraceRecord,
+    /// Attempted to read more values than available from a serialized input.
+    UnexpectedEndOfInput,
     /// Attempted to jump to a scalar value with no associated provenance.
     InvalidAbsoluteJump,
     /// Attempted to jump to a pointer value with associated provenance.
     InvalidRelativeJump,
-    /// Attempted to return to a scalar value with no associated provenance.
+    /// Attempted to return from a malformed frame.
+    ///
+    /// This happens when the **Frame Pointer** is too close to the start of its segment to
+    /// hold a frame, when the previous frame pointer (`[fp - 2]`) or the return address
+    /// (`[fp - 1]`) is unknown, or when the previous frame pointer is a scalar value with no
+    /// associated provenance or references a segment that has not been allocated.
     InvalidReturn,
+    /// The program executed more steps than allowed by [`RunOptions::max_steps`].
+    ///
+    /// [`RunOptions::max_steps`]: crate::RunOptions::max_steps
+    StepLimitReached,
+    /// The program consumed more resources than allowed by the [`ResourceTracker`] it was run
+    /// with.
+    ///
+    /// [`ResourceTracker`]: crate::resource::ResourceTracker
+    OutOfGas,
+    /// The program kept executing the same instruction without modifying its registers, and
+    /// will never reach the end of its execution.
+    ///
+    /// This is only detected when [`RunOptions::loop_detection`] is set.
+    ///
+    /// [`RunOptions::loop_detection`]: crate::RunOptions::loop_detection
+    InfiniteLoop,
+    /// An assertion provided to [`CairoVM::run_with_assertions`] failed, with the provided
+    /// diagnostic.
+    ///
+    /// [`CairoVM::run_with_assertions`]: crate::CairoVM::run_with_assertions
+    AssertionFailed(String),
+    /// Attempted to undo a step with an [`UndoLog`] that did not record any.
+    ///
+    /// [`UndoLog`]: crate::trace::UndoLog
+    NothingToUndo,
 
     /// The value of one of the memory cells contradicted a previous assertion on that same
     /// memory cell.
@@ -54,9 +114,14 @@ pub enum Error {
     /// The update logic of the **Program Counter** of an instruction was invalid.
     UndefinedPcUpdate,
     /// The update logic of the **Allocation Pointer** of an instruction was invalid.
+    ///
+    /// This is also reported by `ap += res` when the result is a pointer.
     UndefinedApUpdate,
     /// The OP code of an instruction was invalid.
     UndefinedOpCode,
+    /// The immediate value of an instruction (the cell following it) was a pointer, where
+    /// only scalar values are expected.
+    InvalidImmediate,
     /// In a `Call` instruction, the only allowed `ApUpdate` value is `None`.
     UndefinedApUpdateInCall,
     /// A conditional jump was used with invalid instruction values:
@@ -66,3 +131,280 @@ pub enum Error {
     /// 3. The update logic of the **Allocation Pointer** was not `AddResult`.
     UndefinedConditionalJump,
 }
+
+/// The category of an [`Error`], as returned by [`Error::kind`].
+///
+/// Unlike [`Error`], this enumeration is not expected to grow, and can be matched exhaustively.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// An instruction could not be fetched or decoded.
+    Decode,
+    /// The memory could not be allocated, accessed, relocated or deserialized.
+    Memory,
+    /// An arithmetic operation was applied to invalid operands.
+    Arithmetic,
+    /// The value of a memory cell could not be deduced, or contradicted a previous assertion.
+    Deduction,
+    /// The registers could not be updated, or the execution was interrupted.
+    ControlFlow,
+}
+
+impl Error {
+    /// Returns the category of the error.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rayquaza_vm::error::{Error, ErrorKind};
+    ///
+    /// assert_eq!(Error::UndefinedOpCode.kind(), ErrorKind::Decode);
+    /// assert_eq!(Error::CantDeduceOp1.kind(), ErrorKind::Deduction);
+    /// ```
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::InstructionIsPointer
+            | Self::UndefinedInstruction
+            | Self::UndefinedOp1Source
+            | Self::UndefinedResultLogic
+            | Self::UndefinedPcUpdate
+            | Self::UndefinedApUpdate
+            | Self::UndefinedOpCode
+            | Self::InvalidImmediate
+            | Self::UndefinedApUpdateInCall
+            | Self::UndefinedConditionalJump => ErrorKind::Decode,
+            Self::OutOfMemory
+            | Self::UnallocatedSegment
+            | Self::InvalidRelocation
+            | Self::InvalidPublicMemory
+            | Self::UnexpectedEndOfInput => ErrorKind::Memory,
+            Self::PointerTooLarge
+            | Self::InvalidPointerArithmetic
+            | Self::DivideByZero
+            | Self::ExpectedScalar
+            | Self::ExpectedPointer
+            | Self::IncoherentProvenance => ErrorKind::Arithmetic,
+            Self::CantDeduceOp0
+            | Self::CantDeduceOp1
+            | Self::CantDeduceDst
+            | Self::Builtin
+            | Self::MissingBuiltin
+            | Self::Contradiction
+            | Self::AssertionFailed(_)
+            | Self::InvalidTraceRecord => ErrorKind::Deduction,
+            Self::ProgramCounterLost
+            | Self::InvalidAbsoluteJump
+            | Self::InvalidRelativeJump
+            | Self::InvalidReturn
+            | Self::StepLimitReached
+            | Self::OutOfGas
+            | Self::InfiniteLoop
+            | Self::NothingToUndo => ErrorKind::ControlFlow,
+        }
+    }
+
+    /// Returns whether the error is fatal to the execution of the program.
+    ///
+    /// Fatal errors (such as the `Undefined*` decoding errors or a
+    /// [`Contradiction`](Error::Contradiction)) indicate that the program is malformed or has
+    /// violated one of its assertions, and execution should be aborted.
+    ///
+    /// Other errors are potentially recoverable: the `CantDeduce*` variants only indicate that
+    /// the virtual machine lacked the information required to execute the current step. An
+    /// embedding able to supply that information (e.g. by running a hint) may write the missing
+    /// memory cells and retry the step.
+    pub fn is_fatal(&self) -> bool {
+        !matches!(
+            self,
+            Self::CantDeduceOp0 | Self::CantDeduceOp1 | Self::CantDeduceDst
+        )
+    }
+
+    /// Returns a stable code identifying the variant of the error, such as `"E_CONTRADICTION"`.
+    ///
+    /// Unlike the debug representation of the error, codes are guaranteed not to change
+    /// between versions, and are suitable for tools or test snapshots to key off.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::OutOfMemory => "E_OUT_OF_MEMORY",
+            Self::ProgramCounterLost => "E_PROGRAM_COUNTER_LOST",
+            Self::InstructionIsPointer => "E_INSTRUCTION_IS_POINTER",
+            Self::CantDeduceOp0 => "E_CANT_DEDUCE_OP0",
+            Self::CantDeduceOp1 => "E_CANT_DEDUCE_OP1",
+            Self::CantDeduceDst => "E_CANT_DEDUCE_DST",
+            Self::Builtin => "E_BUILTIN",
+            Self::MissingBuiltin => "E_MISSING_BUILTIN",
+            Self::PointerTooLarge => "E_POINTER_TOO_LARGE",
+            Self::InvalidPointerArithmetic => "E_INVALID_POINTER_ARITHMETIC",
+            Self::DivideByZero => "E_DIVIDE_BY_ZERO",
+            Self::ExpectedScalar => "E_EXPECTED_SCALAR",
+            Self::ExpectedPointer => "E_EXPECTED_POINTER",
+            Self::IncoherentProvenance => "E_INCOHERENT_PROVENANCE",
+            Self::UnallocatedSegment => "E_UNALLOCATED_SEGMENT",
+            Self::InvalidRelocation => "E_INVALID_RELOCATION",
+            Self::InvalidPublicMemory => "E_INVALID_PUBLIC_MEMORY",
+            Self::InvalidTraceRecord => "E_INVALID_TRACE_RECORD",
+            Self::UnexpectedEndOfInput => "E_UNEXPECTED_END_OF_INPUT",
+            Self::InvalidAbsoluteJump => "E_INVALID_ABSOLUTE_JUMP",
+            Self::InvalidRelativeJump => "E_INVALID_RELATIVE_JUMP",
+            Self::InvalidReturn => "E_INVALID_RETURN",
+            Self::StepLimitReached => "E_STEP_LIMIT_REACHED",
+            Self::OutOfGas => "E_OUT_OF_GAS",
+            Self::InfiniteLoop => "E_INFINITE_LOOP",
+            Self::AssertionFailed(_) => "E_ASSERTION_FAILED",
+            Self::NothingToUndo => "E_NOTHING_TO_UNDO",
+            Self::Contradiction => "E_CONTRADICTION",
+            Self::UndefinedInstruction => "E_UNDEFINED_INSTRUCTION",
+            Self::UndefinedOp1Source => "E_UNDEFINED_OP1_SOURCE",
+            Self::UndefinedResultLogic => "E_UNDEFINED_RESULT_LOGIC",
+            Self::UndefinedPcUpdate => "E_UNDEFINED_PC_UPDATE",
+            Self::UndefinedApUpdate => "E_UNDEFINED_AP_UPDATE",
+            Self::UndefinedOpCode => "E_UNDEFINED_OP_CODE",
+            Self::InvalidImmediate => "E_INVALID_IMMEDIATE",
+            Self::UndefinedApUpdateInCall => "E_UNDEFINED_AP_UPDATE_IN_CALL",
+            Self::UndefinedConditionalJump => "E_UNDEFINED_CONDITIONAL_JUMP",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::collections::BTreeSet;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    /// Returns an instance of every variant of [`Error`].
+    fn every_variant() -> Vec<Error> {
+        let errors = vec![
+            Error::OutOfMemory,
+            Error::ProgramCounterLost,
+            Error::InstructionIsPointer,
+            Error::CantDeduceOp0,
+            Error::CantDeduceOp1,
+            Error::CantDeduceDst,
+            Error::Builtin,
+            Error::MissingBuiltin,
+            Error::PointerTooLarge,
+            Error::InvalidPointerArithmetic,
+            Error::DivideByZero,
+            Error::ExpectedScalar,
+            Error::ExpectedPointer,
+            Error::IncoherentProvenance,
+            Error::UnallocatedSegment,
+            Error::InvalidRelocation,
+            Error::InvalidPublicMemory,
+            Error::InvalidTraceRecord,
+            Error::UnexpectedEndOfInput,
+            Error::InvalidAbsoluteJump,
+            Error::InvalidRelativeJump,
+            Error::InvalidReturn,
+            Error::StepLimitReached,
+            Error::OutOfGas,
+            Error::InfiniteLoop,
+            Error::AssertionFailed(String::new()),
+            Error::NothingToUndo,
+            Error::Contradiction,
+            Error::UndefinedInstruction,
+            Error::UndefinedOp1Source,
+            Error::UndefinedResultLogic,
+            Error::UndefinedPcUpdate,
+            Error::UndefinedApUpdate,
+            Error::UndefinedOpCode,
+            Error::InvalidImmediate,
+            Error::UndefinedApUpdateInCall,
+            Error::UndefinedConditionalJump,
+        ];
+
+        // Fails to compile when a variant is added without being listed above.
+        for error in &errors {
+            match error {
+                Error::OutOfMemory
+                | Error::ProgramCounterLost
+                | Error::InstructionIsPointer
+                | Error::CantDeduceOp0
+                | Error::CantDeduceOp1
+                | Error::CantDeduceDst
+                | Error::Builtin
+                | Error::MissingBuiltin
+                | Error::PointerTooLarge
+                | Error::InvalidPointerArithmetic
+                | Error::DivideByZero
+                | Error::ExpectedScalar
+                | Error::ExpectedPointer
+                | Error::IncoherentProvenance
+                | Error::UnallocatedSegment
+                | Error::InvalidRelocation
+                | Error::InvalidPublicMemory
+                | Error::InvalidTraceRecord
+                | Error::UnexpectedEndOfInput
+                | Error::InvalidAbsoluteJump
+                | Error::InvalidRelativeJump
+                | Error::InvalidReturn
+                | Error::StepLimitReached
+                | Error::OutOfGas
+                | Error::InfiniteLoop
+                | Error::AssertionFailed(_)
+                | Error::NothingToUndo
+                | Error::Contradiction
+                | Error::UndefinedInstruction
+                | Error::UndefinedOp1Source
+                | Error::UndefinedResultLogic
+                | Error::UndefinedPcUpdate
+                | Error::UndefinedApUpdate
+                | Error::UndefinedOpCode
+                | Error::InvalidImmediate
+                | Error::UndefinedApUpdateInCall
+                | Error::UndefinedConditionalJump => (),
+            }
+        }
+
+        errors
+    }
+
+    #[test]
+    fn codes_are_unique() {
+        let errors = every_variant();
+        let codes: BTreeSet<_> = errors.iter().map(Error::code).collect();
+        assert_eq!(codes.len(), errors.len());
+        assert!(codes.iter().all(|code| code.starts_with("E_")));
+    }
+
+    #[test]
+    fn kind_of_every_variant() {
+        for error in every_variant() {
+            let expected = match error.code() {
+                "E_INSTRUCTION_IS_POINTER" | "E_INVALID_IMMEDIATE" => ErrorKind::Decode,
+                code if code.starts_with("E_UNDEFINED_") => ErrorKind::Decode,
+                "E_OUT_OF_MEMORY"
+                | "E_UNALLOCATED_SEGMENT"
+                | "E_INVALID_RELOCATION"
+                | "E_INVALID_PUBLIC_MEMORY"
+                | "E_UNEXPECTED_END_OF_INPUT" => ErrorKind::Memory,
+                "E_POINTER_TOO_LARGE"
+                | "E_INVALID_POINTER_ARITHMETIC"
+                | "E_DIVIDE_BY_ZERO"
+                | "E_EXPECTED_SCALAR"
+                | "E_EXPECTED_POINTER"
+                | "E_INCOHERENT_PROVENANCE" => ErrorKind::Arithmetic,
+                code if code.starts_with("E_CANT_DEDUCE_") => ErrorKind::Deduction,
+                "E_BUILTIN"
+                | "E_MISSING_BUILTIN"
+                | "E_CONTRADICTION"
+                | "E_ASSERTION_FAILED"
+                | "E_INVALID_TRACE_RECORD" => ErrorKind::Deduction,
+                "E_PROGRAM_COUNTER_LOST"
+                | "E_INVALID_ABSOLUTE_JUMP"
+                | "E_INVALID_RELATIVE_JUMP"
+                | "E_INVALID_RETURN"
+                | "E_STEP_LIMIT_REACHED"
+                | "E_OUT_OF_GAS"
+                | "E_INFINITE_LOOP"
+                | "E_NOTHING_TO_UNDO" => ErrorKind::ControlFlow,
+                code => panic!("no expected kind for `{code}`"),
+            };
+
+            assert_eq!(error.kind(), expected, "{error:?}");
+        }
+    }
+}
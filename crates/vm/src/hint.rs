@@ -0,0 +1,120 @@
+//! Defines the [`HintProcessor`] trait, responsible for running non-deterministic hints.
+//!
+//! Real Cairo programs rely on hints to fill in memory cells that cannot be deduced by the
+//! virtual machine itself (neither from the instructions, nor from builtins). A hint processor
+//! is given the opportunity to write those cells before each step.
+//!
+//! # Example
+//!
+//! A hint processor that writes a constant to `[ap]` right before the instruction at a
+//! specific address is executed:
+//!
+//! ```
+//! use rayquaza_vm::error::Error;
+//! use rayquaza_vm::hint::HintProcessor;
+//! use rayquaza_vm::memory::{Pointer, Value};
+//! use rayquaza_vm::CairoVM;
+//! use starknet_types_core::felt::Felt;
+//!
+//! struct FillAp {
+//!     target: Pointer,
+//!     value: Felt,
+//! }
+//!
+//! impl HintProcessor for FillAp {
+//!     fn execute(&mut self, vm: &mut CairoVM, pc: Pointer) -> Result<(), Error> {
+//!         if pc == self.target {
+//!             let ap = vm.cpu().ap;
+//!             vm.assert_eq(ap, Value::Scalar(self.value).as_ref())?;
+//!         }
+//!         Ok(())
+//!     }
+//! }
+//! ```
+
+use alloc::collections::BTreeMap;
+
+use crate::error::Error;
+use crate::memory::Pointer;
+use crate::CairoVM;
+
+/// Runs the hints associated with the instructions of a Cairo program.
+pub trait HintProcessor {
+    /// Executes the hints associated with the instruction at `pc`, if any.
+    ///
+    /// This function is called at the beginning of each step, before the virtual machine
+    /// attempts to deduce the operands of the instruction. Hints are expected to write the
+    /// memory cells that could not be deduced otherwise, typically using
+    /// [`CairoVM::assert_eq`].
+    fn execute(&mut self, vm: &mut CairoVM, pc: Pointer) -> Result<(), Error>;
+
+    /// Returns the number of hints associated with the instruction at `pc`.
+    ///
+    /// This is only used to gather statistics about the hints run by the virtual machine (see
+    /// [`CairoVM::hint_stats`]), and does not change the way [`execute`](Self::execute) is
+    /// called.
+    ///
+    /// The default implementation returns `0`.
+    fn hint_count(&self, pc: Pointer) -> usize {
+        let _ = pc;
+        0
+    }
+}
+
+/// Statistics about the hints run by a [`CairoVM`], as returned by [`CairoVM::hint_stats`].
+///
+/// Hints are identified by the address of the instruction they are associated with. The
+/// number of hints run at each address is reported by [`HintProcessor::hint_count`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct HintStats {
+    /// The total number of hints run.
+    total: usize,
+    /// The number of hints run at each address, keyed by `(segment, offset)`.
+    ///
+    /// Pointers are not ordered across segments, hence the tuple.
+    per_pc: BTreeMap<(usize, usize), usize>,
+}
+
+impl HintStats {
+    /// Returns the total number of hints run.
+    #[inline(always)]
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// Returns the number of hints run at the instruction at `pc`.
+    #[inline]
+    pub fn count_at(&self, pc: Pointer) -> usize {
+        self.per_pc
+            .get(&(pc.segment, pc.offset))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Returns an iterator over the addresses at which hints were run, along with the number
+    /// of hints run there.
+    ///
+    /// Addresses are sorted by segment, then by offset.
+    pub fn iter(&self) -> impl Iterator<Item = (Pointer, usize)> + '_ {
+        self.per_pc
+            .iter()
+            .map(|(&(segment, offset), &count)| (Pointer { segment, offset }, count))
+    }
+
+    /// Records that `count` hints were run at the instruction at `pc`.
+    pub(crate) fn record(&mut self, pc: Pointer, count: usize) {
+        self.total = self.total.saturating_add(count);
+        let entry = self.per_pc.entry((pc.segment, pc.offset)).or_insert(0);
+        *entry = entry.saturating_add(count);
+    }
+}
+
+/// An implementation of [`HintProcessor`] that does nothing.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoopHintProcessor;
+impl HintProcessor for NoopHintProcessor {
+    #[inline(always)]
+    fn execute(&mut self, _vm: &mut CairoVM, _pc: Pointer) -> Result<(), Error> {
+        Ok(())
+    }
+}
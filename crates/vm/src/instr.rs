@@ -1,9 +1,13 @@
 //! Defines the [`Instruction`] type, responsible for representing a single Cairo bytecode
 //! instruction, eventually including immediate values.
 
-use std::fmt;
+use core::fmt;
+
+use num_traits::ToPrimitive;
+use starknet_types_core::felt::Felt;
 
 use crate::error::Error;
+use crate::memory::Pointer;
 
 /// A register the destination part of an instruction can be relative to.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -15,6 +19,15 @@ pub enum DstRegister {
     FP = 1,
 }
 
+impl fmt::Display for DstRegister {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AP => f.write_str("ap"),
+            Self::FP => f.write_str("fp"),
+        }
+    }
+}
+
 /// A register the first operand of an instruction can be relative to.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(u8)]
@@ -25,11 +38,20 @@ pub enum Op0Register {
     FP = 1,
 }
 
+impl fmt::Display for Op0Register {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AP => f.write_str("ap"),
+            Self::FP => f.write_str("fp"),
+        }
+    }
+}
+
 /// A register/object the second operand of an instruction can be relative to.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(u8)]
 pub enum Op1Source {
-    /// The address resulting from the first operand of the instruction.
+    /// The value of the first operand of the instruction, which must be a pointer.
     Op0 = 0,
     /// The **Program Counter**.
     PC = 1,
@@ -64,6 +86,10 @@ pub enum PcUpdate {
     /// If the destination part of the instruction is zero, then the **Program Counter** is
     /// simply updated according to the [`PcUpdate::Regular`] update rule. Otherwise, the
     /// second part of the instruction is added to it.
+    ///
+    /// A destination holding a pointer is never zero, meaning that the jump is always taken.
+    /// This matches the reference implementation, where programs commonly test whether a
+    /// pointer is null by comparing it against zero.
     ConditionalJump = 4,
 }
 
@@ -74,8 +100,11 @@ pub enum ApUpdate {
     /// The **Allocation Pointer** remains unchanged.
     None = 0,
     /// The result of the instruction is added to the **Allocation Pointer**.
+    ///
+    /// The result must be a scalar, which may be negative (`ap += -3`).
     AddResult = 1,
-    /// The **Allocation Pointer** is incremented by one.
+    /// The **Allocation Pointer** is incremented by one, independently of the result of the
+    /// instruction.
     Increment = 2,
 }
 
@@ -97,7 +126,13 @@ pub enum OpCode {
 ///
 /// This contains most of the information required to execute the instruction, but some
 /// things might be missing, such as an eventual immediate value.
-#[derive(Clone, Copy)]
+///
+/// # Display
+///
+/// The [`Display`](fmt::Display) implementation of this type renders the instruction as a line
+/// of Cairo assembly, such as `[ap + 1] = [fp - 2] * [ap]; ap++`. Fields that fail to decode are
+/// rendered as placeholders (e.g. `<invalid op_code>`) rather than causing an error.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Instruction(pub u64);
 
 impl Instruction {
@@ -197,6 +232,50 @@ impl Instruction {
         }
     }
 
+    /// Returns the size of the instruction in memory cells.
+    ///
+    /// Instructions that read their second operand relative to the **Program Counter** carry
+    /// an immediate value in the cell directly following them, making them two cells long.
+    /// Other instructions only take a single cell.
+    #[inline(always)]
+    pub fn size(&self) -> Result<usize, Error> {
+        match self.op1_source()? {
+            Op1Source::PC => Ok(2),
+            _ => Ok(1),
+        }
+    }
+
+    /// Decodes and validates all the fields of the instruction at once.
+    ///
+    /// Unlike the individual accessors, the returned [`DecodedInstruction`] can be matched
+    /// against as a single value.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with the error of the first field that cannot be decoded. See
+    /// [`DecodedInstruction::new`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rayquaza_vm::instr::{DecodedInstruction, Instruction, OpCode, PcUpdate};
+    ///
+    /// let instruction = Instruction(0x208b_7fff_7fff_7ffe);
+    ///
+    /// let kind = match instruction.decode().unwrap() {
+    ///     DecodedInstruction { op_code: OpCode::Ret, .. } => "ret",
+    ///     DecodedInstruction { op_code: OpCode::Call, .. } => "call",
+    ///     DecodedInstruction { pc_update: PcUpdate::Regular, .. } => "regular",
+    ///     _ => "jump",
+    /// };
+    ///
+    /// assert_eq!(kind, "ret");
+    /// ```
+    #[inline]
+    pub fn decode(&self) -> Result<DecodedInstruction, Error> {
+        DecodedInstruction::new(*self)
+    }
+
     /// Returns whether the last bit of the instruction representation is set or not.
     ///
     /// Normally, a properly 0 instruction should have this bit set to zero.
@@ -204,6 +283,251 @@ impl Instruction {
     pub fn is_last_bit_set(&self) -> bool {
         self.0 & 0x8000_0000_0000_0000 != 0
     }
+
+    /// Interprets a memory cell holding the provided field element as an instruction.
+    ///
+    /// The instruction is not decoded in any way, but the function makes sure that it fits in
+    /// 64 bits and that its [last bit](Self::is_last_bit_set) is not set.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with [`Error::UndefinedInstruction`] if the field element cannot
+    /// represent an instruction.
+    pub fn try_from_felt(word: &Felt) -> Result<Self, Error> {
+        let instruction = Self(word.to_u64().ok_or(Error::UndefinedInstruction)?);
+
+        if instruction.is_last_bit_set() {
+            return Err(Error::UndefinedInstruction);
+        }
+
+        Ok(instruction)
+    }
+}
+
+/// An [`Instruction`] whose fields have all been decoded and validated.
+///
+/// Accessing the fields of an [`Instruction`] requires masking its raw representation again
+/// on every access, and validating the enumerated fields each time. Executing an instruction
+/// reads most of its fields several times, so the virtual machine decodes them once when the
+/// instruction is fetched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DecodedInstruction {
+    /// The offset applied to the destination part of the instruction.
+    pub dst_offset: i16,
+    /// The offset applied to the first operand of the instruction.
+    pub op0_offset: i16,
+    /// The offset applied to the second operand of the instruction.
+    pub op1_offset: i16,
+    /// The register the destination part of the instruction is relative to.
+    pub dst_register: DstRegister,
+    /// The register the first operand of the instruction is relative to.
+    pub op0_register: Op0Register,
+    /// The source the second operand of the instruction is relative to.
+    pub op1_source: Op1Source,
+    /// The logic used to compute the result of the instruction.
+    pub result_logic: ResultLogic,
+    /// The update rule applied to the **Program Counter**.
+    pub pc_update: PcUpdate,
+    /// The update rule applied to the **Allocation Pointer**.
+    pub ap_update: ApUpdate,
+    /// The OP code of the instruction.
+    pub op_code: OpCode,
+}
+
+impl DecodedInstruction {
+    /// Decodes and validates all the fields of the provided [`Instruction`].
+    ///
+    /// # Errors
+    ///
+    /// This function fails with the error of the first field that cannot be decoded, in the
+    /// following order: op1 source, result logic, pc update, ap update, op code.
+    pub fn new(instruction: Instruction) -> Result<Self, Error> {
+        Ok(Self {
+            dst_offset: instruction.dst_offset(),
+            op0_offset: instruction.op0_offset(),
+            op1_offset: instruction.op1_offset(),
+            dst_register: instruction.dst_register(),
+            op0_register: instruction.op0_register(),
+            op1_source: instruction.op1_source()?,
+            result_logic: instruction.result_logic()?,
+            pc_update: instruction.pc_update()?,
+            ap_update: instruction.ap_update()?,
+            op_code: instruction.op_code()?,
+        })
+    }
+
+    /// Returns the size of the instruction in memory cells.
+    ///
+    /// See [`Instruction::size`].
+    #[inline(always)]
+    pub fn size(&self) -> usize {
+        match self.op1_source {
+            Op1Source::PC => 2,
+            _ => 1,
+        }
+    }
+
+    /// Encodes the fields back into an [`Instruction`].
+    ///
+    /// This is the inverse of [`DecodedInstruction::new`]. The resulting instruction does not
+    /// necessarily define valid Cairo semantics (e.g. a `call` updating `ap`), but it always
+    /// decodes back to the same fields.
+    pub fn encode(&self) -> Instruction {
+        let word = self.dst_offset as u16 as u64
+            | (self.op0_offset as u16 as u64) << 16
+            | (self.op1_offset as u16 as u64) << 32
+            | (self.dst_register as u64) << 48
+            | (self.op0_register as u64) << 49
+            | (self.op1_source as u64) << 50
+            | (self.result_logic as u64) << 53
+            | (self.pc_update as u64) << 55
+            | (self.ap_update as u64) << 58
+            | (self.op_code as u64) << 60;
+
+        Instruction(word)
+    }
+}
+
+/// Crafts an [`Instruction`] field by field.
+///
+/// Every field starts zeroed: the operands are relative to `ap` with no offset, the second
+/// operand is read through the first one, and the instruction has no op-code and no register
+/// update. This is mostly useful to write instructions by hand in tests and tools, without
+/// having to remember the layout of the instruction word.
+///
+/// This type is only available with the `testing` feature.
+///
+/// # Example
+///
+/// ```
+/// use rayquaza_vm::instr::{InstructionBuilder, Op1Source, OpCode, PcUpdate};
+///
+/// // call rel [pc + 1]
+/// let instruction = InstructionBuilder::new()
+///     .op_code(OpCode::Call)
+///     .op0_offset(1)
+///     .op1_source(Op1Source::PC)
+///     .op1_offset(1)
+///     .pc_update(PcUpdate::RelativeJump)
+///     .build();
+///
+/// let decoded = instruction.decode().unwrap();
+/// assert_eq!(decoded.op_code, OpCode::Call);
+/// assert_eq!(decoded.op1_offset, 1);
+/// assert_eq!(instruction.size().unwrap(), 2);
+/// ```
+#[cfg(any(test, feature = "testing"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InstructionBuilder {
+    /// The fields of the instruction being built.
+    fields: DecodedInstruction,
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl InstructionBuilder {
+    /// Creates a new [`InstructionBuilder`] with every field zeroed.
+    pub const fn new() -> Self {
+        Self {
+            fields: DecodedInstruction {
+                dst_offset: 0,
+                op0_offset: 0,
+                op1_offset: 0,
+                dst_register: DstRegister::AP,
+                op0_register: Op0Register::AP,
+                op1_source: Op1Source::Op0,
+                result_logic: ResultLogic::Op1,
+                pc_update: PcUpdate::Regular,
+                ap_update: ApUpdate::None,
+                op_code: OpCode::None,
+            },
+        }
+    }
+
+    /// Sets the offset applied to the destination part of the instruction.
+    #[inline(always)]
+    pub const fn dst_offset(mut self, offset: i16) -> Self {
+        self.fields.dst_offset = offset;
+        self
+    }
+
+    /// Sets the offset applied to the first operand of the instruction.
+    #[inline(always)]
+    pub const fn op0_offset(mut self, offset: i16) -> Self {
+        self.fields.op0_offset = offset;
+        self
+    }
+
+    /// Sets the offset applied to the second operand of the instruction.
+    #[inline(always)]
+    pub const fn op1_offset(mut self, offset: i16) -> Self {
+        self.fields.op1_offset = offset;
+        self
+    }
+
+    /// Sets the register the destination part of the instruction is relative to.
+    #[inline(always)]
+    pub const fn dst_register(mut self, register: DstRegister) -> Self {
+        self.fields.dst_register = register;
+        self
+    }
+
+    /// Sets the register the first operand of the instruction is relative to.
+    #[inline(always)]
+    pub const fn op0_register(mut self, register: Op0Register) -> Self {
+        self.fields.op0_register = register;
+        self
+    }
+
+    /// Sets the source the second operand of the instruction is relative to.
+    #[inline(always)]
+    pub const fn op1_source(mut self, source: Op1Source) -> Self {
+        self.fields.op1_source = source;
+        self
+    }
+
+    /// Sets the logic used to compute the result of the instruction.
+    #[inline(always)]
+    pub const fn result_logic(mut self, logic: ResultLogic) -> Self {
+        self.fields.result_logic = logic;
+        self
+    }
+
+    /// Sets the update rule applied to the **Program Counter**.
+    #[inline(always)]
+    pub const fn pc_update(mut self, update: PcUpdate) -> Self {
+        self.fields.pc_update = update;
+        self
+    }
+
+    /// Sets the update rule applied to the **Allocation Pointer**.
+    #[inline(always)]
+    pub const fn ap_update(mut self, update: ApUpdate) -> Self {
+        self.fields.ap_update = update;
+        self
+    }
+
+    /// Sets the OP code of the instruction.
+    #[inline(always)]
+    pub const fn op_code(mut self, op_code: OpCode) -> Self {
+        self.fields.op_code = op_code;
+        self
+    }
+
+    /// Encodes the instruction.
+    ///
+    /// See [`DecodedInstruction::encode`].
+    #[inline]
+    pub fn build(&self) -> Instruction {
+        self.fields.encode()
+    }
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl Default for InstructionBuilder {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl fmt::Debug for Instruction {
@@ -222,3 +546,361 @@ impl fmt::Debug for Instruction {
             .finish()
     }
 }
+
+impl Instruction {
+    /// Writes the destination part of the instruction to the provided formatter.
+    fn write_dst(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Deref(self.dst_register(), self.dst_offset()))
+    }
+
+    /// Writes the first operand of the instruction to the provided formatter.
+    fn write_op0(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Deref(self.op0_register(), self.op0_offset()))
+    }
+
+    /// Writes the second operand of the instruction to the provided formatter.
+    fn write_op1(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let offset = self.op1_offset();
+
+        match self.op1_source() {
+            Ok(Op1Source::Op0) => {
+                write!(
+                    f,
+                    "{}",
+                    Deref(Deref(self.op0_register(), self.op0_offset()), offset)
+                )
+            }
+            Ok(Op1Source::PC) => write!(f, "{}", Deref("pc", offset)),
+            Ok(Op1Source::FP) => write!(f, "{}", Deref("fp", offset)),
+            Ok(Op1Source::AP) => write!(f, "{}", Deref("ap", offset)),
+            Err(_) => f.write_str("<invalid op1_source>"),
+        }
+    }
+
+    /// Writes the result of the instruction (computed from its operands) to the provided
+    /// formatter.
+    fn write_res(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.result_logic() {
+            Ok(ResultLogic::Op1) => self.write_op1(f),
+            Ok(ResultLogic::Add) => {
+                self.write_op0(f)?;
+                f.write_str(" + ")?;
+                self.write_op1(f)
+            }
+            Ok(ResultLogic::Mul) => {
+                self.write_op0(f)?;
+                f.write_str(" * ")?;
+                self.write_op1(f)
+            }
+            Err(_) => f.write_str("<invalid result_logic>"),
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.op_code() {
+            Ok(OpCode::AssertEq) => {
+                self.write_dst(f)?;
+                f.write_str(" = ")?;
+                self.write_res(f)?;
+            }
+            Ok(OpCode::Call) => {
+                match self.pc_update() {
+                    Ok(PcUpdate::AbsoluteJump) => f.write_str("call abs ")?,
+                    Ok(PcUpdate::RelativeJump) => f.write_str("call rel ")?,
+                    _ => f.write_str("call <invalid pc_update> ")?,
+                }
+                self.write_res(f)?;
+            }
+            Ok(OpCode::Ret) => f.write_str("ret")?,
+            Ok(OpCode::None) => match self.pc_update() {
+                // Without any jump, the only thing the instruction can do is update the
+                // allocation pointer. No need to append it as a suffix.
+                Ok(PcUpdate::Regular) => {
+                    return match self.ap_update() {
+                        Ok(ApUpdate::None) => f.write_str("nop"),
+                        Ok(ApUpdate::AddResult) => {
+                            f.write_str("ap += ")?;
+                            self.write_res(f)
+                        }
+                        Ok(ApUpdate::Increment) => f.write_str("ap++"),
+                        Err(_) => f.write_str("<invalid ap_update>"),
+                    };
+                }
+                Ok(PcUpdate::AbsoluteJump) => {
+                    f.write_str("jmp abs ")?;
+                    self.write_res(f)?;
+                }
+                Ok(PcUpdate::RelativeJump) => {
+                    f.write_str("jmp rel ")?;
+                    self.write_res(f)?;
+                }
+                Ok(PcUpdate::ConditionalJump) => {
+                    f.write_str("jmp rel ")?;
+                    self.write_op1(f)?;
+                    f.write_str(" if ")?;
+                    self.write_dst(f)?;
+                    f.write_str(" != 0")?;
+                }
+                Err(_) => f.write_str("<invalid pc_update>")?,
+            },
+            Err(_) => return f.write_str("<invalid op_code>"),
+        }
+
+        self.write_ap_suffix(f)
+    }
+}
+
+/// An [`Instruction`] displayed at a known address, created by [`Instruction::at`].
+///
+/// When the instruction is a relative jump (or call) whose displacement is its immediate
+/// value, the absolute target of the jump is displayed instead of the displacement (e.g.
+/// `jmp 0:42` rather than `jmp rel [pc + 1]`). Other instructions are displayed as usual.
+#[derive(Debug, Clone, Copy)]
+pub struct Listing<'a> {
+    /// The instruction to display.
+    instruction: Instruction,
+    /// The address of the instruction.
+    pc: Pointer,
+    /// The immediate value of the instruction, if known.
+    immediate: Option<&'a Felt>,
+}
+
+impl Instruction {
+    /// Returns an object displaying the instruction as located at `pc`, with the provided
+    /// immediate value.
+    ///
+    /// See [`Listing`] for more information.
+    #[inline(always)]
+    pub fn at<'a>(self, pc: Pointer, immediate: Option<&'a Felt>) -> Listing<'a> {
+        Listing {
+            instruction: self,
+            pc,
+            immediate,
+        }
+    }
+
+    /// Writes the suffix describing the update of the **Allocation Pointer** to the provided
+    /// formatter.
+    fn write_ap_suffix(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.ap_update() {
+            Ok(ApUpdate::None) => Ok(()),
+            Ok(ApUpdate::AddResult) => {
+                f.write_str("; ap += ")?;
+                self.write_res(f)
+            }
+            Ok(ApUpdate::Increment) => f.write_str("; ap++"),
+            Err(_) => f.write_str("; <invalid ap_update>"),
+        }
+    }
+}
+
+impl<'a> Listing<'a> {
+    /// Returns the absolute target of the instruction, if it is a relative jump whose
+    /// displacement is its (known) immediate value.
+    pub fn jump_target(&self) -> Option<Pointer> {
+        let instruction = &self.instruction;
+
+        if !matches!(instruction.op1_source(), Ok(Op1Source::PC)) || instruction.op1_offset() != 1 {
+            return None;
+        }
+
+        let relative = match instruction.pc_update() {
+            Ok(PcUpdate::RelativeJump) => {
+                matches!(instruction.result_logic(), Ok(ResultLogic::Op1))
+            }
+            Ok(PcUpdate::ConditionalJump) => true,
+            _ => false,
+        };
+        if !relative {
+            return None;
+        }
+
+        let immediate = self.immediate?;
+        match immediate.to_usize() {
+            Some(delta) => self.pc.checked_add(delta),
+            None => self.pc.checked_sub((Felt::ZERO - *immediate).to_usize()?),
+        }
+    }
+}
+
+impl<'a> fmt::Display for Listing<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let instruction = &self.instruction;
+
+        let Some(target) = self.jump_target() else {
+            return write!(f, "{instruction}");
+        };
+
+        match (instruction.op_code(), instruction.pc_update()) {
+            (Ok(OpCode::Call), _) => write!(f, "call {target}")?,
+            (Ok(OpCode::None), Ok(PcUpdate::ConditionalJump)) => {
+                write!(f, "jmp {target} if ")?;
+                instruction.write_dst(f)?;
+                f.write_str(" != 0")?;
+            }
+            (Ok(OpCode::None), _) => write!(f, "jmp {target}")?,
+            // Other op codes do not combine with jumps in a meaningful way.
+            _ => return write!(f, "{instruction}"),
+        }
+
+        instruction.write_ap_suffix(f)
+    }
+}
+
+/// A memory access relative to some base address, displayed as `[base + offset]`.
+///
+/// The base can itself be a [`Deref`], allowing double-indirect accesses such as
+/// `[[fp - 3] + 2]` to be displayed.
+struct Deref<B>(B, i16);
+
+impl<B: fmt::Display> fmt::Display for Deref<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self(base, offset) = self;
+
+        match *offset {
+            0 => write!(f, "[{base}]"),
+            o if o > 0 => write!(f, "[{base} + {o}]"),
+            o => write!(f, "[{base} - {}]", o.unsigned_abs()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+
+    #[test]
+    fn encode_decode_round_trip() {
+        let offsets = [i16::MIN, -1, 0, 1, i16::MAX];
+        let op1_sources = [Op1Source::Op0, Op1Source::PC, Op1Source::FP, Op1Source::AP];
+        let result_logics = [ResultLogic::Op1, ResultLogic::Add, ResultLogic::Mul];
+        let pc_updates = [
+            PcUpdate::Regular,
+            PcUpdate::AbsoluteJump,
+            PcUpdate::RelativeJump,
+            PcUpdate::ConditionalJump,
+        ];
+        let ap_updates = [ApUpdate::None, ApUpdate::AddResult, ApUpdate::Increment];
+        let op_codes = [OpCode::None, OpCode::Call, OpCode::Ret, OpCode::AssertEq];
+
+        for (&offset, &op1_source) in offsets.iter().zip(op1_sources.iter().cycle()) {
+            for &result_logic in &result_logics {
+                for &pc_update in &pc_updates {
+                    for &ap_update in &ap_updates {
+                        for &op_code in &op_codes {
+                            let fields = DecodedInstruction {
+                                dst_offset: offset,
+                                op0_offset: offset.wrapping_neg(),
+                                op1_offset: offset.wrapping_add(1),
+                                dst_register: DstRegister::FP,
+                                op0_register: Op0Register::AP,
+                                op1_source,
+                                result_logic,
+                                pc_update,
+                                ap_update,
+                                op_code,
+                            };
+
+                            let instruction = InstructionBuilder::new()
+                                .dst_offset(fields.dst_offset)
+                                .op0_offset(fields.op0_offset)
+                                .op1_offset(fields.op1_offset)
+                                .dst_register(fields.dst_register)
+                                .op0_register(fields.op0_register)
+                                .op1_source(op1_source)
+                                .result_logic(result_logic)
+                                .pc_update(pc_update)
+                                .ap_update(ap_update)
+                                .op_code(op_code)
+                                .build();
+
+                            assert_eq!(instruction, fields.encode());
+                            assert_eq!(instruction.decode().unwrap(), fields);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn decode_matches_accessors() {
+        // A simple xorshift generator, covering both valid and invalid encodings.
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut valid = 0;
+        for _ in 0..10_000 {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+
+            let instruction = Instruction(state & !0x8000_0000_0000_0000);
+
+            match instruction.decode() {
+                Ok(decoded) => {
+                    valid += 1;
+                    assert_eq!(decoded.dst_offset, instruction.dst_offset());
+                    assert_eq!(decoded.op0_offset, instruction.op0_offset());
+                    assert_eq!(decoded.op1_offset, instruction.op1_offset());
+                    assert_eq!(decoded.dst_register, instruction.dst_register());
+                    assert_eq!(decoded.op0_register, instruction.op0_register());
+                    assert_eq!(decoded.op1_source, instruction.op1_source().unwrap());
+                    assert_eq!(decoded.result_logic, instruction.result_logic().unwrap());
+                    assert_eq!(decoded.pc_update, instruction.pc_update().unwrap());
+                    assert_eq!(decoded.ap_update, instruction.ap_update().unwrap());
+                    assert_eq!(decoded.op_code, instruction.op_code().unwrap());
+                    assert_eq!(decoded.size(), instruction.size().unwrap());
+                }
+                Err(_) => assert!(
+                    instruction.op1_source().is_err()
+                        || instruction.result_logic().is_err()
+                        || instruction.pc_update().is_err()
+                        || instruction.ap_update().is_err()
+                        || instruction.op_code().is_err()
+                ),
+            }
+        }
+
+        assert!(valid > 0);
+    }
+
+    #[test]
+    fn relative_jump_target() {
+        let jump = InstructionBuilder::new()
+            .op1_offset(1)
+            .op1_source(Op1Source::PC)
+            .pc_update(PcUpdate::RelativeJump)
+            .build();
+        let pc = Pointer {
+            segment: 0,
+            offset: 10,
+        };
+
+        let forward = Felt::from(5);
+        let listing = jump.at(pc, Some(&forward));
+        assert_eq!(listing.jump_target(), Some(pc.wrapping_add(5)));
+        assert_eq!(listing.to_string(), "jmp 0:15");
+
+        let backward = Felt::from(-10);
+        assert_eq!(jump.at(pc, Some(&backward)).to_string(), "jmp 0:0");
+
+        // Targets before the start of the segment, or an unknown displacement, are not resolved.
+        let underflow = Felt::from(-11);
+        assert_eq!(jump.at(pc, Some(&underflow)).jump_target(), None);
+        assert_eq!(jump.at(pc, None).to_string(), jump.to_string());
+
+        let conditional = InstructionBuilder::new()
+            .dst_offset(-1)
+            .op1_offset(1)
+            .op1_source(Op1Source::PC)
+            .pc_update(PcUpdate::ConditionalJump)
+            .build();
+        assert_eq!(
+            conditional.at(pc, Some(&forward)).to_string(),
+            "jmp 0:15 if [ap - 1] != 0"
+        );
+    }
+}
@@ -5,30 +5,72 @@
 //! # Documentation
 //!
 //! - [The Cairo Whitepaper](https://www.cairo-lang.org/cairo-whitepaper/).
+//!
+//! # Features
+//!
+//! The crate is `no_std`, and only requires an allocator. The memory, the segments, the
+//! instructions and the virtual machine itself are available in every configuration.
+//!
+//! - `std` (enabled by default): Enables the APIs that rely on the standard library. This
+//!   includes everything that writes to an [`std::io::Write`] implementation (such as
+//!   [`RelocatedMemory::write_bin`](memory::RelocatedMemory::write_bin) and
+//!   [`RegisterTrace::write_bin`](trace::RegisterTrace::write_bin)), as well as the types
+//!   backed by a hash map ([`MemoryView`](memory::MemoryView) and
+//!   [`CfgTrace`](trace::CfgTrace)).
+//!
+//! - `serde`: Enables serialization of [`VmSnapshot`]s, as well as the
+//!   [`JsonTrace`](trace::JsonTrace) type. Implies `std`.
+//!
+//! - `constant-time`: Compares the values asserted to known memory cells without branching on
+//!   their content, at a performance cost. See [`Segment::assert_eq`](memory::Segment::assert_eq)
+//!   for what remains observable.
+//!
+//! - `testing`: Exposes helpers meant to write programs by hand in tests and tools, such as
+//!   `InstructionBuilder`. They are always available to the tests of this crate.
 
+#![no_std]
 #![warn(missing_docs, missing_debug_implementations)]
 #![deny(unsafe_op_in_unsafe_fn)]
 
-use std::fmt;
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::any::Any;
+use core::fmt;
 
 use bitflags::bitflags;
-use num_traits::ToPrimitive;
 use starknet_types_core::felt::Felt;
 
 use builtin::Builtin;
 use cpu::Cpu;
 use error::Error;
-use instr::{Instruction, ResultLogic};
-use memory::{Memory, Pointer, Value};
+use hint::{HintProcessor, HintStats};
+use instr::{DecodedInstruction, Instruction, OpCode, ResultLogic};
+use memory::{Memory, Pointer, Segment, Value, ValueRef};
+use resource::{ResourceTracker, Unmetered};
 use trace::Trace;
 
 pub mod builtin;
 pub mod cpu;
 pub mod error;
+pub mod hint;
 pub mod instr;
 pub mod memory;
+pub mod program;
+pub mod resource;
 pub mod trace;
 
+#[cfg(feature = "serde")]
+mod snapshot;
+#[cfg(test)]
+mod tests;
+mod verify;
+
 /// Contains the full state of a Cairo virtual machine.
 ///
 /// This includes memory, registers, builtins, etc. It can be used to execute a Cairo program
@@ -61,55 +103,1066 @@ pub struct CairoVM {
     ///
     /// Instructions and working memory are stored here.
     memory: Memory,
+    /// The number of steps successfully executed by the virtual machine so far.
+    steps: usize,
+    /// The state of the registers before the first step was executed.
+    initial: Cpu,
 
     /// The built-in functions that the virtual machine can execute.
     builtins: BuiltinManager,
+    /// Statistics about the hints run so far.
+    hint_stats: HintStats,
+
+    /// The cells written through [`CairoVM::assert_eq`] while journaling is enabled.
+    ///
+    /// This is used to record the cells written by hints (see [`trace::ReplayTrace`]).
+    journal: Option<Vec<(Pointer, Value)>>,
 }
 
 impl CairoVM {
+    /// The index of the segment holding the bytecode of the program, when the virtual machine
+    /// is created with [`CairoVM::new`].
+    pub const PROGRAM_SEGMENT: usize = 0;
+
+    /// The index of the segment holding the working memory of the program, when the virtual
+    /// machine is created with [`CairoVM::new`].
+    pub const EXECUTION_SEGMENT: usize = 1;
+
+    /// The index of the segment assigned to the first builtin, when the virtual machine is
+    /// created with [`CairoVM::new`].
+    pub const FIRST_BUILTIN_SEGMENT: usize = 2;
+
+    /// Creates a new [`CairoVM`] ready to execute the provided program.
+    ///
+    /// # Segments
+    ///
+    /// The memory of the virtual machine is laid out as follows:
+    ///
+    /// - Segment [`PROGRAM_SEGMENT`](Self::PROGRAM_SEGMENT) (`0`) holds the bytecode of the
+    ///   program, starting at offset `0`.
+    ///
+    /// - Segment [`EXECUTION_SEGMENT`](Self::EXECUTION_SEGMENT) (`1`) holds the working memory
+    ///   of the program.
+    ///
+    /// - Segments starting at [`FIRST_BUILTIN_SEGMENT`](Self::FIRST_BUILTIN_SEGMENT) (`2`) are
+    ///   assigned to the provided builtins, in order. Each of them is created with the
+    ///   capacity requested by [`Builtin::initial_segment_size`].
+    ///
+    /// The **Program Counter** points to the first instruction of the program, while the
+    /// **Allocation Pointer** and the **Frame Pointer** point to the start of the execution
+    /// segment.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with [`Error::OutOfMemory`] if the capacity reserved for one of the
+    /// builtins cannot be allocated.
+    pub fn new(program: &[Value], builtins: Vec<Box<dyn Builtin>>) -> Result<Self, Error> {
+        let mut memory = Memory::default();
+
+        let program_segment = memory.push_segment(Segment::from_values(program));
+        let execution_segment = memory.allocate_segment();
+        debug_assert_eq!(program_segment, Self::PROGRAM_SEGMENT);
+        debug_assert_eq!(execution_segment, Self::EXECUTION_SEGMENT);
+
+        for builtin in &builtins {
+            let segment = memory.allocate_segment();
+            memory
+                .segment_mut(segment)
+                .ok_or(Error::UnallocatedSegment)?
+                .reserve(builtin.initial_segment_size())?;
+        }
+
+        let start = Pointer {
+            segment: execution_segment,
+            offset: 0,
+        };
+
+        let cpu = Cpu {
+            pc: Pointer {
+                segment: program_segment,
+                offset: 0,
+            },
+            ap: start,
+            fp: start,
+        };
+
+        Ok(Self {
+            initial: cpu.clone(),
+            builtins: BuiltinManager::new(Self::FIRST_BUILTIN_SEGMENT, builtins, &memory)?,
+            cpu,
+            memory,
+            steps: 0,
+            hint_stats: HintStats::default(),
+            journal: None,
+        })
+    }
+
+    /// Creates a new [`CairoVM`] from an existing [`Memory`] and an initial register state.
+    ///
+    /// The **Program Counter**, **Allocation Pointer** and **Frame Pointer** are independent
+    /// from each other, and may reference different segments. For example, some entry
+    /// conventions place the initial frame in a dedicated segment, distinct from the one that
+    /// holds the program.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with [`Error::UnallocatedSegment`] if one of the registers
+    /// references a segment that has not been allocated in `memory`.
+    pub fn from_parts(memory: Memory, cpu: Cpu) -> Result<Self, Error> {
+        for register in [cpu.pc, cpu.ap, cpu.fp] {
+            if register.segment >= memory.num_segments() {
+                return Err(Error::UnallocatedSegment);
+            }
+        }
+
+        Ok(Self {
+            initial: cpu.clone(),
+            cpu,
+            memory,
+            steps: 0,
+            builtins: BuiltinManager::empty(),
+            hint_stats: HintStats::default(),
+            journal: None,
+        })
+    }
+
+    /// Creates a new [`CairoVM`] resuming the execution captured by `snapshot`.
+    ///
+    /// Builtins are not part of the snapshot, so they must be provided again, in the same
+    /// order as when the original virtual machine was created with [`CairoVM::new`]. They are
+    /// assigned the segments starting at [`FIRST_BUILTIN_SEGMENT`](Self::FIRST_BUILTIN_SEGMENT).
+    ///
+    /// Together with the `serde` feature, which makes [`VmSnapshot`] serializable, this allows
+    /// long runs to be saved to disk and resumed later.
+    ///
+    /// As with [`CairoVM::new`], the capacity requested by [`Builtin::initial_segment_size`] is
+    /// reserved in the segment of each builtin.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with [`Error::UnallocatedSegment`] if one of the registers or one of
+    /// the builtins references a segment that is missing from the snapshot, and with
+    /// [`Error::OutOfMemory`] if the capacity reserved for one of the builtins cannot be
+    /// allocated.
+    pub fn resume(snapshot: VmSnapshot, builtins: Vec<Box<dyn Builtin>>) -> Result<Self, Error> {
+        let VmSnapshot {
+            cpu,
+            mut memory,
+            steps,
+            initial,
+        } = snapshot;
+
+        let builtins = BuiltinManager::new(Self::FIRST_BUILTIN_SEGMENT, builtins, &memory)?;
+        for (segment, builtin) in builtins.iter() {
+            // The segments of the snapshot have been trimmed to their length.
+            let segment = memory
+                .segment_mut(segment)
+                .ok_or(Error::UnallocatedSegment)?;
+            segment.reserve(
+                builtin
+                    .initial_segment_size()
+                    .saturating_sub(segment.size()),
+            )?;
+        }
+
+        let num_segments = memory.num_segments();
+        if [cpu.pc, cpu.ap, cpu.fp]
+            .iter()
+            .any(|register| register.segment >= num_segments)
+        {
+            return Err(Error::UnallocatedSegment);
+        }
+
+        Ok(Self {
+            cpu,
+            memory,
+            steps,
+            initial,
+            builtins,
+            hint_stats: HintStats::default(),
+            journal: None,
+        })
+    }
+
     /// Returns the current state of the [`Cpu`].
     #[inline(always)]
     pub fn cpu(&self) -> &Cpu {
         &self.cpu
     }
 
+    /// Returns the current value of the **Program Counter**.
+    #[inline(always)]
+    pub fn pc(&self) -> Pointer {
+        self.cpu.pc
+    }
+
+    /// Returns the current value of the **Allocation Pointer**.
+    #[inline(always)]
+    pub fn ap(&self) -> Pointer {
+        self.cpu.ap
+    }
+
+    /// Returns the current value of the **Frame Pointer**.
+    #[inline(always)]
+    pub fn fp(&self) -> Pointer {
+        self.cpu.fp
+    }
+
     /// Returns the current state of the [`Memory`].
     #[inline(always)]
     pub fn memory(&self) -> &Memory {
         &self.memory
     }
 
-    /// Advances the virtual machine by a single step, tracing events using the provided [`Trace`]
+    /// Returns the instruction referenced by the **Program Counter**, without executing it.
+    ///
+    /// The instruction is not decoded in any way.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with:
+    ///
+    /// - [`Error::ProgramCounterLost`] if the **Program Counter** references an unallocated
+    ///   segment or an unknown memory cell.
+    ///
+    /// - [`Error::InstructionIsPointer`] if the memory cell holds a pointer.
+    ///
+    /// - [`Error::UndefinedInstruction`] if the memory cell does not fit in 64 bits, or has its
+    ///   last bit set (see [`Instruction::try_from_felt`]).
+    pub fn current_instruction(&self) -> Result<Instruction, Error> {
+        if self.cpu.pc.segment >= self.memory.num_segments() {
+            return Err(Error::ProgramCounterLost);
+        }
+
+        // SAFETY:
+        //  We just made sure that the segment referenced by the program counter exists.
+        unsafe { fetch_instruction(&self.cpu, &self.memory) }.map(|(instruction, _)| instruction)
+    }
+
+    /// Returns the number of steps successfully executed by the virtual machine so far.
+    #[inline(always)]
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// Returns the number of hints run so far.
+    ///
+    /// See [`hint_stats`](Self::hint_stats).
+    #[inline(always)]
+    pub fn hint_count(&self) -> usize {
+        self.hint_stats.total()
+    }
+
+    /// Returns statistics about the hints run so far, as reported by the
+    /// [`HintProcessor::hint_count`] implementation of the hint processors used to run the
+    /// program.
+    ///
+    /// Hints are counted once they have been executed successfully, even if the step they
+    /// belong to fails afterwards. The statistics are not part of [`VmSnapshot`]s.
+    #[inline(always)]
+    pub fn hint_stats(&self) -> &HintStats {
+        &self.hint_stats
+    }
+
+    /// Returns the state of the registers before the first step was executed.
+    ///
+    /// # Public input
+    ///
+    /// Together with [`final_registers`](Self::final_registers), this delimits the execution
+    /// being proven: the initial **Program Counter** and **Allocation Pointer** become the
+    /// `initial_pc` and `initial_ap` of the public input of the proof (the initial **Frame
+    /// Pointer** being equal to `initial_ap` by convention).
+    #[inline(always)]
+    pub fn initial_registers(&self) -> &Cpu {
+        &self.initial
+    }
+
+    /// Returns the state of the registers after the last step executed so far.
+    ///
+    /// # Public input
+    ///
+    /// Once [`run`](Self::run) has returned successfully, the **Program Counter** is the `end`
+    /// address the program was run until. The final **Program Counter** and **Allocation
+    /// Pointer** become the `final_pc` and `final_ap` of the public input of the proof.
+    #[inline(always)]
+    pub fn final_registers(&self) -> &Cpu {
+        &self.cpu
+    }
+
+    /// Returns the current state of the [`Memory`], mutably.
+    ///
+    /// Memory cells that cannot be deduced by the virtual machine (for example from a
+    /// [`HintProcessor`]) can be written without `unsafe` code using
+    /// [`assert_eq`](Self::assert_eq).
+    ///
+    /// # Safety
+    ///
+    /// The virtual machine assumes that the **Program Counter**, **Allocation Pointer** and
+    /// **Frame Pointer** always reference allocated segments, as well as the segments assigned
+    /// to builtins. When the returned reference is dropped, the memory must still contain all
+    /// of those segments. In practice, this means the memory must not be replaced by one with
+    /// fewer segments.
+    #[inline(always)]
+    pub unsafe fn memory_mut(&mut self) -> &mut Memory {
+        &mut self.memory
+    }
+
+    /// Returns the current state of the [`Cpu`], mutably.
+    ///
+    /// Registers can be modified without `unsafe` code using [`set_cpu`](Self::set_cpu).
+    ///
+    /// # Safety
+    ///
+    /// When the returned reference is dropped, the **Program Counter**, **Allocation Pointer**
+    /// and **Frame Pointer** must all reference segments that have been allocated in the
+    /// memory of the virtual machine.
+    #[inline(always)]
+    pub unsafe fn cpu_mut(&mut self) -> &mut Cpu {
+        &mut self.cpu
+    }
+
+    /// Replaces the registers of the virtual machine, for example to set the entry point of
+    /// the program before running it.
+    ///
+    /// When no step has been executed yet, the [initial registers](Self::initial_registers)
+    /// are replaced as well.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with [`Error::UnallocatedSegment`] if one of the registers
+    /// references a segment that has not been allocated. In that case, the registers are left
+    /// untouched.
+    pub fn set_cpu(&mut self, cpu: Cpu) -> Result<(), Error> {
+        if [cpu.pc, cpu.ap, cpu.fp]
+            .iter()
+            .any(|register| register.segment >= self.memory.num_segments())
+        {
+            return Err(Error::UnallocatedSegment);
+        }
+
+        if self.steps == 0 {
+            self.initial = cpu.clone();
+        }
+
+        self.cpu = cpu;
+        Ok(())
+    }
+
+    /// Allocates a new empty segment in the memory of the virtual machine, returning its
+    /// index.
+    ///
+    /// See [`Memory::allocate_segment`].
+    #[inline(always)]
+    pub fn allocate_segment(&mut self) -> usize {
+        self.memory.allocate_segment()
+    }
+
+    /// Allocates a new empty segment in the memory of the virtual machine, returning a pointer
+    /// to its first cell.
+    ///
+    /// This is the pattern used by hints that need fresh memory (e.g. for a dictionary or a
+    /// temporary array), and write its base address into a memory cell with
+    /// [`assert_eq`](Self::assert_eq). Like any other segment, the final location of the new
+    /// segment is only decided once the memory is [relocated](Memory::relocate).
+    #[inline]
+    pub fn alloc_segment_pointer(&mut self) -> Pointer {
+        Pointer {
+            segment: self.memory.allocate_segment(),
+            offset: 0,
+        }
+    }
+
+    /// Attempts to assert that the memory cell referenced by the provided [`Pointer`] has a
+    /// given value.
+    ///
+    /// See [`Memory::assert_eq`].
+    #[inline]
+    pub fn assert_eq(&mut self, pointer: Pointer, value: ValueRef) -> Result<(), Error> {
+        let Some(journal) = &mut self.journal else {
+            return self.memory.assert_eq(pointer, value);
+        };
+
+        let fresh = self.memory.get(pointer).is_none();
+        self.memory.assert_eq(pointer, value)?;
+        if fresh {
+            journal.push((pointer, value.copied()));
+        }
+
+        Ok(())
+    }
+
+    /// Starts recording the cells written through [`assert_eq`](Self::assert_eq), discarding
+    /// any previous record.
+    #[inline]
+    pub(crate) fn start_journal(&mut self) {
+        self.journal = Some(Vec::new());
+    }
+
+    /// Stops recording the cells written through [`assert_eq`](Self::assert_eq), returning
+    /// the cells written since [`start_journal`](Self::start_journal) was called.
+    #[inline]
+    pub(crate) fn take_journal(&mut self) -> Vec<(Pointer, Value)> {
+        self.journal.take().unwrap_or_default()
+    }
+
+    /// Captures the current state of the registers and of the memory, so that it can be
+    /// restored later with [`restore`](Self::restore).
+    ///
+    /// # Cost
+    ///
+    /// This performs a full copy of the memory, which can be expensive for programs that have
+    /// been running for a long time.
+    pub fn snapshot(&self) -> VmSnapshot {
+        VmSnapshot {
+            cpu: self.cpu.clone(),
+            memory: self.memory.clone(),
+            steps: self.steps,
+            initial: self.initial.clone(),
+        }
+    }
+
+    /// Saves the current state of the virtual machine, so that its execution can be resumed
+    /// later with [`resume`](Self::resume).
+    ///
+    /// This is the same as [`snapshot`](Self::snapshot). With the `serde` feature, the returned
+    /// [`VmSnapshot`] can be serialized to disk.
+    #[inline]
+    pub fn save(&self) -> VmSnapshot {
+        self.snapshot()
+    }
+
+    /// Restores the state of the registers and of the memory from a previously captured
+    /// [`VmSnapshot`].
+    ///
+    /// # Errors
+    ///
+    /// This function fails with [`Error::UnallocatedSegment`] if the snapshot is missing some
+    /// of the segments required by this virtual machine (which can only happen when restoring
+    /// a snapshot taken from another virtual machine). In that case, the state of the virtual
+    /// machine is left untouched.
+    pub fn restore(&mut self, snapshot: VmSnapshot) -> Result<(), Error> {
+        let VmSnapshot {
+            cpu,
+            memory,
+            steps,
+            initial,
+        } = snapshot;
+
+        let num_segments = memory.num_segments();
+        if self.builtins.max_segment > num_segments
+            || [cpu.pc, cpu.ap, cpu.fp]
+                .iter()
+                .any(|register| register.segment >= num_segments)
+        {
+            return Err(Error::UnallocatedSegment);
+        }
+
+        self.cpu = cpu;
+        self.memory = memory;
+        self.steps = steps;
+        self.initial = initial;
+
+        Ok(())
+    }
+
+    /// Steps the virtual machine backwards, undoing the last step recorded by `log`.
+    ///
+    /// The registers are restored to the state they had before the step. The memory cells
+    /// written by the step keep their value, as the memory is write-once. They are reported by
+    /// [`UndoLog::stale_cells`](trace::UndoLog::stale_cells) instead.
+    ///
+    /// `log` must have been used to trace the steps of this virtual machine.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with [`Error::NothingToUndo`] if `log` has no step left to undo.
+    pub fn undo(&mut self, log: &mut trace::UndoLog) -> Result<(), Error> {
+        self.cpu = log.pop()?;
+        self.steps = self.steps.saturating_sub(1);
+        Ok(())
+    }
+
+    /// Executes the steps recorded by `trace` again, feeding the virtual machine the segments
+    /// and the cells that hints provided during the recording.
+    ///
+    /// The virtual machine must be in the state the recorded one was in when the recording
+    /// started (e.g. created with the same program and builtins). Hints are not run: the
+    /// cells they wrote are asserted before each step instead.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with [`Error::InvalidTraceRecord`] if the registers diverge from
+    /// the recorded ones, and with the error of the failing step if one of them fails.
+    pub fn replay(&mut self, trace: &trace::ReplayTrace) -> Result<(), Error> {
+        for step in trace.steps() {
+            if self.cpu != step.cpu {
+                return Err(Error::InvalidTraceRecord);
+            }
+
+            for _ in 0..step.new_segments {
+                self.memory.allocate_segment();
+            }
+            for (pointer, value) in &step.writes {
+                self.memory.assert_eq(*pointer, value.as_ref())?;
+            }
+
+            self.step(&mut hint::NoopHintProcessor, &mut trace::NoopTrace)?;
+        }
+
+        Ok(())
+    }
+
+    /// Runs the virtual machine until the **Program Counter** reaches `end`, running hints
+    /// with the provided [`HintProcessor`] and tracing events using the provided [`Trace`]
     /// implementation.
-    pub fn step<T>(&mut self, _trace: &mut T) -> Result<(), Error>
+    ///
+    /// The provided [`RunOptions`] can be used to bound the execution of programs that never
+    /// reach `end`.
+    pub fn run<H, T>(
+        &mut self,
+        end: Pointer,
+        options: &RunOptions,
+        hints: &mut H,
+        trace: &mut T,
+    ) -> Result<(), Error>
+    where
+        H: ?Sized + HintProcessor,
+        T: ?Sized + Trace,
+    {
+        self.run_with_assertions(end, options, hints, trace, |_| Ok(()))
+    }
+
+    /// Like [`run`](Self::run), but invokes `assert_fn` after each step to check arbitrary
+    /// invariants on the state of the virtual machine.
+    ///
+    /// This is meant for ad-hoc checks during development, where a full [`Trace`] would be
+    /// overkill.
+    ///
+    /// # Errors
+    ///
+    /// In addition to the errors returned by [`run`](Self::run), this function fails with
+    /// [`Error::AssertionFailed`] as soon as `assert_fn` returns an error, carrying the
+    /// provided diagnostic.
+    pub fn run_with_assertions<H, T, F>(
+        &mut self,
+        end: Pointer,
+        options: &RunOptions,
+        hints: &mut H,
+        trace: &mut T,
+        assert_fn: F,
+    ) -> Result<(), Error>
+    where
+        H: ?Sized + HintProcessor,
+        T: ?Sized + Trace,
+        F: FnMut(&CairoVM) -> Result<(), String>,
+    {
+        self.run_loop(end, options, hints, trace, &mut Unmetered, assert_fn)
+    }
+
+    /// Like [`run`](Self::run), but charges the cost of each step to the provided
+    /// [`ResourceTracker`].
+    ///
+    /// The cost of a step is the [instruction cost](ResourceTracker::instruction_cost), plus
+    /// the [cost](Builtin::cost) of the builtin owning each operand deduced by the step. It is
+    /// charged once the step has been executed.
+    ///
+    /// # Errors
+    ///
+    /// In addition to the errors returned by [`run`](Self::run), this function fails with the
+    /// error returned by the tracker (usually [`Error::OutOfGas`]) as soon as it runs out of
+    /// resources.
+    pub fn run_metered<H, T, R>(
+        &mut self,
+        end: Pointer,
+        options: &RunOptions,
+        hints: &mut H,
+        trace: &mut T,
+        resources: &mut R,
+    ) -> Result<(), Error>
+    where
+        H: ?Sized + HintProcessor,
+        T: ?Sized + Trace,
+        R: ?Sized + ResourceTracker,
+    {
+        self.run_loop(end, options, hints, trace, resources, |_| Ok(()))
+    }
+
+    /// The execution loop shared by [`run_with_assertions`](Self::run_with_assertions) and
+    /// [`run_metered`](Self::run_metered).
+    fn run_loop<H, T, R, F>(
+        &mut self,
+        end: Pointer,
+        options: &RunOptions,
+        hints: &mut H,
+        trace: &mut T,
+        resources: &mut R,
+        mut assert_fn: F,
+    ) -> Result<(), Error>
+    where
+        H: ?Sized + HintProcessor,
+        T: ?Sized + Trace,
+        R: ?Sized + ResourceTracker,
+        F: FnMut(&CairoVM) -> Result<(), String>,
+    {
+        let mut steps = 0usize;
+        let mut repeated = 0usize;
+
+        while self.cpu.pc != end {
+            if options.max_steps.is_some_and(|max| steps >= max) {
+                return Err(Error::StepLimitReached);
+            }
+
+            let previous = self.cpu.clone();
+            let outcome = self.step(hints, trace)?;
+            steps += 1;
+
+            resources.consume(self.step_cost(&outcome, resources.instruction_cost()))?;
+
+            assert_fn(self).map_err(Error::AssertionFailed)?;
+
+            if let Some(limit) = options.loop_detection {
+                // Memory is immutable, meaning that an instruction leaving the registers
+                // untouched will keep doing so forever.
+                if self.cpu == previous {
+                    repeated += 1;
+                    if repeated >= limit {
+                        return Err(Error::InfiniteLoop);
+                    }
+                } else {
+                    repeated = 0;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Computes the cost of the step described by `outcome`, for a [`ResourceTracker`] charging
+    /// `instruction_cost` for the instruction itself.
+    fn step_cost(&self, outcome: &StepOutcome, instruction_cost: u64) -> u64 {
+        [outcome.dst, outcome.op0, outcome.op1]
+            .iter()
+            .filter(|operand| operand.origin == OperandOrigin::Deduced)
+            .filter_map(|operand| self.builtins.get_runner(operand.address.segment))
+            .fold(instruction_cost, |cost, builtin| {
+                cost.saturating_add(builtin.cost())
+            })
+    }
+
+    /// Advances the virtual machine by a single step, running hints with the provided
+    /// [`HintProcessor`] and tracing events using the provided [`Trace`] implementation.
+    ///
+    /// Hints associated with the current **Program Counter** are executed before anything
+    /// else, giving them the opportunity to write memory cells that the instruction needs.
+    ///
+    /// On success, a [`StepOutcome`] describing the executed instruction is returned.
+    pub fn step<H, T>(&mut self, hints: &mut H, trace: &mut T) -> Result<StepOutcome, Error>
+    where
+        H: ?Sized + HintProcessor,
+        T: ?Sized + Trace,
+    {
+        let result = self.step_inner(hints, trace);
+
+        match &result {
+            Ok(_) => self.steps += 1,
+            Err(err) => trace.on_error(&self.cpu, err),
+        }
+
+        result
+    }
+
+    /// Advances the virtual machine by a single step.
+    ///
+    /// This is the implementation of [`step`](Self::step), without the error reporting.
+    #[inline(always)]
+    fn step_inner<H, T>(&mut self, hints: &mut H, trace: &mut T) -> Result<StepOutcome, Error>
     where
+        H: ?Sized + HintProcessor,
         T: ?Sized + Trace,
     {
+        let hint_pc = self.cpu.pc;
+        hints.execute(self, hint_pc)?;
+
+        let hint_count = hints.hint_count(hint_pc);
+        if hint_count != 0 {
+            self.hint_stats.record(hint_pc, hint_count);
+        }
+
+        trace.on_step(&self.cpu);
+
         // SAFETY:
         //  We make sure when updating the program counter of the `CPU` that the segment it points
         //  to remains valid.
-        let instruction = unsafe { fetch_instruction(&self.cpu, &self.memory)? };
+        let (instruction, immediate) = unsafe { fetch_instruction(&self.cpu, &self.memory)? };
 
-        if instruction.is_last_bit_set() {
-            return Err(Error::UndefinedInstruction);
-        }
+        let pc = self.cpu.pc;
+        trace.on_decode(pc, instruction);
 
-        let mut ctx = StepContext::initial(instruction);
+        let mut ctx = StepContext::initial(DecodedInstruction::new(instruction)?);
         compute_dst(&mut ctx, self);
         compute_op0(&mut ctx, self);
-        compute_op1(&mut ctx, self)?;
-        run_builtins(&mut ctx, self)?;
-        deduce_from_op_code(&mut ctx, self)?;
+        compute_op1(&mut ctx, self, immediate)?;
+
+        if ctx.flags.contains(StepContextFlags::ALL_ASSERTED) {
+            // Fast path: every operand is already known, nothing needs to be deduced.
+            verify_asserted(&mut ctx, self)?;
+        } else {
+            run_builtins(&mut ctx, self)?;
+            deduce_from_op_code(&mut ctx, self)?;
+        }
+
         update_ap(&mut ctx, self)?;
         update_pc(&mut ctx, self)?;
 
         // Apply the modifications to the memory.
         apply_modifications(&mut ctx, self)?;
 
+        for (flag, addr) in [
+            (StepContextFlags::DST_DEDUCED, ctx.dst_addr),
+            (StepContextFlags::OP0_DEDUCED, ctx.op0_addr),
+            (StepContextFlags::OP1_DEDUCED, ctx.op1_addr),
+        ] {
+            if ctx.flags.contains(flag) {
+                trace.on_write(addr);
+            }
+        }
+
+        match ctx.instruction.op_code {
+            OpCode::Call => trace.on_call(self.cpu.pc, pc.wrapping_add(ctx.instruction.size())),
+            OpCode::Ret => trace.on_ret(self.cpu.pc),
+            _ => (),
+        }
+
+        Ok(StepOutcome {
+            op_code: ctx.instruction.op_code,
+            dst: Operand::new(
+                ctx.dst_addr,
+                ctx.flags,
+                StepContextFlags::DST_ASSERTED,
+                StepContextFlags::DST_DEDUCED,
+            ),
+            op0: Operand::new(
+                ctx.op0_addr,
+                ctx.flags,
+                StepContextFlags::OP0_ASSERTED,
+                StepContextFlags::OP0_DEDUCED,
+            ),
+            op1: Operand::new(
+                ctx.op1_addr,
+                ctx.flags,
+                StepContextFlags::OP1_ASSERTED,
+                StepContextFlags::OP1_DEDUCED,
+            ),
+            cpu: self.cpu.clone(),
+        })
+    }
+
+    /// Walks a linked list stored in memory, returning the address of each node.
+    ///
+    /// Starting from `head`, the pointer stored at `[node + next_offset]` is followed until a
+    /// cell that does not hold a pointer is found (usually a scalar acting as a null
+    /// terminator, or an unknown cell), until a [null-like](Pointer::is_null_like) pointer is
+    /// found, or until `max` nodes have been collected.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with [`Error::UnallocatedSegment`] if one of the nodes is located in
+    /// a segment that has not been allocated.
+    pub fn walk_list(
+        &self,
+        head: Pointer,
+        next_offset: usize,
+        max: usize,
+    ) -> Result<Vec<Pointer>, Error> {
+        let mut nodes = Vec::new();
+        let mut node = head;
+
+        while nodes.len() < max && !node.is_null_like() {
+            if node.segment >= self.memory.num_segments() {
+                return Err(Error::UnallocatedSegment);
+            }
+
+            nodes.push(node);
+
+            match self.memory.get(node.wrapping_add(next_offset)) {
+                Some(ValueRef::Pointer(next)) => node = *next,
+                _ => break,
+            }
+        }
+
+        Ok(nodes)
+    }
+
+    /// Disassembles the next `count` instructions, starting at the current **Program Counter**.
+    ///
+    /// Each instruction is returned along with its address, rendered as a line of Cairo
+    /// assembly. Relative jumps whose displacement is known are rendered with their absolute
+    /// target (see [`Listing`](instr::Listing)). Disassembly stops early if a memory cell
+    /// cannot be decoded as an instruction.
+    pub fn disassemble_current(&self, count: usize) -> Vec<(Pointer, String)> {
+        let mut lines = Vec::with_capacity(count);
+        let mut pc = self.cpu.pc;
+
+        while lines.len() < count {
+            let Some(word) = self.memory.get(pc).and_then(ValueRef::scalar) else {
+                break;
+            };
+            let Ok(instruction) = Instruction::try_from_felt(word) else {
+                break;
+            };
+
+            let immediate = self
+                .memory
+                .get(pc.wrapping_add(1))
+                .and_then(ValueRef::scalar);
+            lines.push((pc, instruction.at(pc, immediate).to_string()));
+
+            let Ok(size) = instruction.size() else { break };
+            pc = pc.wrapping_add(size);
+        }
+
+        lines
+    }
+
+    /// Disassembles the whole content of a segment, usually the one holding the bytecode of
+    /// the program.
+    ///
+    /// Each known cell is returned along with its offset, rendered as a line of Cairo
+    /// assembly. Immediate values are not listed on their own, but appended to the instruction
+    /// they belong to (e.g. `[ap] = [pc + 1]; ap++ // imm = 42`). Cells that cannot be decoded
+    /// as an instruction (pointers, values that do not fit in 64 bits, invalid encodings) are
+    /// listed as raw data words (`dw <value>`), and unknown cells are skipped.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with [`Error::UnallocatedSegment`] if `segment` has not been
+    /// allocated.
+    pub fn disassemble(&self, segment: usize) -> Result<Vec<(usize, String)>, Error> {
+        let cells = self
+            .memory
+            .segment(segment)
+            .ok_or(Error::UnallocatedSegment)?;
+
+        let mut lines = Vec::new();
+        let mut offset = 0;
+
+        while offset < cells.highest_known_cell() {
+            let Some(cell) = cells.get(offset) else {
+                offset += 1;
+                continue;
+            };
+
+            let instruction = cell
+                .scalar()
+                .and_then(|word| Instruction::try_from_felt(word).ok())
+                .filter(|instruction| DecodedInstruction::new(*instruction).is_ok());
+
+            let Some(instruction) = instruction else {
+                lines.push((offset, format!("dw {cell}")));
+                offset += 1;
+                continue;
+            };
+
+            let pc = Pointer { segment, offset };
+            let immediate = cells.get(offset + 1).and_then(ValueRef::scalar);
+            let mut line = instruction.at(pc, immediate).to_string();
+
+            // The size can't fail, as the instruction has just been decoded successfully.
+            let size = instruction.size().unwrap_or(1);
+            if size == 2 {
+                match cells.get(offset + 1) {
+                    Some(value) => line.push_str(&format!(" // imm = {value}")),
+                    None => line.push_str(" // imm = ?"),
+                }
+            }
+
+            lines.push((offset, line));
+            offset += size;
+        }
+
+        Ok(lines)
+    }
+
+    /// Returns the first registered builtin of type `T`, if any.
+    pub fn builtin<T: Builtin>(&self) -> Option<&T> {
+        self.builtins
+            .builtins
+            .iter()
+            .find_map(|builtin| (&**builtin as &dyn Any).downcast_ref())
+    }
+
+    /// Returns the first registered builtin of type `T`, if any.
+    ///
+    /// This is how hints reach the state of the builtins they feed, such as the signatures
+    /// checked by the [`Ecdsa`](builtin::Ecdsa) builtin.
+    pub fn builtin_mut<T: Builtin>(&mut self) -> Option<&mut T> {
+        self.builtins
+            .builtins
+            .iter_mut()
+            .find_map(|builtin| (&mut **builtin as &mut dyn Any).downcast_mut())
+    }
+
+    /// Returns the outputs written by the program to the segment of the `output` builtin.
+    ///
+    /// The builtin is found using [`Builtin::name`]. Every cell of its segment, from offset
+    /// `0` up to its [size](memory::Segment::size), is returned.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with [`Error::MissingBuiltin`] if no `output` builtin has been
+    /// registered, and with [`Error::Builtin`] if the output segment contains a gap or a
+    /// pointer.
+    pub fn output(&self) -> Result<Vec<Felt>, Error> {
+        let (segment, _) = self
+            .builtins
+            .iter()
+            .find(|(_, builtin)| builtin.name() == Some(builtin::BuiltinName::Output))
+            .ok_or(Error::MissingBuiltin)?;
+
+        // SAFETY:
+        //  We know by invariant of the `CairoVM` that the segments for which a builtin
+        //  is registered are always present.
+        let segment = unsafe { self.memory.segment_unchecked(segment) };
+
+        segment
+            .raw_felt_slice()
+            .map(<[Felt]>::to_vec)
+            .ok_or(Error::Builtin)
+    }
+
+    /// Validates the final state of the memory once the program has finished running.
+    ///
+    /// This gives each registered [`Builtin`] the opportunity to check the global invariants of
+    /// its segment through [`Builtin::finalize`].
+    pub fn finalize(&self) -> Result<(), Error> {
+        for (segment, builtin) in self.builtins.iter() {
+            // SAFETY:
+            //  We know by invariant of the `CairoVM` that the segments for which a builtin
+            //  is registered are always present.
+            let segment = unsafe { self.memory.segment_unchecked(segment) };
+            builtin.finalize(segment)?;
+        }
+
         Ok(())
     }
 }
 
+/// A checkpoint of the state of a [`CairoVM`], created by [`CairoVM::snapshot`].
+///
+/// Builtins are not part of the snapshot. The state held by some of them, such as the
+/// signatures registered in the [`Ecdsa`](builtin::Ecdsa) builtin, must be saved separately.
+///
+/// When the `serde` feature is enabled, snapshots can be serialized, and later resumed with
+/// [`CairoVM::resume`].
+#[derive(Debug, Clone)]
+pub struct VmSnapshot {
+    /// The state of the registers when the snapshot was taken.
+    cpu: Cpu,
+    /// A copy of the memory when the snapshot was taken.
+    memory: Memory,
+    /// The number of steps executed when the snapshot was taken.
+    steps: usize,
+    /// The state of the registers before the first step was executed.
+    initial: Cpu,
+}
+
+impl VmSnapshot {
+    /// Returns the state of the registers when the snapshot was taken.
+    #[inline(always)]
+    pub fn cpu(&self) -> &Cpu {
+        &self.cpu
+    }
+
+    /// Returns the state of the memory when the snapshot was taken.
+    #[inline(always)]
+    pub fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    /// Returns the number of steps executed when the snapshot was taken.
+    #[inline(always)]
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+}
+
+/// Describes an instruction executed by [`CairoVM::step`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepOutcome {
+    /// The OP code of the executed instruction.
+    pub op_code: OpCode,
+    /// The destination of the instruction.
+    pub dst: Operand,
+    /// The first operand of the instruction.
+    pub op0: Operand,
+    /// The second operand of the instruction.
+    pub op1: Operand,
+    /// The state of the registers after the instruction was executed.
+    pub cpu: Cpu,
+}
+
+/// An operand of an instruction executed by [`CairoVM::step`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Operand {
+    /// The address of the operand.
+    pub address: Pointer,
+    /// How the value of the operand was determined.
+    pub origin: OperandOrigin,
+}
+
+impl Operand {
+    /// Creates a new [`Operand`] from the flags of a [`StepContext`].
+    ///
+    /// `asserted` and `deduced` are the flags indicating that the operand was asserted or
+    /// deduced, respectively.
+    #[inline(always)]
+    fn new(
+        address: Pointer,
+        flags: StepContextFlags,
+        asserted: StepContextFlags,
+        deduced: StepContextFlags,
+    ) -> Self {
+        let origin = if flags.contains(asserted) {
+            OperandOrigin::Asserted
+        } else if flags.contains(deduced) {
+            OperandOrigin::Deduced
+        } else {
+            OperandOrigin::Unknown
+        };
+
+        Self { address, origin }
+    }
+}
+
+/// Describes how the value of an [`Operand`] was determined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OperandOrigin {
+    /// The operand was already known before the instruction was executed.
+    Asserted,
+    /// The operand was deduced by the instruction (or by a builtin), and written to memory.
+    Deduced,
+    /// The value of the operand was not needed to execute the instruction.
+    Unknown,
+}
+
+/// Options controlling the execution of a program by [`CairoVM::run`].
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    /// The maximum number of steps to execute before giving up with
+    /// [`Error::StepLimitReached`].
+    ///
+    /// When `None`, the number of steps is not limited.
+    pub max_steps: Option<usize>,
+    /// The number of consecutive steps leaving all registers unchanged after which the program
+    /// is considered stuck in an infinite loop, failing with [`Error::InfiniteLoop`].
+    ///
+    /// This catches programs spinning on a self-jump, which would otherwise only be stopped
+    /// by [`max_steps`](Self::max_steps). Longer loops are not detected.
+    ///
+    /// When `None`, infinite loops are not detected.
+    pub loop_detection: Option<usize>,
+}
+
 /// The builtin manager is responsible for holding a collection of [`Builtin`]s implementations
 /// and running them when necessary.
 ///
@@ -122,12 +1175,53 @@ struct BuiltinManager {
     /// The first segment allocated for the builtins managed by this [`BuiltinManager`].
     min_segment: usize,
     /// The first segment not allocated for the builtins managed by this [`BuiltinManager`].
+    ///
+    /// `max_segment - min_segment` is always the number of managed builtins.
     max_segment: usize,
     /// The builtins managed by this [`BuiltinManager`].
     builtins: Box<[Box<dyn Builtin>]>,
 }
 
 impl BuiltinManager {
+    /// Creates a new [`BuiltinManager`] assigning consecutive segments to the provided
+    /// builtins, starting at `min_segment`.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with [`Error::UnallocatedSegment`] if one of the segments assigned
+    /// to the builtins has not been allocated in `memory`. Otherwise, [`get_runner`] would
+    /// hand out builtins for segments that don't exist.
+    ///
+    /// [`get_runner`]: Self::get_runner
+    pub fn new(
+        min_segment: usize,
+        builtins: Vec<Box<dyn Builtin>>,
+        memory: &Memory,
+    ) -> Result<Self, Error> {
+        let max_segment = min_segment
+            .checked_add(builtins.len())
+            .ok_or(Error::UnallocatedSegment)?;
+
+        if !builtins.is_empty() && max_segment > memory.num_segments() {
+            return Err(Error::UnallocatedSegment);
+        }
+
+        Ok(Self {
+            min_segment,
+            max_segment,
+            builtins: builtins.into_boxed_slice(),
+        })
+    }
+
+    /// Creates a new [`BuiltinManager`] that does not manage any builtin.
+    pub fn empty() -> Self {
+        Self {
+            min_segment: 0,
+            max_segment: 0,
+            builtins: Box::new([]),
+        }
+    }
+
     /// Attempts to get the [`BuiltinRunner`] suitable for deducing a memory cell in the provided
     /// segment.
     pub fn get_runner(&self, segment: usize) -> Option<&dyn Builtin> {
@@ -142,6 +1236,15 @@ impl BuiltinManager {
             Some(unsafe { &**self.builtins.get_unchecked(index) })
         }
     }
+
+    /// Returns an iterator over the builtins managed by this [`BuiltinManager`], along with
+    /// the segment assigned to each of them.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &dyn Builtin)> {
+        self.builtins
+            .iter()
+            .enumerate()
+            .map(|(index, builtin)| (self.min_segment + index, &**builtin))
+    }
 }
 
 impl fmt::Debug for BuiltinManager {
@@ -157,52 +1260,73 @@ impl fmt::Debug for BuiltinManager {
 /// Attempts to fetch an instruction from the provided [`Memory`].
 ///
 /// The returned instruction is the one directly referenced by the **Program Counter** of ths
-/// [`Cpu`] instance. Note that the instruction is not actually decoded in any way, meaning
-/// that it might be missing an eventual associated immediate value.
+/// [`Cpu`] instance. Note that the instruction is not actually decoded in any way.
+///
+/// When the instruction reads its second operand relative to the **Program Counter**, the
+/// cell directly following it (usually holding an immediate value) is fetched as well, while
+/// the segment is at hand. `None` is returned in its place otherwise, or if the cell is unknown.
 ///
 /// # Safety
 ///
 /// The program counter of the [`Cpu`] instance must reference a valid segment within [`Memory`].
 #[inline]
-unsafe fn fetch_instruction(cpu: &Cpu, memory: &Memory) -> Result<Instruction, Error> {
+unsafe fn fetch_instruction(
+    cpu: &Cpu,
+    memory: &Memory,
+) -> Result<(Instruction, Option<Value>), Error> {
     // SAFETY:
     //  The caller must make sure that `memory` contains a segment at the index pointed to by
     //  `self.pc.segment`.
     let segment = unsafe { memory.segment_unchecked(cpu.pc.segment) };
 
-    let instr_cell = segment
-        .get(cpu.pc.offset)
-        .ok_or(Error::ProgramCounterLost)?
-        .scalar()
-        .ok_or(Error::ProgramCounterLost)?;
+    let instr_cell = match segment.get(cpu.pc.offset) {
+        Some(ValueRef::Scalar(value)) => value,
+        Some(ValueRef::Pointer(_)) => return Err(Error::InstructionIsPointer),
+        None => return Err(Error::ProgramCounterLost),
+    };
 
-    let instr = Instruction(instr_cell.to_u64().ok_or(Error::UndefinedInstruction)?);
+    let instr = Instruction::try_from_felt(instr_cell)?;
+
+    let immediate = match instr.op1_source() {
+        Ok(instr::Op1Source::PC) => segment
+            .get(cpu.pc.offset.wrapping_add(1))
+            .map(ValueRef::copied),
+        _ => None,
+    };
+
+    Ok((instr, immediate))
+}
+
+/// Reads the value of an operand of the current instruction, if it is known.
+///
+/// # Invariants
+///
+/// `addr` must be derived from one of the registers of `vm`, ensuring that its segment is
+/// valid by invariant of [`CairoVM`].
+#[inline(always)]
+fn read_operand(addr: Pointer, vm: &CairoVM) -> Option<Value> {
+    // SAFETY:
+    //  We know by invariant of `CairoVM` that the segments referenced by `pc`, `ap` and `fp`
+    //  are always valid, and operand addresses are always derived from them.
+    let segment = unsafe { vm.memory.segment_unchecked(addr.segment) };
 
-    Ok(instr)
+    segment.get(addr.offset).map(ValueRef::copied)
 }
 
 /// Determines what the destination of an instruction is.
 #[inline]
 fn compute_dst(ctx: &mut StepContext, vm: &CairoVM) {
-    match ctx.instruction.dst_register() {
-        instr::DstRegister::AP => ctx.dst_addr = ctx.op0_addr,
-        instr::DstRegister::FP => ctx.dst_addr = ctx.op1_addr,
+    match ctx.instruction.dst_register {
+        instr::DstRegister::AP => ctx.dst_addr = vm.cpu.ap,
+        instr::DstRegister::FP => ctx.dst_addr = vm.cpu.fp,
     }
 
     // We know that this operation won't ever overflow because `ap` and `fp` must
     // both reference values within a segment, which cannot overflow `isize`.
-    ctx.dst_addr.offset = ctx
-        .dst_addr
-        .offset
-        .wrapping_add(ctx.instruction.dst_offset() as isize as usize);
-
-    // SAFETY:
-    //  We know by invariant of `CairoVM` that the segment referenced by `ap` and `fp`
-    //  is always valid.
-    let segment = unsafe { vm.memory.segment_unchecked(ctx.dst_addr.segment) };
+    ctx.dst_addr = ctx.dst_addr.add_signed(ctx.instruction.dst_offset as isize);
 
-    if let Some(val) = segment.get(ctx.dst_addr.offset) {
-        ctx.dst = val.copied();
+    if let Some(val) = read_operand(ctx.dst_addr, vm) {
+        ctx.dst = val;
         ctx.flags.insert(StepContextFlags::DST_ASSERTED);
     }
 }
@@ -210,64 +1334,98 @@ fn compute_dst(ctx: &mut StepContext, vm: &CairoVM) {
 /// Determines what the first operand of an instruction is.
 #[inline]
 fn compute_op0(ctx: &mut StepContext, vm: &CairoVM) {
-    match ctx.instruction.op0_register() {
+    match ctx.instruction.op0_register {
         instr::Op0Register::AP => ctx.op0_addr = vm.cpu.ap,
         instr::Op0Register::FP => ctx.op0_addr = vm.cpu.fp,
     }
 
     // We know that this operation won't ever overflow because `ap` and `fp` must
     // both reference values within a segment, which cannot overflow `isize`.
-    ctx.op0_addr.offset = ctx
-        .op0_addr
-        .offset
-        .wrapping_add(ctx.instruction.op0_offset() as isize as usize);
+    ctx.op0_addr = ctx.op0_addr.add_signed(ctx.instruction.op0_offset as isize);
 
-    // SAFETY:
-    //  We know by invariant of `CairoVM` that the segment referenced by `ap` and `fp`
-    //  is always valid.
-    let segment = unsafe { vm.memory.segment_unchecked(ctx.op0_addr.segment) };
-
-    if let Some(val) = segment.get(ctx.op0_addr.offset) {
-        ctx.op0 = val.copied();
+    if let Some(val) = read_operand(ctx.op0_addr, vm) {
+        ctx.op0 = val;
         ctx.flags.insert(StepContextFlags::OP0_ASSERTED);
     }
 }
 
 /// Determines what the second operand of an instruction is.
 ///
+/// `immediate` is the value of the cell following the instruction, if it was fetched along
+/// with it. It is used in place of a second memory access when the operand refers to it.
+///
 /// This function also updates the `instr_size` field of the provided context.
+///
+/// # Errors
+///
+/// When the operand is relative to `op0`, the value of `op0` is used as its base address. In
+/// that case, this function fails with:
+///
+/// - [`Error::CantDeduceOp0`] if `op0` is not known;
+///
+/// - [`Error::ExpectedPointer`] if `op0` is a scalar;
+///
+/// - [`Error::UnallocatedSegment`] if `op0` references a segment that has not been allocated.
+///
+/// It also fails with [`Error::InvalidImmediate`] if the operand is the immediate value of
+/// the instruction, and that value is a pointer.
 #[inline]
-fn compute_op1(ctx: &mut StepContext, vm: &CairoVM) -> Result<(), Error> {
-    match ctx.instruction.op1_source()? {
-        instr::Op1Source::Op0 => ctx.op1_addr = ctx.op0_addr,
-        instr::Op1Source::PC => {
-            ctx.op1_addr = vm.cpu.pc;
-            ctx.flags.insert(StepContextFlags::SIZE_TWO);
-        }
+fn compute_op1(ctx: &mut StepContext, vm: &CairoVM, immediate: Option<Value>) -> Result<(), Error> {
+    if ctx.instruction.size() == 2 {
+        ctx.flags.insert(StepContextFlags::SIZE_TWO);
+    }
+
+    match ctx.instruction.op1_source {
+        instr::Op1Source::Op0 => ctx.op1_addr = op0_as_base(ctx, vm)?,
+        instr::Op1Source::PC => ctx.op1_addr = vm.cpu.pc,
         instr::Op1Source::FP => ctx.op1_addr = vm.cpu.fp,
         instr::Op1Source::AP => ctx.op1_addr = vm.cpu.ap,
     }
 
-    // We know that this operation won't ever overflow because `ap` and `fp` must
-    // both reference values within a segment, which cannot overflow `isize`.
-    ctx.op1_addr.offset = ctx
-        .op1_addr
-        .offset
-        .wrapping_add(ctx.instruction.op1_offset() as isize as usize);
-
-    // SAFETY:
-    //  We know by invariant of `CairoVM` that the segment referenced by `ap` and `fp`
-    //  is always valid.
-    let segment = unsafe { vm.memory.segment_unchecked(ctx.op1_addr.segment) };
+    // We know that this operation won't ever overflow because the base address must
+    // reference a value within a segment, which cannot overflow `isize`.
+    ctx.op1_addr = ctx.op1_addr.add_signed(ctx.instruction.op1_offset as isize);
+
+    if ctx.instruction.op1_source == instr::Op1Source::PC && ctx.instruction.op1_offset == 1 {
+        // The operand is the immediate value, which has already been fetched. Immediates
+        // are part of the bytecode of the program, which is only made of field elements.
+        match immediate {
+            Some(Value::Pointer(_)) => return Err(Error::InvalidImmediate),
+            Some(val) => {
+                ctx.op1 = val;
+                ctx.flags.insert(StepContextFlags::OP1_ASSERTED);
+            }
+            None => (),
+        }
+        return Ok(());
+    }
 
-    if let Some(val) = segment.get(ctx.op1_addr.offset) {
-        ctx.op1 = val.copied();
+    if let Some(val) = read_operand(ctx.op1_addr, vm) {
+        ctx.op1 = val;
         ctx.flags.insert(StepContextFlags::OP1_ASSERTED);
     }
 
     Ok(())
 }
 
+/// Returns the value of `op0` as the base address of `op1`, for instructions such as
+/// `[ap] = [[fp - 1] + 2]`.
+///
+/// The returned pointer references an allocated segment, upholding the invariant required by
+/// [`read_operand`].
+#[inline]
+fn op0_as_base(ctx: &StepContext, vm: &CairoVM) -> Result<Pointer, Error> {
+    if !ctx.flags.has_op0() {
+        return Err(Error::CantDeduceOp0);
+    }
+
+    match ctx.op0 {
+        Value::Pointer(p) if p.segment < vm.memory.num_segments() => Ok(p),
+        Value::Pointer(_) => Err(Error::UnallocatedSegment),
+        Value::Scalar(_) => Err(Error::ExpectedPointer),
+    }
+}
+
 /// Attempts to deduce the value of a memory cell using one of the registered builtins.
 ///
 /// # Returns
@@ -365,17 +1523,26 @@ fn deduce_op0_from_op1(
 }
 
 /// Deduces the missing operands of a `Call` instruction.
+///
+/// A `call` pushes the frame of the caller on the stack: its destination (`[ap]`) holds the
+/// frame pointer of the caller, and its first operand (`[ap + 1]`) holds the return address.
+/// Deduced operands are written to the memory along with the other deduced values of the
+/// step.
 #[inline]
 fn deduce_call(ctx: &mut StepContext, vm: &CairoVM) -> Result<(), Error> {
     // When in a `Call` instruction, `op0`, must be asserted to
     // `pc + instruction_size`.
-    let fp_plus_size = vm.cpu.fp.wrapping_add(ctx.flags.instruction_size());
+    let return_pc = vm
+        .cpu
+        .pc
+        .checked_add(ctx.flags.instruction_size())
+        .ok_or(Error::PointerTooLarge)?;
     if ctx.flags.has_op0() {
-        if ctx.op0 != fp_plus_size {
+        if ctx.op0 != return_pc {
             return Err(Error::Contradiction);
         }
     } else {
-        ctx.op0 = fp_plus_size.into();
+        ctx.op0 = return_pc.into();
         ctx.flags.insert(StepContextFlags::OP0_DEDUCED);
     }
 
@@ -396,7 +1563,7 @@ fn deduce_call(ctx: &mut StepContext, vm: &CairoVM) -> Result<(), Error> {
 /// Deduces the missing operands of an `AssertEq` instruction.
 #[inline]
 fn deduce_assert_eq(ctx: &mut StepContext) -> Result<(), Error> {
-    let res_logic = ctx.instruction.result_logic()?;
+    let res_logic = ctx.instruction.result_logic;
 
     // With this op-code, we know that the result of the instruction must be
     // asserted to be equal to `dst`.
@@ -443,38 +1610,73 @@ fn deduce_assert_eq(ctx: &mut StepContext) -> Result<(), Error> {
             return Err(Error::CantDeduceDst);
         }
 
-        match res_logic {
-            ResultLogic::Op1 => {
-                // dst = op1
-                ctx.dst = ctx.op1;
-            }
-            ResultLogic::Add => {
-                // dst = op0 + op1
-                ctx.dst = ctx.op0.add(&ctx.op1)?;
-            }
-            ResultLogic::Mul => {
-                // dst = op0 * op1
-                ctx.dst = ctx.op0.multiply(&ctx.op1)?;
-            }
-        }
+        ctx.dst = compute_res(res_logic, &ctx.op0, &ctx.op1)?;
+        ctx.flags.insert(StepContextFlags::DST_DEDUCED);
     }
 
     Ok(())
 }
 
+/// Computes the result of an instruction from the values of its operands.
+fn compute_res(res_logic: ResultLogic, op0: &Value, op1: &Value) -> Result<Value, Error> {
+    match res_logic {
+        // res = op1
+        ResultLogic::Op1 => Ok(*op1),
+        // res = op0 + op1
+        ResultLogic::Add => op0.add(op1),
+        // res = op0 * op1
+        ResultLogic::Mul => op0.multiply(op1),
+    }
+}
+
+/// Verifies the consistency of an instruction whose operands have all been asserted by
+/// existing memory cells.
+///
+/// This is a fast path for [`deduce_from_op_code`]: because no operand needs to be deduced,
+/// builtins don't have to run, and an `AssertEq` instruction only has to check that its
+/// result matches `dst`.
+fn verify_asserted(ctx: &mut StepContext, vm: &CairoVM) -> Result<(), Error> {
+    if ctx.instruction.op_code != instr::OpCode::AssertEq {
+        // Other op-codes don't deduce anything, they only check the operands.
+        return deduce_from_op_code(ctx, vm);
+    }
+
+    let res = compute_res(ctx.instruction.result_logic, &ctx.op0, &ctx.op1)?;
+    if res != ctx.dst {
+        return Err(Error::Contradiction);
+    }
+
+    ctx.res = res;
+    ctx.flags.insert(StepContextFlags::RES_COMPUTED);
+    ctx.next_fp = vm.cpu.fp;
+
+    Ok(())
+}
+
 /// Attempt to deduce missing operands from the OP-Code of the instruction.
 ///
 /// This function also populates the value of `res` with the result of the instruction.
 fn deduce_from_op_code(ctx: &mut StepContext, vm: &CairoVM) -> Result<(), Error> {
-    match ctx.instruction.op_code()? {
+    match ctx.instruction.op_code {
         instr::OpCode::Call => {
             deduce_call(ctx, vm)?;
 
-            ctx.next_fp = vm.cpu.ap.wrapping_add(2);
+            // The callee starts with a fresh frame, right after the frame of the caller that
+            // was just pushed. Registers are controlled by the program, which may have moved
+            // `ap` to the very end of the addressable range.
+            ctx.next_fp = vm.cpu.ap.checked_add(2).ok_or(Error::PointerTooLarge)?;
 
-            if ctx.instruction.ap_update()? != instr::ApUpdate::None {
+            if ctx.instruction.ap_update != instr::ApUpdate::None {
                 return Err(Error::UndefinedApUpdateInCall);
             }
+
+            // The result is the target of the call (absolute or relative to `pc`).
+            if !ctx.flags.has_op1() {
+                return Err(Error::CantDeduceOp1);
+            }
+
+            ctx.res = compute_res(ctx.instruction.result_logic, &ctx.op0, &ctx.op1)?;
+            ctx.flags.insert(StepContextFlags::RES_COMPUTED);
         }
         instr::OpCode::AssertEq => {
             deduce_assert_eq(ctx)?;
@@ -485,13 +1687,45 @@ fn deduce_from_op_code(ctx: &mut StepContext, vm: &CairoVM) -> Result<(), Error>
             ctx.next_fp = vm.cpu.fp;
         }
         instr::OpCode::None => {
+            // The result is only meaningful when it is used to update the registers.
+            let needs_res = matches!(
+                ctx.instruction.pc_update,
+                instr::PcUpdate::AbsoluteJump | instr::PcUpdate::RelativeJump
+            ) || ctx.instruction.ap_update == instr::ApUpdate::AddResult;
+
+            if needs_res {
+                if !ctx.flags.has_op1() {
+                    return Err(Error::CantDeduceOp1);
+                }
+                if ctx.instruction.result_logic != ResultLogic::Op1 && !ctx.flags.has_op0() {
+                    return Err(Error::CantDeduceOp0);
+                }
+
+                ctx.res = compute_res(ctx.instruction.result_logic, &ctx.op0, &ctx.op1)?;
+                ctx.flags.insert(StepContextFlags::RES_COMPUTED);
+            }
+
             ctx.next_fp = vm.cpu.fp;
         }
         instr::OpCode::Ret => {
-            ctx.next_fp = match ctx.op0 {
-                Value::Scalar(_) => return Err(Error::InvalidReturn),
-                Value::Pointer(p) => p,
+            // A `ret` instruction restores the frame pointer of the caller from its
+            // destination (`[fp - 2]`), and jumps to the return address held by its second
+            // operand (`[fp - 1]`). Both must have been written by the matching `call`.
+            if vm.cpu.fp.offset < 2 || !ctx.flags.has_dst() || !ctx.flags.has_op1() {
+                return Err(Error::InvalidReturn);
+            }
+
+            ctx.next_fp = match ctx.dst {
+                Value::Pointer(p) if p.segment < vm.memory.num_segments() => p,
+                _ => return Err(Error::InvalidReturn),
             };
+
+            if ctx.instruction.result_logic != ResultLogic::Op1 && !ctx.flags.has_op0() {
+                return Err(Error::CantDeduceOp0);
+            }
+
+            ctx.res = compute_res(ctx.instruction.result_logic, &ctx.op0, &ctx.op1)?;
+            ctx.flags.insert(StepContextFlags::RES_COMPUTED);
         }
     }
 
@@ -500,23 +1734,28 @@ fn deduce_from_op_code(ctx: &mut StepContext, vm: &CairoVM) -> Result<(), Error>
 
 /// Updates the next **Allocation Pointer** of the provided [`StepContext`].
 fn update_ap(ctx: &mut StepContext, vm: &CairoVM) -> Result<(), Error> {
-    match ctx.instruction.ap_update()? {
+    match ctx.instruction.ap_update {
         instr::ApUpdate::None => {
-            if ctx.instruction.op_code()? == instr::OpCode::Call {
-                ctx.next_ap = vm.cpu.ap.wrapping_add(2);
+            // A `Call` instruction pushes the frame pointer and the return address of the
+            // caller on the stack, which implicitly advances `ap` by two. This is the only
+            // update allowed for that op-code (see `UndefinedApUpdateInCall`).
+            if ctx.instruction.op_code == instr::OpCode::Call {
+                ctx.next_ap = vm.cpu.ap.checked_add(2).ok_or(Error::PointerTooLarge)?;
             } else {
                 ctx.next_ap = vm.cpu.ap;
             }
         }
         instr::ApUpdate::AddResult => {
+            // `ap += res`, where `res` is a (possibly negative) scalar.
             debug_assert!(ctx.flags.has_res());
             match ctx.res {
-                Value::Scalar(_) => return Err(Error::UndefinedApUpdate),
-                Value::Pointer(p) => ctx.next_ap = vm.cpu.ap.wrapping_add(p.offset),
+                Value::Pointer(_) => return Err(Error::UndefinedApUpdate),
+                Value::Scalar(s) => ctx.next_ap = vm.cpu.ap.add_signed(verify::felt_to_offset(&s)?),
             }
         }
         instr::ApUpdate::Increment => {
-            ctx.next_ap = vm.cpu.ap.wrapping_add(1);
+            // `ap++`, regardless of the result of the instruction.
+            ctx.next_ap = vm.cpu.ap.checked_add(1).ok_or(Error::PointerTooLarge)?;
         }
     }
 
@@ -525,7 +1764,7 @@ fn update_ap(ctx: &mut StepContext, vm: &CairoVM) -> Result<(), Error> {
 
 /// Updates the next **Program Counter** of the provided [`StepContext`].
 fn update_pc(ctx: &mut StepContext, vm: &CairoVM) -> Result<(), Error> {
-    match ctx.instruction.pc_update()? {
+    match ctx.instruction.pc_update {
         instr::PcUpdate::Regular => {
             ctx.next_pc = vm.cpu.pc.wrapping_add(ctx.flags.instruction_size());
         }
@@ -534,29 +1773,39 @@ fn update_pc(ctx: &mut StepContext, vm: &CairoVM) -> Result<(), Error> {
                 Value::Pointer(p) => p,
                 Value::Scalar(_) => return Err(Error::InvalidAbsoluteJump),
             };
+
+            // The segment referenced by the program counter must remain valid. Reporting this
+            // at jump time gives a clearer diagnostic than failing on the next fetch.
+            if ctx.next_pc.segment >= vm.memory.num_segments() {
+                return Err(Error::ProgramCounterLost);
+            }
         }
         instr::PcUpdate::RelativeJump => {
             ctx.next_pc = match ctx.res {
                 Value::Pointer(_) => return Err(Error::InvalidRelativeJump),
-                Value::Scalar(s) => vm
-                    .cpu
-                    .pc
-                    .wrapping_add(s.to_usize().ok_or(Error::PointerTooLarge)?),
+                Value::Scalar(s) => vm.cpu.pc.add_signed(verify::felt_to_offset(&s)?),
             };
         }
         instr::PcUpdate::ConditionalJump => {
-            if ctx.dst.is_zero() {
-                vm.cpu.pc.wrapping_add(ctx.flags.instruction_size());
+            // Nothing deduces the condition of a jump: it must already be known.
+            if !ctx.flags.has_dst() {
+                return Err(Error::CantDeduceDst);
+            }
+
+            // Pointers are never zero, so a pointer `dst` always takes the jump (see
+            // `PcUpdate::ConditionalJump`).
+            ctx.next_pc = if ctx.dst.is_zero() {
+                vm.cpu.pc.wrapping_add(ctx.flags.instruction_size())
             } else {
-                debug_assert!(ctx.flags.has_res());
-                match ctx.res {
+                if !ctx.flags.has_op1() {
+                    return Err(Error::CantDeduceOp1);
+                }
+
+                match ctx.op1 {
                     Value::Pointer(_) => return Err(Error::InvalidRelativeJump),
-                    Value::Scalar(s) => vm
-                        .cpu
-                        .pc
-                        .wrapping_add(s.to_usize().ok_or(Error::PointerTooLarge)?),
-                };
-            }
+                    Value::Scalar(s) => vm.cpu.pc.add_signed(verify::felt_to_offset(&s)?),
+                }
+            };
         }
     }
 
@@ -565,24 +1814,30 @@ fn update_pc(ctx: &mut StepContext, vm: &CairoVM) -> Result<(), Error> {
 
 /// Applies the modifications to the memory.
 fn apply_modifications(ctx: &mut StepContext, vm: &mut CairoVM) -> Result<(), Error> {
-    debug_assert!(ctx.flags.has_dst());
-    debug_assert!(ctx.flags.has_op0());
-    debug_assert!(ctx.flags.has_op1());
-
+    // Only deduced values need to be written, asserted ones come from memory in the first
+    // place.
+    //
     // SAFETY:
     //  We know that the segments referenced by `dst_addr`, `op0_addr` and `op1_addr`
     //  are always valid by invariant of `CairoVM`.
-    if ctx.flags.has_dst() {
-        let dst_segment = unsafe { vm.memory.segment_unchecked_mut(ctx.dst_addr.segment) };
-        dst_segment.set(ctx.dst_addr.offset, ctx.dst.as_ref())?;
+    if ctx.flags.contains(StepContextFlags::DST_DEDUCED) {
+        unsafe { vm.memory.set_unchecked(ctx.dst_addr, ctx.dst.as_ref())? };
     }
-    if ctx.flags.has_op0() {
-        let op0_segment = unsafe { vm.memory.segment_unchecked_mut(ctx.op0_addr.segment) };
-        op0_segment.set(ctx.op0_addr.offset, ctx.op0.as_ref())?;
+    if ctx.flags.contains(StepContextFlags::OP0_DEDUCED) {
+        unsafe { vm.memory.set_unchecked(ctx.op0_addr, ctx.op0.as_ref())? };
+    }
+    if ctx.flags.contains(StepContextFlags::OP1_DEDUCED) {
+        unsafe { vm.memory.set_unchecked(ctx.op1_addr, ctx.op1.as_ref())? };
     }
-    if ctx.flags.has_op1() {
-        let op1_segment = unsafe { vm.memory.segment_unchecked_mut(ctx.op1_addr.segment) };
-        op1_segment.set(ctx.op1_addr.offset, ctx.op1.as_ref())?;
+
+    // SAFETY:
+    //  The operands are located in segments that are valid by invariant of `CairoVM`, and so
+    //  is the instruction, which is referenced by the program counter.
+    unsafe {
+        vm.memory.mark_accessed_unchecked(vm.cpu.pc);
+        vm.memory.mark_accessed_unchecked(ctx.dst_addr);
+        vm.memory.mark_accessed_unchecked(ctx.op0_addr);
+        vm.memory.mark_accessed_unchecked(ctx.op1_addr);
     }
 
     // Update the registers.
@@ -619,6 +1874,11 @@ bitflags! {
         const RES_COMPUTED = 1 << 6;
         /// The instruction has a size of two cells instead of one.
         const SIZE_TWO = 1 << 7;
+
+        /// All operands of the instruction were asserted by existing memory cells.
+        const ALL_ASSERTED = Self::DST_ASSERTED.bits()
+            | Self::OP0_ASSERTED.bits()
+            | Self::OP1_ASSERTED.bits();
     }
 }
 
@@ -626,19 +1886,19 @@ impl StepContextFlags {
     /// Returns whether the destination of the instruction is known.
     #[inline(always)]
     pub const fn has_dst(self) -> bool {
-        self.contains(Self::DST_ASSERTED.union(Self::DST_DEDUCED))
+        self.intersects(Self::DST_ASSERTED.union(Self::DST_DEDUCED))
     }
 
     /// Returns whether the first operand of the instruction is known.
     #[inline(always)]
     pub const fn has_op0(self) -> bool {
-        self.contains(Self::OP0_ASSERTED.union(Self::OP0_DEDUCED))
+        self.intersects(Self::OP0_ASSERTED.union(Self::OP0_DEDUCED))
     }
 
     /// Returns whether the second operand of the instruction is known.
     #[inline(always)]
     pub const fn has_op1(self) -> bool {
-        self.contains(Self::OP1_ASSERTED.union(Self::OP1_DEDUCED))
+        self.intersects(Self::OP1_ASSERTED.union(Self::OP1_DEDUCED))
     }
 
     /// Returns whether the result of the instruction is known.
@@ -661,7 +1921,7 @@ impl StepContextFlags {
 /// Stores a state that must be kept around while decoding an instruction.
 struct StepContext {
     /// The instruction being decoded.
-    pub instruction: Instruction,
+    pub instruction: DecodedInstruction,
     /// The destination address of the instruction being decoded.
     pub dst_addr: Pointer,
     /// The value of the destination of the instruction being decoded, if known.
@@ -698,7 +1958,7 @@ impl StepContext {
     /// All fields are initialized to dummy values and should be properly set before using the
     /// context.
     #[inline]
-    pub const fn initial(instruction: Instruction) -> Self {
+    pub const fn initial(instruction: DecodedInstruction) -> Self {
         Self {
             instruction,
             dst_addr: Pointer {
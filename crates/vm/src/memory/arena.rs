@@ -0,0 +1,129 @@
+//! Defines the [`Arena`] type, a bump allocator used to back the initial buffers of segments.
+
+use alloc::rc::Rc;
+use core::alloc::Layout;
+use core::fmt;
+use core::ptr::NonNull;
+
+/// The alignment of the chunks allocated by an [`Arena`].
+///
+/// This must be at least the alignment of anything allocated within the arena.
+const CHUNK_ALIGN: usize = 16;
+
+/// A block of memory allocated by an [`Arena`].
+///
+/// Segments whose buffers are located within a chunk keep a reference to it, ensuring that the
+/// memory remains valid for as long as it is used, even if the [`Arena`] itself is dropped.
+pub(super) struct Chunk {
+    /// The start of the allocated block.
+    ptr: NonNull<u8>,
+    /// The layout that was used to allocate the block.
+    layout: Layout,
+}
+
+impl Chunk {
+    /// Allocates a new [`Chunk`] of `size` bytes.
+    ///
+    /// `None` is returned if the allocation fails.
+    fn allocate(size: usize) -> Option<Self> {
+        let layout = Layout::from_size_align(size.max(1), CHUNK_ALIGN).ok()?;
+
+        // SAFETY:
+        //  The size of the layout is non-zero.
+        let ptr = NonNull::new(unsafe { alloc::alloc::alloc(layout) })?;
+
+        Some(Self { ptr, layout })
+    }
+}
+
+impl Drop for Chunk {
+    fn drop(&mut self) {
+        // SAFETY:
+        //  The pointer has been allocated with the global allocator using this exact layout.
+        unsafe { alloc::alloc::dealloc(self.ptr.as_ptr(), self.layout) }
+    }
+}
+
+/// A bump allocator owned by a [`Memory`](super::Memory).
+///
+/// Allocating many small segments with the global allocator creates a lot of allocation churn.
+/// Instead, an arena allocates large chunks of memory upfront and hands out slices of them
+/// by simply bumping an offset. Memory handed out by the arena is never reused: it is released
+/// once the chunk it belongs to is no longer referenced by any segment.
+pub(super) struct Arena {
+    /// The chunk currently used to serve allocations.
+    ///
+    /// No chunk is allocated until the first allocation is requested.
+    chunk: Option<Rc<Chunk>>,
+    /// The number of bytes of the current chunk that have already been handed out.
+    used: usize,
+    /// The size of the chunks allocated by the arena, in bytes.
+    chunk_size: usize,
+}
+
+impl Arena {
+    /// Creates a new [`Arena`] allocating chunks of `chunk_size` bytes.
+    ///
+    /// No memory is allocated by this function.
+    pub const fn new(chunk_size: usize) -> Self {
+        Self {
+            chunk: None,
+            used: 0,
+            chunk_size,
+        }
+    }
+
+    /// Allocates a block of memory with the provided layout.
+    ///
+    /// On success, the chunk the block belongs to is returned along with a pointer to the
+    /// block. The block remains valid for as long as the chunk is kept alive.
+    ///
+    /// `None` is returned if the layout cannot be served by the arena, or if a new chunk
+    /// needed to be allocated and the allocation failed.
+    pub fn allocate(&mut self, layout: Layout) -> Option<(Rc<Chunk>, NonNull<u8>)> {
+        if layout.align() > CHUNK_ALIGN {
+            return None;
+        }
+
+        if let Some(chunk) = &self.chunk {
+            let start = self.used.checked_next_multiple_of(layout.align())?;
+            let end = start.checked_add(layout.size())?;
+
+            if end <= chunk.layout.size() {
+                self.used = end;
+
+                // SAFETY:
+                //  `start` is within the bounds of the chunk.
+                let ptr = unsafe { NonNull::new_unchecked(chunk.ptr.as_ptr().add(start)) };
+                return Some((chunk.clone(), ptr));
+            }
+        }
+
+        // The current chunk is exhausted (or was never allocated). Previous chunks remain
+        // alive for as long as segments reference them.
+        let chunk = Rc::new(Chunk::allocate(self.chunk_size.max(layout.size()))?);
+        let ptr = chunk.ptr;
+        self.used = layout.size();
+        self.chunk = Some(chunk.clone());
+
+        Some((chunk, ptr))
+    }
+}
+
+impl Clone for Arena {
+    /// Creates a new empty [`Arena`] with the same configuration.
+    ///
+    /// The memory handed out by the original arena is not shared with the clone.
+    fn clone(&self) -> Self {
+        Self::new(self.chunk_size)
+    }
+}
+
+impl fmt::Debug for Arena {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Arena")
+            .field("used", &self.used)
+            .field("chunk_size", &self.chunk_size)
+            .finish()
+    }
+}
@@ -19,13 +19,28 @@
 //! segments is not decided until the program has finished running, meaning that a program can
 //! never rely on the final location of a segment.
 
+use alloc::vec::Vec;
+
+use starknet_types_core::felt::Felt;
+
+use crate::error::Error;
+
+use self::arena::Arena;
+
+mod arena;
 mod pointer;
+mod relocate;
 mod segment;
 mod value;
+#[cfg(feature = "std")]
+mod view;
 
 pub use self::pointer::*;
+pub use self::relocate::*;
 pub use self::segment::*;
 pub use self::value::*;
+#[cfg(feature = "std")]
+pub use self::view::*;
 
 /// Represents the memory of the Cairo virtual machine.
 ///
@@ -34,9 +49,507 @@ pub use self::value::*;
 pub struct Memory {
     /// The segments that have been initialized in the memory.
     segments: Vec<Segment>,
+
+    /// The bump allocator backing the initial buffers of new segments, if any.
+    arena: Option<Arena>,
+
+    /// The maximum number of cells the segments of the memory may span in total, if any.
+    ///
+    /// See [`Memory::set_max_cells`].
+    max_cells: Option<usize>,
+    /// The number of cells spanned by the segments of the memory, as tracked since the last
+    /// call to [`Memory::set_max_cells`].
+    used_cells: usize,
+}
+
+impl<'a> IntoIterator for &'a Memory {
+    type Item = (usize, &'a Segment);
+    type IntoIter = core::iter::Enumerate<core::slice::Iter<'a, Segment>>;
+
+    /// Returns an iterator over the segments of the memory, along with their index.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rayquaza_vm::memory::Memory;
+    ///
+    /// let mut memory = Memory::default();
+    /// memory.allocate_segment();
+    /// memory.allocate_segment();
+    ///
+    /// for (index, segment) in &memory {
+    ///     assert_eq!(segment.size(), 0);
+    ///     assert!(index < 2);
+    /// }
+    /// ```
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.segments.iter().enumerate()
+    }
+}
+
+/// Pushes the cells of `segment` that differ between `left` and `right` to `differences`.
+///
+/// See [`Memory::diff`].
+fn diff_segment(
+    segment: usize,
+    left: Option<&Segment>,
+    right: Option<&Segment>,
+    differences: &mut Vec<(Pointer, Option<Value>, Option<Value>)>,
+) {
+    let length = left
+        .map_or(0, Segment::highest_known_cell)
+        .max(right.map_or(0, Segment::highest_known_cell));
+
+    for offset in 0..length {
+        let left = left.and_then(|s| s.get(offset));
+        let right = right.and_then(|s| s.get(offset));
+
+        if left != right {
+            differences.push((
+                Pointer { segment, offset },
+                left.map(ValueRef::copied),
+                right.map(ValueRef::copied),
+            ));
+        }
+    }
+}
+
+/// Statistics about the footprint of a [`Memory`], as returned by [`Memory::stats`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryStats {
+    /// The number of segments that have been allocated.
+    pub segments: usize,
+    /// The number of cells the segments can hold without growing.
+    pub capacity: usize,
+    /// The number of cells spanned by the segments, up to their highest known cell.
+    ///
+    /// See [`Memory::total_cells`].
+    pub cells: usize,
+    /// The number of known cells.
+    pub used: usize,
+    /// The number of unknown cells within the span of the segments.
+    pub holes: usize,
 }
 
+/// The outcome of loading data into a [`Memory`], as returned by [`Memory::load_data`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoadedData {
+    /// A pointer to the cell directly following the loaded data.
+    ///
+    /// This is where the next run of data should be loaded to be contiguous with this one.
+    pub end: Pointer,
+    /// The number of cells that have been written, and that were already known.
+    pub cells: AssertedRun,
+}
+
+/// The initial capacity of the segments allocated from the arena of a [`Memory`].
+const ARENA_SEGMENT_CAPACITY: usize = 16;
+
 impl Memory {
+    /// Creates a new empty [`Memory`] whose segments draw their initial buffers from a bump
+    /// allocator, rather than from the global allocator.
+    ///
+    /// This reduces the allocation churn of programs allocating many small segments. Segments
+    /// that outgrow their initial capacity are moved to the global allocator as usual.
+    ///
+    /// `capacity_hint` is the number of memory cells the arena should be able to hold before
+    /// it needs to allocate another block of memory. No memory is allocated until the first
+    /// segment is.
+    pub fn with_arena(capacity_hint: usize) -> Self {
+        let cell_size = core::mem::size_of::<Felt>() + 1;
+
+        Self {
+            arena: Some(Arena::new(capacity_hint.saturating_mul(cell_size))),
+            ..Self::default()
+        }
+    }
+
+    /// Limits the total number of cells the segments of the memory may span, or removes the
+    /// limit when `None` is provided.
+    ///
+    /// A segment spans every cell up to its highest known cell, whether or not the cells in
+    /// between are known. Once the limit is set, writes that would make the segments span
+    /// more than `limit` cells in total fail with [`Error::OutOfMemory`], no matter how much
+    /// memory is actually available. This protects hosts running untrusted programs from
+    /// memory exhaustion, independently of the growth strategy of the segments.
+    ///
+    /// # Notes
+    ///
+    /// The limit applies to the writes performed through [`Memory::assert_eq`] and by the
+    /// virtual machine itself. Writes performed directly on a segment (through
+    /// [`segment_unchecked_mut`](Self::segment_unchecked_mut)) are not accounted for until the
+    /// next call to this function.
+    pub fn set_max_cells(&mut self, limit: Option<usize>) {
+        self.max_cells = limit;
+        self.used_cells = self.segments.iter().map(Segment::highest_known_cell).sum();
+    }
+
+    /// Returns the maximum number of cells the segments of the memory may span in total, if
+    /// any.
+    ///
+    /// See [`set_max_cells`](Self::set_max_cells).
+    #[inline(always)]
+    pub fn max_cells(&self) -> Option<usize> {
+        self.max_cells
+    }
+
+    /// Returns the number of cells the segments of the memory would span once `pointer` has
+    /// been written to, failing if this would exceed the limit set by
+    /// [`set_max_cells`](Self::set_max_cells).
+    ///
+    /// # Safety
+    ///
+    /// The segment referenced by `pointer` must have been allocated.
+    unsafe fn used_cells_after(&self, pointer: Pointer) -> Result<usize, Error> {
+        let Some(max_cells) = self.max_cells else {
+            return Ok(self.used_cells);
+        };
+
+        // SAFETY:
+        //  The caller must ensure that the segment has been allocated.
+        let length = unsafe { self.segment_unchecked(pointer.segment) }.highest_known_cell();
+        if pointer.offset < length {
+            return Ok(self.used_cells);
+        }
+
+        (pointer.offset - length)
+            .checked_add(1)
+            .and_then(|added| self.used_cells.checked_add(added))
+            .filter(|&used| used <= max_cells)
+            .ok_or(Error::OutOfMemory)
+    }
+
+    /// Sets the value of the memory cell referenced by the provided [`Pointer`], eventually
+    /// overriding its previous value.
+    ///
+    /// This is [`Segment::set`], accounting for the limit set by
+    /// [`set_max_cells`](Self::set_max_cells).
+    ///
+    /// # Safety
+    ///
+    /// The segment referenced by `pointer` must have been allocated.
+    pub(crate) unsafe fn set_unchecked(
+        &mut self,
+        pointer: Pointer,
+        value: ValueRef,
+    ) -> Result<(), Error> {
+        // SAFETY:
+        //  The caller must ensure that the segment has been allocated.
+        unsafe {
+            let used_cells = self.used_cells_after(pointer)?;
+            self.segment_unchecked_mut(pointer.segment)
+                .set(pointer.offset, value)?;
+            self.used_cells = used_cells;
+        }
+
+        Ok(())
+    }
+
+    /// Marks the memory cell referenced by the provided [`Pointer`] as accessed by the program.
+    ///
+    /// See [`Segment::is_accessed`].
+    ///
+    /// # Safety
+    ///
+    /// The segment referenced by `pointer` must have been allocated.
+    #[inline(always)]
+    pub(crate) unsafe fn mark_accessed_unchecked(&mut self, pointer: Pointer) {
+        // SAFETY:
+        //  The caller must ensure that the segment has been allocated.
+        unsafe { self.segment_unchecked_mut(pointer.segment) }.mark_accessed(pointer.offset);
+    }
+
+    /// Allocates a new empty [`Segment`] in the memory, returning its index.
+    ///
+    /// The final location of the segment within the address space of the virtual machine is
+    /// only decided once the memory is [relocated](Self::relocate).
+    pub fn allocate_segment(&mut self) -> usize {
+        let segment = self
+            .arena
+            .as_mut()
+            .and_then(|arena| Segment::new_in(arena, ARENA_SEGMENT_CAPACITY))
+            .unwrap_or_default();
+
+        self.segments.push(segment);
+        self.segments.len() - 1
+    }
+
+    /// Adds an existing [`Segment`] to the memory, returning its index.
+    ///
+    /// This is mostly useful to build a memory from known content, along with
+    /// [`Segment::from_values`]. The cells of the segment count towards the limit set by
+    /// [`set_max_cells`](Self::set_max_cells), but pushing a segment never fails.
+    pub fn push_segment(&mut self, segment: Segment) -> usize {
+        self.used_cells = self.used_cells.saturating_add(segment.size());
+        self.segments.push(segment);
+        self.segments.len() - 1
+    }
+
+    /// Returns the number of segments that have been allocated in the memory.
+    #[inline(always)]
+    pub fn num_segments(&self) -> usize {
+        self.segments.len()
+    }
+
+    /// Returns the number of cells spanned by the segments of the memory.
+    ///
+    /// This is the sum of the [sizes](Segment::size) of the segments, including the unknown
+    /// cells below their highest known cell.
+    pub fn total_cells(&self) -> usize {
+        self.segments.iter().map(Segment::size).sum()
+    }
+
+    /// Computes statistics about the footprint of the memory.
+    ///
+    /// This requires going through every cell of the memory.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rayquaza_vm::memory::{Memory, Pointer, ValueRef};
+    /// use starknet_types_core::felt::Felt;
+    ///
+    /// let mut memory = Memory::default();
+    /// let segment = memory.allocate_segment();
+    /// memory
+    ///     .assert_eq(Pointer { segment, offset: 3 }, ValueRef::Scalar(&Felt::ONE))
+    ///     .unwrap();
+    ///
+    /// let stats = memory.stats();
+    /// assert_eq!(stats.cells, 4);
+    /// assert_eq!(stats.used, 1);
+    /// assert_eq!(stats.holes, 3);
+    /// ```
+    pub fn stats(&self) -> MemoryStats {
+        let mut stats = MemoryStats {
+            segments: self.segments.len(),
+            ..MemoryStats::default()
+        };
+
+        for segment in &self.segments {
+            let used = segment.used();
+            stats.capacity += segment.capacity();
+            stats.cells += segment.size();
+            stats.used += used;
+            stats.holes += segment.size() - used;
+        }
+
+        stats
+    }
+
+    /// Returns the value of the memory cell referenced by the provided [`Pointer`], if it has
+    /// been asserted to a specific value.
+    ///
+    /// `None` is returned if the cell is unknown or if its segment has not been allocated.
+    #[inline]
+    pub fn get(&self, pointer: Pointer) -> Option<ValueRef<'_>> {
+        self.segments.get(pointer.segment)?.get(pointer.offset)
+    }
+
+    /// Marks the memory cell referenced by the provided [`Pointer`] as part of the public memory
+    /// of the program.
+    ///
+    /// See [`Segment::mark_public`].
+    ///
+    /// # Errors
+    ///
+    /// This function fails with [`Error::UnallocatedSegment`] if the segment referenced by
+    /// `pointer` has not been allocated, and with [`Error::InvalidPublicMemory`] if the cell is
+    /// unknown.
+    pub fn mark_public(&mut self, pointer: Pointer) -> Result<(), Error> {
+        self.segments
+            .get_mut(pointer.segment)
+            .ok_or(Error::UnallocatedSegment)?
+            .mark_public(pointer.offset)
+    }
+
+    /// Returns every known cell marked as part of the public memory, along with its value.
+    ///
+    /// This is the memory that must be included in the public input of a proof of the
+    /// execution. It usually covers the program segment and the output segment. Cells are
+    /// sorted by address.
+    pub fn public_memory(&self) -> Vec<(Pointer, Value)> {
+        self.into_iter()
+            .flat_map(|(segment, cells)| {
+                cells
+                    .public_cells()
+                    .map(move |(offset, value)| (Pointer { segment, offset }, value.copied()))
+            })
+            .collect()
+    }
+
+    /// Returns every known cell holding a pointer, along with the pointer it holds.
+    ///
+    /// Each entry is made of the address of the cell, followed by its value. This can be used
+    /// to build the graph of references between segments.
+    pub fn pointer_cells(&self) -> Vec<(Pointer, Pointer)> {
+        let mut cells = Vec::new();
+
+        for (index, segment) in self {
+            for offset in 0..segment.highest_known_cell() {
+                if let Some(ValueRef::Pointer(target)) = segment.get(offset) {
+                    let address = Pointer {
+                        segment: index,
+                        offset,
+                    };
+                    cells.push((address, *target));
+                }
+            }
+        }
+
+        cells
+    }
+
+    /// Compares this memory with `other` cell by cell, returning every cell that differs.
+    ///
+    /// Each entry is made of the address of the cell, followed by its value in `self` and its
+    /// value in `other` (`None` when the cell is unknown, or when its segment has not been
+    /// allocated in that memory). Entries are sorted by address.
+    ///
+    /// This is useful to pin down where two executions of the same program diverge, for
+    /// example when validating the virtual machine against a reference implementation.
+    pub fn diff(&self, other: &Memory) -> Vec<(Pointer, Option<Value>, Option<Value>)> {
+        let mut differences = Vec::new();
+
+        for (segment, left) in self {
+            diff_segment(
+                segment,
+                Some(left),
+                other.segment(segment),
+                &mut differences,
+            );
+        }
+        for (segment, right) in other.into_iter().skip(self.segments.len()) {
+            diff_segment(segment, None, Some(right), &mut differences);
+        }
+
+        differences
+    }
+
+    /// Releases the capacity of every segment that is not used by any known cell.
+    ///
+    /// This is useful to reduce the memory footprint of a finished execution that is kept
+    /// around.
+    pub fn shrink_to_fit(&mut self) -> Result<(), Error> {
+        self.segments
+            .iter_mut()
+            .try_for_each(Segment::shrink_to_fit)
+    }
+
+    /// Attempts to assert that the memory cell referenced by the provided [`Pointer`] has a
+    /// given value.
+    ///
+    /// If the cell is still unknown, it is asserted to the given value. If it is known and has a
+    /// different value, [`Error::Contradiction`] is returned.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with [`Error::UnallocatedSegment`] if the segment referenced by
+    /// `pointer` has not been allocated, and with [`Error::OutOfMemory`] if the write would
+    /// exceed the limit set by [`set_max_cells`](Self::set_max_cells).
+    pub fn assert_eq(&mut self, pointer: Pointer, value: ValueRef) -> Result<(), Error> {
+        if pointer.segment >= self.segments.len() {
+            return Err(Error::UnallocatedSegment);
+        }
+
+        // SAFETY:
+        //  We just made sure that the segment has been allocated.
+        unsafe {
+            let used_cells = self.used_cells_after(pointer)?;
+            self.segment_unchecked_mut(pointer.segment)
+                .assert_eq(pointer.offset, value)?;
+            self.used_cells = used_cells;
+        }
+
+        Ok(())
+    }
+
+    /// Asserts that the contiguous run of cells starting at `start` holds the provided values.
+    ///
+    /// This is [`Segment::assert_run`], accounting for the limit set by
+    /// [`set_max_cells`](Self::set_max_cells). The returned [`LoadedData`] holds a pointer to
+    /// the cell following the run, as well as the number of cells that were written.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with [`Error::UnallocatedSegment`] if the segment referenced by
+    /// `start` has not been allocated, with [`Error::OutOfMemory`] if the run would exceed the
+    /// limit set by [`set_max_cells`](Self::set_max_cells), and with [`Error::Contradiction`]
+    /// if one of the cells was already known to hold a different value.
+    pub fn load_data(&mut self, start: Pointer, data: &[Value]) -> Result<LoadedData, Error> {
+        self.load_with(start, data.len(), |segment| {
+            segment.assert_run(start.offset, data)
+        })
+    }
+
+    /// Asserts that the contiguous run of cells starting at `start` holds the provided scalar
+    /// values.
+    ///
+    /// This is the typical way to load program bytecode, which is only made of field elements:
+    /// unlike [`load_data`](Self::load_data), it does not require wrapping each of them in a
+    /// [`Value`].
+    ///
+    /// # Errors
+    ///
+    /// This function fails with the same errors as [`load_data`](Self::load_data).
+    pub fn load_felt_data(&mut self, start: Pointer, data: &[Felt]) -> Result<LoadedData, Error> {
+        self.load_with(start, data.len(), |segment| {
+            segment.assert_felt_run(start.offset, data)
+        })
+    }
+
+    /// Writes a run of `len` cells starting at `start` using the provided function, accounting
+    /// for the limit set by [`set_max_cells`](Self::set_max_cells).
+    fn load_with<F>(&mut self, start: Pointer, len: usize, write: F) -> Result<LoadedData, Error>
+    where
+        F: FnOnce(&mut Segment) -> Result<AssertedRun, Error>,
+    {
+        if start.segment >= self.segments.len() {
+            return Err(Error::UnallocatedSegment);
+        }
+
+        let end = start.checked_add(len).ok_or(Error::OutOfMemory)?;
+        if len == 0 {
+            return Ok(LoadedData {
+                end,
+                cells: AssertedRun::default(),
+            });
+        }
+
+        // SAFETY:
+        //  We just made sure that the segment has been allocated.
+        let cells = unsafe {
+            self.used_cells_after(end.wrapping_sub(1))?;
+
+            let segment = self.segment_unchecked_mut(start.segment);
+            let before = segment.highest_known_cell();
+            let result = write(segment);
+            let after = segment.highest_known_cell();
+
+            // The run may have been partially written if it failed.
+            self.used_cells = self.used_cells.saturating_add(after - before);
+            result?
+        };
+
+        Ok(LoadedData { end, cells })
+    }
+
+    /// Returns a [`Segment`] of the memory, or `None` if it has not been allocated.
+    #[inline]
+    pub fn segment(&self, segment: usize) -> Option<&Segment> {
+        self.segments.get(segment)
+    }
+
+    /// Returns a mutable [`Segment`] of the memory, or `None` if it has not been allocated.
+    ///
+    /// Cells written directly through the segment are not accounted for by the limit set with
+    /// [`set_max_cells`](Self::set_max_cells) until it is set again.
+    #[inline]
+    pub fn segment_mut(&mut self, segment: usize) -> Option<&mut Segment> {
+        self.segments.get_mut(segment)
+    }
+
     /// Returns a [`Segment`] of the memory.
     ///
     /// # Safety
@@ -57,3 +570,354 @@ impl Memory {
         unsafe { self.segments.get_unchecked_mut(segment) }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The value written at `offset` of `segment` by the tests.
+    fn cell(segment: usize, offset: usize) -> Value {
+        Value::Scalar(Felt::from(segment * 100 + offset))
+    }
+
+    #[test]
+    fn arena_backed_segments() {
+        // Small enough for the arena to allocate several chunks.
+        let mut memory = Memory::with_arena(2 * ARENA_SEGMENT_CAPACITY);
+        let segments: Vec<usize> = (0..8).map(|_| memory.allocate_segment()).collect();
+
+        for &segment in &segments {
+            let capacity = memory.segment(segment).unwrap().capacity();
+            assert_eq!(capacity, ARENA_SEGMENT_CAPACITY);
+
+            for offset in 0..ARENA_SEGMENT_CAPACITY {
+                let pointer = Pointer { segment, offset };
+                memory
+                    .assert_eq(pointer, cell(segment, offset).as_ref())
+                    .unwrap();
+            }
+        }
+
+        // Grow the first segment beyond its initial arena buffer.
+        let grown = Pointer {
+            segment: segments[0],
+            offset: 4 * ARENA_SEGMENT_CAPACITY,
+        };
+        memory
+            .assert_eq(grown, ValueRef::Scalar(&Felt::TWO))
+            .unwrap();
+        assert!(memory.segment(grown.segment).unwrap().capacity() > ARENA_SEGMENT_CAPACITY);
+
+        // The clone does not share the buffers of the memory it was taken from.
+        let clone = memory.clone();
+        drop(memory);
+
+        for &segment in &segments {
+            for offset in 0..ARENA_SEGMENT_CAPACITY {
+                let pointer = Pointer { segment, offset };
+                assert_eq!(clone.get(pointer), Some(cell(segment, offset).as_ref()));
+            }
+        }
+        assert_eq!(clone.get(grown), Some(ValueRef::Scalar(&Felt::TWO)));
+    }
+
+    #[test]
+    fn shrink_arena_backed_segments() {
+        let mut memory = Memory::with_arena(4 * ARENA_SEGMENT_CAPACITY);
+        let small = memory.allocate_segment();
+        let grown = memory.allocate_segment();
+
+        let pointer = Pointer {
+            segment: small,
+            offset: 3,
+        };
+        memory.assert_eq(pointer, cell(small, 3).as_ref()).unwrap();
+        for offset in 0..2 * ARENA_SEGMENT_CAPACITY + 1 {
+            let pointer = Pointer {
+                segment: grown,
+                offset,
+            };
+            memory
+                .assert_eq(pointer, cell(grown, offset).as_ref())
+                .unwrap();
+        }
+
+        memory.shrink_to_fit().unwrap();
+
+        // Segments still drawn from the arena keep their capacity, the others are shrunk.
+        let small_segment = memory.segment(small).unwrap();
+        assert_eq!(small_segment.capacity(), ARENA_SEGMENT_CAPACITY);
+        let grown_segment = memory.segment(grown).unwrap();
+        assert_eq!(grown_segment.capacity(), 2 * ARENA_SEGMENT_CAPACITY + 1);
+
+        assert_eq!(memory.get(pointer), Some(cell(small, 3).as_ref()));
+        for offset in 0..2 * ARENA_SEGMENT_CAPACITY + 1 {
+            let pointer = Pointer {
+                segment: grown,
+                offset,
+            };
+            assert_eq!(memory.get(pointer), Some(cell(grown, offset).as_ref()));
+        }
+    }
+
+    #[test]
+    fn load_felt_data_matches_load_data() {
+        let felts: Vec<Felt> = (0..40u64).map(Felt::from).collect();
+        let values: Vec<Value> = felts.iter().copied().map(Value::Scalar).collect();
+
+        // The data overlaps with a cell that is already known.
+        let mut base = Memory::default();
+        let segment = base.allocate_segment();
+        let start = Pointer { segment, offset: 2 };
+        base.assert_eq(start.wrapping_add(5), values[5].as_ref())
+            .unwrap();
+        base.set_max_cells(Some(64));
+
+        let mut from_values = base.clone();
+        let mut from_felts = base.clone();
+        let loaded = from_values.load_data(start, &values).unwrap();
+        assert_eq!(from_felts.load_felt_data(start, &felts).unwrap(), loaded);
+        assert_eq!(loaded.end, start.wrapping_add(felts.len()));
+        assert_eq!(
+            loaded.cells,
+            AssertedRun {
+                fresh: 39,
+                known: 1
+            }
+        );
+        assert!(from_values.diff(&from_felts).is_empty());
+
+        // Both fail the same way when exceeding the limit.
+        let past = loaded.end.wrapping_add(30);
+        assert!(matches!(
+            from_values.load_data(past, &values),
+            Err(Error::OutOfMemory)
+        ));
+        assert!(matches!(
+            from_felts.load_felt_data(past, &felts),
+            Err(Error::OutOfMemory)
+        ));
+        assert!(from_values.diff(&from_felts).is_empty());
+    }
+
+    #[test]
+    fn pointer_cells_between_segments() {
+        let mut memory = Memory::default();
+        let first = memory.allocate_segment();
+        let second = memory.allocate_segment();
+        let third = memory.allocate_segment();
+
+        let at = |segment, offset| Pointer { segment, offset };
+        let writes = [
+            (at(first, 0), Value::Pointer(at(second, 0))),
+            (at(first, 1), cell(first, 1)),
+            (at(first, 3), Value::Pointer(at(third, 2))),
+            (at(second, 0), cell(second, 0)),
+            (at(third, 1), Value::Pointer(at(first, 0))),
+        ];
+        for (address, value) in &writes {
+            memory.assert_eq(*address, value.as_ref()).unwrap();
+        }
+
+        assert_eq!(
+            memory.pointer_cells(),
+            [
+                (at(first, 0), at(second, 0)),
+                (at(first, 3), at(third, 2)),
+                (at(third, 1), at(first, 0)),
+            ]
+        );
+        assert!(Memory::default().pointer_cells().is_empty());
+    }
+
+    #[test]
+    fn max_cells_across_segments() {
+        let mut memory = Memory::default();
+        let first = memory.allocate_segment();
+        let second = memory.allocate_segment();
+        memory.set_max_cells(Some(10));
+
+        let at = |segment, offset| Pointer { segment, offset };
+        memory
+            .assert_eq(at(first, 5), cell(first, 5).as_ref())
+            .unwrap();
+        memory
+            .assert_eq(at(second, 3), cell(second, 3).as_ref())
+            .unwrap();
+        assert_eq!(memory.total_cells(), 10);
+
+        // The cells below the highest known cell of a segment are already accounted for.
+        memory
+            .assert_eq(at(first, 2), cell(first, 2).as_ref())
+            .unwrap();
+
+        for _ in 0..2 {
+            assert!(matches!(
+                memory.assert_eq(at(second, 4), cell(second, 4).as_ref()),
+                Err(Error::OutOfMemory)
+            ));
+            assert!(matches!(
+                memory.assert_eq(at(first, usize::MAX), cell(first, 0).as_ref()),
+                Err(Error::OutOfMemory)
+            ));
+        }
+        assert_eq!(memory.total_cells(), 10);
+        assert_eq!(memory.get(at(second, 4)), None);
+
+        // Raising the limit allows the write again.
+        memory.set_max_cells(Some(11));
+        memory
+            .assert_eq(at(second, 4), cell(second, 4).as_ref())
+            .unwrap();
+        memory.set_max_cells(None);
+        memory
+            .assert_eq(at(second, 100), cell(second, 100).as_ref())
+            .unwrap();
+    }
+
+    #[test]
+    fn diff_single_cell() {
+        let mut left = Memory::default();
+        for segment in 0..2 {
+            left.allocate_segment();
+            for offset in 0..4 {
+                let pointer = Pointer { segment, offset };
+                left.assert_eq(pointer, cell(segment, offset).as_ref())
+                    .unwrap();
+            }
+        }
+        assert!(left.diff(&left.clone()).is_empty());
+
+        // Rebuild the same memory, with a different value in one cell.
+        let changed = Pointer {
+            segment: 1,
+            offset: 2,
+        };
+        let mut right = Memory::default();
+        for segment in 0..2 {
+            let values: Vec<Value> = (0..4)
+                .map(|offset| match (Pointer { segment, offset }) {
+                    pointer if pointer == changed => Value::Pointer(changed),
+                    _ => cell(segment, offset),
+                })
+                .collect();
+            right.push_segment(Segment::from_values(&values));
+        }
+        assert_eq!(
+            left.diff(&right),
+            [(changed, Some(cell(1, 2)), Some(Value::Pointer(changed)))]
+        );
+
+        // Cells missing from one side, including in segments it never allocated.
+        let extra = Pointer {
+            segment: right.allocate_segment(),
+            offset: 1,
+        };
+        right.assert_eq(extra, cell(2, 1).as_ref()).unwrap();
+        let unknown = Pointer {
+            segment: 0,
+            offset: 6,
+        };
+        left.assert_eq(unknown, cell(0, 6).as_ref()).unwrap();
+        assert_eq!(
+            left.diff(&right),
+            [
+                (unknown, Some(cell(0, 6)), None),
+                (changed, Some(cell(1, 2)), Some(Value::Pointer(changed))),
+                (extra, None, Some(cell(2, 1))),
+            ]
+        );
+        assert_eq!(right.diff(&left).len(), 3);
+    }
+
+    #[test]
+    fn stats_of_uneven_segments() {
+        let mut memory = Memory::default();
+        assert_eq!(memory.stats(), MemoryStats::default());
+
+        let dense = memory.allocate_segment();
+        let sparse = memory.allocate_segment();
+        let empty = memory.allocate_segment();
+        memory
+            .load_felt_data(
+                Pointer {
+                    segment: dense,
+                    offset: 0,
+                },
+                &[Felt::ONE; 5],
+            )
+            .unwrap();
+        for offset in [2, 6] {
+            let pointer = Pointer {
+                segment: sparse,
+                offset,
+            };
+            memory
+                .assert_eq(pointer, cell(sparse, offset).as_ref())
+                .unwrap();
+        }
+        memory.segment_mut(empty).unwrap().reserve(50).unwrap();
+
+        let stats = memory.stats();
+        assert_eq!(memory.total_cells(), 12);
+        assert_eq!(
+            (stats.segments, stats.cells, stats.used, stats.holes),
+            (3, 12, 7, 5)
+        );
+        assert_eq!(stats.cells, stats.used + stats.holes);
+        assert!(stats.capacity >= 5 + 7 + 50);
+    }
+
+    #[test]
+    fn load_over_partially_filled_segment() {
+        let mut memory = Memory::default();
+        let segment = memory.allocate_segment();
+        let at = |offset| Pointer { segment, offset };
+        for offset in [1, 3] {
+            memory
+                .assert_eq(at(offset), cell(segment, offset).as_ref())
+                .unwrap();
+        }
+
+        let values: Vec<Value> = (0..6).map(|offset| cell(segment, offset)).collect();
+        let loaded = memory.load_data(at(0), &values).unwrap();
+        assert_eq!(loaded.end, at(6));
+        assert_eq!(loaded.cells, AssertedRun { fresh: 4, known: 2 });
+
+        // Loading the same data again only hits known cells.
+        let loaded = memory.load_data(at(2), &values[2..]).unwrap();
+        assert_eq!(loaded.end, at(6));
+        assert_eq!(loaded.cells, AssertedRun { fresh: 0, known: 4 });
+
+        // An accidental overwrite with a different value is a contradiction.
+        assert!(matches!(
+            memory.load_data(at(5), &[cell(segment, 0), cell(segment, 7)]),
+            Err(Error::Contradiction)
+        ));
+    }
+
+    #[test]
+    fn iterate_segments() {
+        let mut memory = Memory::default();
+        assert_eq!((&memory).into_iter().count(), 0);
+
+        for _ in 0..4 {
+            memory.allocate_segment();
+        }
+        let written = Pointer {
+            segment: 2,
+            offset: 1,
+        };
+        memory.assert_eq(written, cell(2, 1).as_ref()).unwrap();
+
+        assert_eq!((&memory).into_iter().count(), memory.num_segments());
+        let sizes: Vec<(usize, usize)> = memory
+            .into_iter()
+            .map(|(index, segment)| (index, segment.size()))
+            .collect();
+        assert_eq!(sizes, [(0, 0), (1, 0), (2, 2), (3, 0)]);
+        for (index, segment) in &memory {
+            assert!(core::ptr::eq(segment, memory.segment(index).unwrap()));
+        }
+    }
+}
@@ -1,8 +1,18 @@
 //! Defines the [`Pointer`] type.`
 
+use core::cmp::Ordering;
+use core::fmt;
+
 use crate::error::Error;
 
 /// A pointer within a [`Memory`] segment.
+///
+/// # Ordering
+///
+/// Two pointers can only be compared if they refer to the same segment, in which case they are
+/// ordered by offset. Pointers with different provenances are deliberately unordered (their
+/// [`partial_cmp`](PartialOrd::partial_cmp) returns `None`), because the final location of
+/// segments is not known until the memory is relocated.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Pointer {
     /// The index of the segment in the memory.
@@ -14,6 +24,32 @@ pub struct Pointer {
 }
 
 impl Pointer {
+    /// A pointer that never references a valid memory cell.
+    ///
+    /// Its segment index can never be allocated, making it suitable to represent "null"
+    /// pointers in data structures (such as the end of a linked list) without risking a
+    /// collision with an actual memory cell.
+    pub const SENTINEL: Self = Self {
+        segment: usize::MAX,
+        offset: 0,
+    };
+
+    /// Returns the [`SENTINEL`](Self::SENTINEL) pointer.
+    #[inline(always)]
+    pub const fn sentinel() -> Self {
+        Self::SENTINEL
+    }
+
+    /// Returns whether this pointer is a "null" pointer, meaning that it references the
+    /// segment of the [`SENTINEL`](Self::SENTINEL) pointer.
+    ///
+    /// The offset is ignored, ensuring that pointer arithmetic performed on a null pointer
+    /// (for example, accessing a field of a null structure) keeps it null.
+    #[inline(always)]
+    pub const fn is_null_like(&self) -> bool {
+        self.segment == Self::SENTINEL.segment
+    }
+
     /// Returns the signed distance between `self` and `other`, given that are refering to the same
     /// segment.
     pub fn subtract(&self, other: &Self) -> Result<isize, Error> {
@@ -33,6 +69,17 @@ impl Pointer {
         }
     }
 
+    /// Adds the signed `delta` to `self.offset` using wrapping arithmetic.
+    ///
+    /// This is typically used to apply the (signed) offsets of an instruction to a register.
+    #[inline(always)]
+    pub fn add_signed(self, delta: isize) -> Self {
+        Self {
+            segment: self.segment,
+            offset: self.offset.wrapping_add_signed(delta),
+        }
+    }
+
     /// Subtracts `offset` from `self.offset` using wrapping arithmetic.
     #[inline(always)]
     pub fn wrapping_sub(self, offset: usize) -> Self {
@@ -41,4 +88,95 @@ impl Pointer {
             offset: self.offset.wrapping_sub(offset),
         }
     }
+
+    /// Adds `offset` to `self.offset`, returning `None` if the resulting offset would exceed
+    /// `isize::MAX`.
+    ///
+    /// Offsets within a segment are always expected to fit in an `isize`.
+    #[inline]
+    pub fn checked_add(self, offset: usize) -> Option<Self> {
+        let offset = self.offset.checked_add(offset)?;
+        if offset > isize::MAX as usize {
+            return None;
+        }
+
+        Some(Self {
+            segment: self.segment,
+            offset,
+        })
+    }
+
+    /// Subtracts `offset` from `self.offset`, returning `None` if the resulting offset would be
+    /// negative.
+    #[inline]
+    pub fn checked_sub(self, offset: usize) -> Option<Self> {
+        Some(Self {
+            segment: self.segment,
+            offset: self.offset.checked_sub(offset)?,
+        })
+    }
+
+    /// Returns an iterator over the `len` successive pointers starting at `start` within its
+    /// segment.
+    #[inline]
+    pub fn contiguous_range(start: Self, len: usize) -> impl Iterator<Item = Self> {
+        (0..len).map(move |i| start.wrapping_add(i))
+    }
+}
+
+impl PartialOrd for Pointer {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        if self.segment != other.segment {
+            None
+        } else {
+            Some(self.offset.cmp(&other.offset))
+        }
+    }
+}
+
+impl fmt::Display for Pointer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.segment, self.offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sentinel_is_null_like() {
+        assert_eq!(Pointer::sentinel(), Pointer::SENTINEL);
+        assert!(Pointer::SENTINEL.is_null_like());
+
+        // Accessing a field of a null structure keeps the pointer null.
+        assert!(Pointer::SENTINEL.wrapping_add(3).is_null_like());
+        assert!(Pointer::SENTINEL.add_signed(-1).is_null_like());
+
+        for segment in [0, 1, usize::MAX - 1] {
+            assert!(!Pointer { segment, offset: 0 }.is_null_like());
+        }
+    }
+
+    #[test]
+    fn add_signed_offsets() {
+        let base = Pointer {
+            segment: 1,
+            offset: 10,
+        };
+
+        assert_eq!(base.add_signed(5), base.wrapping_add(5));
+        assert_eq!(base.add_signed(-3), base.wrapping_sub(3));
+        assert_eq!(base.add_signed(0), base);
+        assert_eq!(
+            base.add_signed(i16::MIN as isize).offset,
+            10usize.wrapping_sub(32768)
+        );
+
+        // Going below the start of the segment wraps around, like the other operations.
+        let underflow = base.add_signed(-11);
+        assert_eq!(underflow.segment, 1);
+        assert_eq!(underflow.offset, usize::MAX);
+        assert_eq!(underflow.add_signed(11), base);
+    }
 }
@@ -0,0 +1,395 @@
+//! Defines the [`RelocatedMemory`] type, responsible for representing the memory of a Cairo
+//! virtual machine once its segments have been collapsed into a single address space.
+
+use alloc::vec;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io::{self, Write};
+
+use starknet_types_core::felt::Felt;
+
+use crate::error::Error;
+
+use num_traits::ToPrimitive;
+
+use super::{Memory, Pointer, Segment, Value, ValueRef};
+
+/// Maps each segment of a [`Memory`] to its base address within the relocated address space.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RelocationTable {
+    /// The base address of each segment, indexed by segment.
+    bases: Vec<usize>,
+}
+
+impl RelocationTable {
+    /// Returns the base address of the provided segment, if it was part of the relocated
+    /// memory.
+    #[inline(always)]
+    pub fn base(&self, segment: usize) -> Option<usize> {
+        self.bases.get(segment).copied()
+    }
+
+    /// Returns the base addresses of all relocated segments, indexed by segment.
+    #[inline(always)]
+    pub fn bases(&self) -> &[usize] {
+        &self.bases
+    }
+
+    /// Converts the provided [`Pointer`] into its absolute address in the relocated address
+    /// space.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with [`Error::UnallocatedSegment`] if the pointer references a
+    /// segment that was not part of the relocated memory.
+    pub fn relocate(&self, pointer: Pointer) -> Result<usize, Error> {
+        self.base(pointer.segment)
+            .ok_or(Error::UnallocatedSegment)?
+            .checked_add(pointer.offset)
+            .ok_or(Error::PointerTooLarge)
+    }
+
+    /// Converts an absolute address of the relocated address space back into a [`Pointer`].
+    ///
+    /// The address is attributed to the last segment whose base address is not greater than
+    /// it. `None` is returned if the address is located before the first segment.
+    pub fn unrelocate(&self, address: usize) -> Option<Pointer> {
+        let segment = self
+            .bases
+            .partition_point(|&base| base <= address)
+            .checked_sub(1)?;
+
+        Some(Pointer {
+            segment,
+            offset: address - self.bases[segment],
+        })
+    }
+}
+
+/// The memory of a Cairo virtual machine, collapsed into a single linear address space.
+///
+/// This is the representation expected by provers and serialization formats. Every segment has
+/// been assigned a base address, and every pointer has been rewritten to the absolute address
+/// it refers to.
+#[derive(Debug, Clone, Default)]
+pub struct RelocatedMemory {
+    /// The base address assigned to each segment.
+    table: RelocationTable,
+    /// The cells of the relocated memory, indexed by absolute address.
+    ///
+    /// Cells that were never asserted to a specific value are `None`.
+    cells: Vec<Option<Felt>>,
+}
+
+impl RelocatedMemory {
+    /// Returns the [`RelocationTable`] used to relocate the memory.
+    #[inline(always)]
+    pub fn table(&self) -> &RelocationTable {
+        &self.table
+    }
+
+    /// Returns the cells of the relocated memory, indexed by absolute address.
+    ///
+    /// Address `0` is never used and is always `None`.
+    #[inline(always)]
+    pub fn cells(&self) -> &[Option<Felt>] {
+        &self.cells
+    }
+
+    /// Returns the value of the cell at the provided absolute address, if it is known.
+    #[inline]
+    pub fn get(&self, address: usize) -> Option<&Felt> {
+        self.cells.get(address)?.as_ref()
+    }
+
+    /// Writes the relocated memory to the provided writer, using the binary `memory.bin`
+    /// format expected by the Cairo prover.
+    ///
+    /// Each known cell is written as its 8-byte little-endian address, followed by its value
+    /// as a 32-byte little-endian field element. Unknown cells are skipped.
+    ///
+    /// This function is only available with the `std` feature.
+    #[cfg(feature = "std")]
+    pub fn write_bin<W>(&self, w: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        for (address, cell) in self.cells.iter().enumerate() {
+            let Some(value) = cell else { continue };
+
+            w.write_all(&(address as u64).to_le_bytes())?;
+            w.write_all(&value.to_bytes_le())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Memory {
+    /// Relocates the segments of this [`Memory`] into a single flat address space.
+    ///
+    /// Segment `0` is placed at address `1`, and every following segment is placed directly
+    /// after the highest known cell of the previous one. Pointers are rewritten to the
+    /// absolute address they refer to.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with [`Error::UnallocatedSegment`] if one of the cells holds a
+    /// pointer to a segment that was never allocated in this [`Memory`].
+    pub fn relocate(&self) -> Result<RelocatedMemory, Error> {
+        let mut bases = Vec::with_capacity(self.segments.len());
+        let mut next_base = 1usize;
+        for segment in &self.segments {
+            bases.push(next_base);
+            next_base = next_base
+                .checked_add(segment.highest_known_cell())
+                .ok_or(Error::OutOfMemory)?;
+        }
+
+        let table = RelocationTable { bases };
+        let mut cells = vec![None; next_base];
+
+        for (index, segment) in self {
+            // The base address of each segment has just been computed above.
+            let base = table.bases[index];
+
+            for offset in 0..segment.highest_known_cell() {
+                cells[base + offset] = match segment.get(offset) {
+                    None => continue,
+                    Some(ValueRef::Scalar(value)) => Some(*value),
+                    Some(ValueRef::Pointer(pointer)) => Some(Felt::from(table.relocate(*pointer)?)),
+                };
+            }
+        }
+
+        Ok(RelocatedMemory { table, cells })
+    }
+
+    /// Like [`relocate`](Self::relocate), but first makes sure that no cell marked as part
+    /// of the [public memory](Memory::public_memory) holds a pointer.
+    ///
+    /// Public segments (such as the program and the output) are meant to hold scalar data.
+    /// A pointer in one of them usually indicates a bug, which [`relocate`](Self::relocate)
+    /// silently hides by rewriting it to an absolute address.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with [`Error::InvalidPublicMemory`] if a public cell holds a
+    /// pointer, and otherwise with the errors of [`relocate`](Self::relocate).
+    pub fn relocate_strict(&self) -> Result<RelocatedMemory, Error> {
+        let has_public_pointer = self.into_iter().any(|(_, segment)| {
+            segment
+                .public_cells()
+                .any(|(_, value)| matches!(value, ValueRef::Pointer(_)))
+        });
+
+        if has_public_pointer {
+            return Err(Error::InvalidPublicMemory);
+        }
+
+        self.relocate()
+    }
+
+    /// Rebuilds a [`Memory`] from its relocated representation.
+    ///
+    /// `cells` is indexed by absolute address, and `bases` holds the base address of each
+    /// segment (as returned by [`RelocationTable::bases`]). Segment `i` spans the addresses from
+    /// `bases[i]` up to the base of the next segment (or the end of `cells` for the last one).
+    ///
+    /// # Pointer recovery
+    ///
+    /// Relocation erases the distinction between scalars and pointers, which this function
+    /// attempts to recover heuristically: any value that is the address of a cell within one
+    /// of the segments is assumed to be a pointer to that cell. This means that scalars that
+    /// happen to match a valid address (small constants, in particular) are recovered as
+    /// pointers too. The resulting memory should therefore be used with care.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with [`Error::InvalidRelocation`] if `bases` is not sorted, or if
+    /// one of the bases lies outside of `cells`.
+    pub fn from_relocated(cells: &[Option<Felt>], bases: &[usize]) -> Result<Self, Error> {
+        let sorted = bases.windows(2).all(|w| w[0] <= w[1]);
+        if !sorted || bases.last().is_some_and(|&last| last > cells.len()) {
+            return Err(Error::InvalidRelocation);
+        }
+
+        // Returns the pointer corresponding to an absolute address, if any segment contains it.
+        let to_pointer = |address: usize| {
+            let segment = bases
+                .partition_point(|&base| base <= address)
+                .checked_sub(1)?;
+            let end = bases.get(segment + 1).copied().unwrap_or(cells.len());
+            (address < end).then(|| Pointer {
+                segment,
+                offset: address - bases[segment],
+            })
+        };
+
+        let mut memory = Memory::default();
+        for (index, &base) in bases.iter().enumerate() {
+            let end = bases.get(index + 1).copied().unwrap_or(cells.len());
+            let mut segment = Segment::new();
+
+            for (offset, cell) in cells[base..end].iter().enumerate() {
+                let Some(cell) = cell else { continue };
+
+                let value = match cell.to_usize().and_then(to_pointer) {
+                    Some(pointer) => Value::Pointer(pointer),
+                    None => Value::Scalar(*cell),
+                };
+
+                segment.set(offset, value.as_ref())?;
+            }
+
+            memory.segments.push(segment);
+        }
+
+        Ok(memory)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a pointer to `offset` in `segment`.
+    fn ptr(segment: usize, offset: usize) -> Pointer {
+        Pointer { segment, offset }
+    }
+
+    /// Returns a memory with three segments pointing to each other, one of them holding a gap.
+    fn cross_segment_memory() -> Memory {
+        let segments: [&[(usize, Value)]; 3] = [
+            &[
+                (0, Value::Scalar(Felt::from(5))),
+                (1, Value::Pointer(ptr(2, 1))),
+            ],
+            &[
+                (0, Value::Pointer(ptr(0, 0))),
+                (2, Value::Scalar(Felt::from(7))),
+            ],
+            &[
+                (0, Value::Scalar(Felt::from(9))),
+                (1, Value::Pointer(ptr(1, 2))),
+            ],
+        ];
+
+        let mut memory = Memory::default();
+        for cells in segments {
+            let mut segment = Segment::new();
+            for (offset, value) in cells {
+                segment.set(*offset, value.as_ref()).unwrap();
+            }
+            memory.push_segment(segment);
+        }
+        memory
+    }
+
+    #[test]
+    fn relocate_cross_segment_pointers() {
+        let relocated = cross_segment_memory().relocate().unwrap();
+
+        assert_eq!(relocated.table().bases(), [1, 3, 6]);
+        let felt = |value: u64| Some(Felt::from(value));
+        assert_eq!(
+            relocated.cells(),
+            [
+                None,
+                felt(5),
+                felt(7),
+                felt(1),
+                None,
+                felt(7),
+                felt(9),
+                felt(5),
+            ],
+        );
+
+        let mut dangling = Memory::default();
+        let segment = dangling.allocate_segment();
+        dangling
+            .assert_eq(ptr(segment, 0), ValueRef::Pointer(&ptr(3, 0)))
+            .unwrap();
+        assert!(matches!(
+            dangling.relocate(),
+            Err(Error::UnallocatedSegment)
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn write_bin_round_trip() {
+        let relocated = cross_segment_memory().relocate().unwrap();
+
+        let mut bin = Vec::new();
+        relocated.write_bin(&mut bin).unwrap();
+        assert_eq!(bin.len() % 40, 0);
+
+        let mut cells = vec![None; relocated.cells().len()];
+        for pair in bin.chunks_exact(40) {
+            let (address, value) = pair.split_at(8);
+            let address = u64::from_le_bytes(address.try_into().unwrap()) as usize;
+            cells[address] = Some(Felt::from_bytes_le(value.try_into().unwrap()));
+        }
+        assert_eq!(cells, relocated.cells());
+    }
+
+    #[test]
+    fn from_relocated_round_trip() {
+        let mut memory = Memory::default();
+        let segments: [&[Value]; 2] = [
+            &[Value::Scalar(Felt::from(1000)), Value::Pointer(ptr(1, 1))],
+            &[
+                Value::Scalar(Felt::from(2000)),
+                Value::Scalar(Felt::from(3000)),
+            ],
+        ];
+        for values in segments {
+            memory.push_segment(Segment::from_values(values));
+        }
+
+        let relocated = memory.relocate().unwrap();
+        let bases = relocated.table().bases();
+        let rebuilt = Memory::from_relocated(relocated.cells(), bases).unwrap();
+        assert_eq!(rebuilt.num_segments(), 2);
+        assert!(rebuilt.diff(&memory).is_empty());
+
+        // Scalars matching a valid address are recovered as pointers.
+        let mut cells = relocated.cells().to_vec();
+        cells[3] = Some(Felt::from(2));
+        let rebuilt = Memory::from_relocated(&cells, bases).unwrap();
+        let pointer = ptr(0, 1);
+        assert_eq!(rebuilt.get(ptr(1, 0)), Some(ValueRef::Pointer(&pointer)));
+
+        assert!(matches!(
+            Memory::from_relocated(&cells, &[3, 1]),
+            Err(Error::InvalidRelocation)
+        ));
+        assert!(matches!(
+            Memory::from_relocated(&cells, &[1, 6]),
+            Err(Error::InvalidRelocation)
+        ));
+    }
+
+    #[test]
+    fn strict_and_lenient_relocation() {
+        let mut memory = cross_segment_memory();
+        let lenient = memory.relocate().unwrap();
+
+        // Pointers in private cells are fine in both modes.
+        memory.mark_public(ptr(0, 0)).unwrap();
+        memory.mark_public(ptr(1, 2)).unwrap();
+        let strict = memory.relocate_strict().unwrap();
+        assert_eq!(strict.cells(), lenient.cells());
+
+        memory.mark_public(ptr(0, 1)).unwrap();
+        assert!(matches!(
+            memory.relocate_strict(),
+            Err(Error::InvalidPublicMemory)
+        ));
+        let relocated = memory.relocate().unwrap();
+        assert_eq!(relocated.cells(), lenient.cells());
+        assert_eq!(relocated.cells()[2], Some(Felt::from(7)));
+    }
+}
@@ -1,15 +1,24 @@
 //! Defines the [`Segment`] type.
 
-use std::alloc::Layout;
-use std::fmt;
-use std::mem::{align_of, size_of};
-use std::ptr::NonNull;
+use alloc::rc::Rc;
+use core::alloc::Layout;
+use core::fmt;
+use core::mem::{align_of, size_of};
+use core::ptr::{self, NonNull};
 
+use bitflags::bitflags;
 use starknet_types_core::felt::Felt;
 
 use crate::error::Error;
 
-use super::{Pointer, ValueRef};
+use super::arena::{Arena, Chunk};
+use super::{Pointer, Value, ValueRef};
+
+/// The maximum capacity of a [`Segment`].
+///
+/// Beyond this, the size of the buffer holding the cells of the segment would exceed
+/// `isize::MAX` bytes, which cannot be allocated.
+const MAX_CAPACITY: usize = isize::MAX as usize / size_of::<Felt>();
 
 /// A relocatable segment of memory accessible by the Cairo virtual machine.
 ///
@@ -28,7 +37,6 @@ use super::{Pointer, ValueRef};
 /// because it means we don't use to deal with *a lot* of fragmentation within individual
 /// segments, enabling the use of flat arrays to represent segments. It is still possible for
 /// "gaps" to appear within a segment, but they should remain relatively small in most cases.
-#[derive(Clone)]
 pub struct Segment {
     /// The total capacity of this segment.
     ///
@@ -48,6 +56,79 @@ pub struct Segment {
     /// An entry in this array is guaranteed to be initialized if and only if the corresponding
     /// entry in the `metadata` array indicates that the value is `known`.
     cells: NonNull<RawValue>,
+
+    /// The arena chunk holding the buffers of the segment, if they were allocated from an
+    /// [`Arena`] rather than with the global allocator.
+    ///
+    /// Such buffers must never be deallocated directly. They are released along with the chunk
+    /// once it is no longer referenced.
+    arena: Option<Rc<Chunk>>,
+
+    /// The strategy used to pick the new capacity of the segment when it grows.
+    growth: GrowthPolicy,
+}
+
+impl Clone for Segment {
+    /// Creates a deep copy of the segment.
+    ///
+    /// The capacity of the returned segment is trimmed to its length.
+    fn clone(&self) -> Self {
+        let mut clone = Self::new();
+        clone.growth = self.growth;
+
+        if self.length == 0 {
+            return clone;
+        }
+
+        // SAFETY:
+        //  The new segment has a capacity of zero, and we know that `self.length` is non-zero.
+        if unsafe { clone.grow(self.length) }.is_err() {
+            alloc::alloc::handle_alloc_error(
+                Layout::array::<Felt>(self.length).unwrap_or(Layout::new::<Felt>()),
+            );
+        }
+
+        // SAFETY:
+        //  Both segments have a capacity of at least `self.length`, and the two allocations
+        //  are distinct. Copying cells that are not initialized is fine because the copy is
+        //  untyped.
+        unsafe {
+            ptr::copy_nonoverlapping(self.metadata.as_ptr(), clone.metadata.as_ptr(), self.length);
+            ptr::copy_nonoverlapping(self.cells.as_ptr(), clone.cells.as_ptr(), self.length);
+        }
+
+        clone.length = self.length;
+        clone
+    }
+}
+
+impl Drop for Segment {
+    fn drop(&mut self) {
+        if self.capacity == 0 || self.arena.is_some() {
+            // Nothing was ever allocated with the global allocator.
+            return;
+        }
+
+        unsafe {
+            // SAFETY:
+            //  Those layouts have been used to allocate the memory in the first place,
+            //  ensuring that they are valid.
+            let metadata_layout = Layout::from_size_align_unchecked(
+                size_of::<Metadata>().wrapping_mul(self.capacity),
+                align_of::<Metadata>(),
+            );
+            let cells_layout = Layout::from_size_align_unchecked(
+                size_of::<Felt>().wrapping_mul(self.capacity),
+                align_of::<Felt>(),
+            );
+
+            // SAFETY:
+            //  The segment has a non-zero capacity, meaning that both pointers have been
+            //  allocated with the global allocator.
+            alloc::alloc::dealloc(self.metadata.as_ptr() as *mut u8, metadata_layout);
+            alloc::alloc::dealloc(self.cells.as_ptr() as *mut u8, cells_layout);
+        }
+    }
 }
 
 impl Default for Segment {
@@ -68,7 +149,68 @@ impl Segment {
             length: 0,
             metadata: NonNull::dangling(),
             cells: NonNull::dangling(),
+            arena: None,
+            growth: GrowthPolicy::Amortized,
+        }
+    }
+
+    /// Creates a new dense [`Segment`] holding the provided values, starting at offset `0`.
+    ///
+    /// Both the length and the capacity of the returned segment are exactly `values.len()`.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the memory required to hold the values cannot be allocated.
+    pub fn from_values(values: &[Value]) -> Self {
+        let mut segment = Self::new();
+
+        segment
+            .reserve(values.len())
+            .expect("failed to allocate the segment");
+        segment
+            .assert_run(0, values)
+            .expect("failed to allocate the segment");
+
+        segment
+    }
+
+    /// Creates a new empty [`Segment`] whose initial buffers are drawn from the provided
+    /// [`Arena`].
+    ///
+    /// If the segment outgrows this initial capacity, its content is moved to buffers allocated
+    /// with the global allocator, as for any other segment.
+    ///
+    /// `None` is returned if the arena could not serve the allocation.
+    pub(super) fn new_in(arena: &mut Arena, capacity: usize) -> Option<Self> {
+        let (layout, cells_offset) = Layout::array::<Metadata>(capacity)
+            .ok()?
+            .extend(Layout::array::<Felt>(capacity).ok()?)
+            .ok()?;
+
+        if layout.size() == 0 {
+            return None;
         }
+
+        let (chunk, ptr) = arena.allocate(layout)?;
+
+        // SAFETY:
+        //  The block has been allocated with a layout large enough to hold both arrays, and
+        //  `cells_offset` is within its bounds.
+        let (metadata, cells) = unsafe {
+            (
+                ptr.cast::<Metadata>(),
+                NonNull::new_unchecked(ptr.as_ptr().add(cells_offset) as *mut RawValue),
+            )
+        };
+
+        Some(Self {
+            capacity,
+            length: 0,
+            metadata,
+            cells,
+            arena: Some(chunk),
+            growth: GrowthPolicy::Amortized,
+        })
     }
 
     /// Returns the capacity of the segment.
@@ -76,12 +218,62 @@ impl Segment {
         self.capacity
     }
 
+    /// Returns the strategy used to pick the new capacity of the segment when it grows.
+    #[inline(always)]
+    pub const fn growth_policy(&self) -> GrowthPolicy {
+        self.growth
+    }
+
+    /// Sets the strategy used to pick the new capacity of the segment when it grows.
+    ///
+    /// This only affects future growths. Segments use [`GrowthPolicy::Amortized`] by default.
+    #[inline(always)]
+    pub fn set_growth_policy(&mut self, policy: GrowthPolicy) {
+        self.growth = policy;
+    }
+
     /// Returns the offset of the highest known cell in the segment.
     #[inline(always)]
     pub const fn highest_known_cell(&self) -> usize {
         self.length
     }
 
+    /// Returns the extent of the segment: the offset directly following its last known cell.
+    ///
+    /// This is the same as [`highest_known_cell`](Self::highest_known_cell). Cells below this
+    /// offset may still be unknown, use [`used`](Self::used) to count the cells that are
+    /// actually known.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rayquaza_vm::memory::{Segment, ValueRef};
+    /// use starknet_types_core::felt::Felt;
+    ///
+    /// let mut segment = Segment::new();
+    /// segment.assert_eq(0, ValueRef::Scalar(&Felt::ONE)).unwrap();
+    /// segment.assert_eq(3, ValueRef::Scalar(&Felt::ONE)).unwrap();
+    ///
+    /// // Offsets 1 and 2 are a gap of unknown cells.
+    /// assert_eq!(segment.size(), 4);
+    /// assert_eq!(segment.used(), 2);
+    /// ```
+    #[inline(always)]
+    pub const fn size(&self) -> usize {
+        self.length
+    }
+
+    /// Returns the number of known cells in the segment.
+    ///
+    /// This is the [`size`](Self::size) of the segment minus the unknown cells (holes) it
+    /// contains. Computing it requires going through the whole segment.
+    pub fn used(&self) -> usize {
+        // SAFETY:
+        //  All metadata entries up to `length` are guaranteed to be initialized.
+        let metadata = unsafe { core::slice::from_raw_parts(self.metadata.as_ptr(), self.length) };
+        metadata.iter().filter(|m| m.is_known()).count()
+    }
+
     /// Returns the memory cell at offset `index` in the segment, as well as metadata about it.
     ///
     /// # Safety
@@ -118,7 +310,7 @@ impl Segment {
 
     /// Returns the memory cell at offset `index` in the segment, if it has been asserted to a
     /// specific value.
-    pub fn get(&self, index: usize) -> Option<ValueRef> {
+    pub fn get(&self, index: usize) -> Option<ValueRef<'_>> {
         if index >= self.length {
             None
         } else {
@@ -128,12 +320,127 @@ impl Segment {
 
             // SAFETY:
             //  The metadata and its associated cell are guaranteed to be syncronized.
-            match *metadata {
-                Metadata::Unknown => None,
-                Metadata::Pointer => Some(ValueRef::Pointer(unsafe { &cell.pointer })),
-                Metadata::Scalar => Some(ValueRef::Scalar(unsafe { &cell.scalar })),
+            unsafe { metadata.read(cell) }
+        }
+    }
+
+    /// Marks the memory cell at offset `index` as part of the public memory of the program.
+    ///
+    /// The cell must already be known: marking a cell never extends the segment.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with [`Error::InvalidPublicMemory`] if the cell is unknown.
+    pub fn mark_public(&mut self, index: usize) -> Result<(), Error> {
+        if index >= self.length {
+            return Err(Error::InvalidPublicMemory);
+        }
+
+        // SAFETY:
+        //  We just made sure that the index is in bounds of the segment's initialized length.
+        let (metadata, _) = unsafe { self.get_unchecked_raw_mut(index) };
+        if !metadata.is_known() {
+            return Err(Error::InvalidPublicMemory);
+        }
+
+        metadata.insert(Metadata::PUBLIC);
+        Ok(())
+    }
+
+    /// Returns an iterator over the known cells of the segment that have been marked as part of
+    /// the public memory, along with their offset.
+    pub fn public_cells(&self) -> impl Iterator<Item = (usize, ValueRef<'_>)> + '_ {
+        (0..self.length).filter_map(move |index| {
+            // SAFETY:
+            //  `index` is within the bounds of the segment's length.
+            let (metadata, cell) = unsafe { self.get_unchecked_raw(index) };
+
+            if !metadata.contains(Metadata::PUBLIC) {
+                return None;
+            }
+
+            // SAFETY:
+            //  The metadata and its associated cell are guaranteed to be syncronized.
+            unsafe { metadata.read(cell) }.map(|value| (index, value))
+        })
+    }
+
+    /// Marks the memory cell at offset `index` as accessed by the program.
+    ///
+    /// Cells beyond the length of the segment are unknown, and are left untouched.
+    #[inline]
+    pub(crate) fn mark_accessed(&mut self, index: usize) {
+        if index < self.length {
+            // SAFETY:
+            //  We just made sure that the index is within the bounds of the segment's length.
+            let (metadata, _) = unsafe { self.get_unchecked_raw_mut(index) };
+            metadata.insert(Metadata::ACCESSED);
+        }
+    }
+
+    /// Returns whether the memory cell at offset `index` has been accessed by an instruction
+    /// executed by the virtual machine.
+    ///
+    /// A cell is accessed when it is fetched as an instruction, or when it is one of the
+    /// operands of an instruction, whether its value was read or deduced.
+    pub fn is_accessed(&self, index: usize) -> bool {
+        if index >= self.length {
+            return false;
+        }
+
+        // SAFETY:
+        //  We just made sure that the index is within the bounds of the segment's length.
+        let (metadata, _) = unsafe { self.get_unchecked_raw(index) };
+        metadata.contains(Metadata::ACCESSED)
+    }
+
+    /// Returns an iterator over the known cells of the segment that have never been accessed
+    /// by an instruction, along with their offset.
+    ///
+    /// Those are usually written by hints or builtins and never read afterwards. Finding them
+    /// helps debugging dead writes, and making sure that every cell is accounted for when
+    /// proving the execution.
+    ///
+    /// See [`is_accessed`](Self::is_accessed).
+    pub fn unaccessed_known_cells(&self) -> impl Iterator<Item = (usize, ValueRef<'_>)> + '_ {
+        (0..self.length).filter_map(move |index| {
+            // SAFETY:
+            //  `index` is within the bounds of the segment's length.
+            let (metadata, cell) = unsafe { self.get_unchecked_raw(index) };
+
+            if metadata.contains(Metadata::ACCESSED) {
+                return None;
             }
+
+            // SAFETY:
+            //  The metadata and its associated cell are guaranteed to be syncronized.
+            unsafe { metadata.read(cell) }.map(|value| (index, value))
+        })
+    }
+
+    /// Returns the known cells of the segment as a contiguous slice of [`Felt`]s.
+    ///
+    /// This is only possible when every cell up to the highest known cell holds a scalar. If
+    /// any of them is a pointer or is still unknown, `None` is returned and the caller should
+    /// fall back to reading the cells one by one.
+    ///
+    /// This is useful to hash the content of a segment without copying it.
+    pub fn raw_felt_slice(&self) -> Option<&[Felt]> {
+        // SAFETY:
+        //  All metadata entries up to `length` are guaranteed to be initialized.
+        let metadata = unsafe { core::slice::from_raw_parts(self.metadata.as_ptr(), self.length) };
+
+        if !metadata.iter().all(|m| m.is_known() && !m.is_pointer()) {
+            return None;
         }
+
+        // SAFETY:
+        //  We just made sure that every cell up to `length` is initialized with a scalar
+        //  value. `RawValue` has the same layout as `Felt` and its fields are all located at
+        //  offset zero, ensuring that the cells can be reinterpreted as `Felt`s.
+        Some(unsafe {
+            core::slice::from_raw_parts(self.cells.as_ptr().cast::<Felt>(), self.length)
+        })
     }
 
     /// Sets the value of a memory cell, eventually overriding its previous value.
@@ -162,22 +469,17 @@ impl Segment {
 
         #[cfg(debug_assertions)]
         {
-            match *metadata {
-                Metadata::Unknown => (),
-                Metadata::Pointer => assert_eq!(
-                    value,
-                    ValueRef::Pointer(unsafe { &cell.pointer }),
-                    "`Segment::set`: contradiction detected (index {index})"
-                ),
-                Metadata::Scalar => assert_eq!(
-                    value,
-                    ValueRef::Scalar(unsafe { &cell.scalar }),
+            // SAFETY:
+            //  The metadata and its associated cell are guaranteed to be syncronized.
+            if let Some(known) = unsafe { metadata.read(cell) } {
+                assert_eq!(
+                    value, known,
                     "`Segment::set`: contradiction detected (index {index})"
-                ),
+                );
             }
         }
 
-        *metadata = Metadata::from_value_ref(value);
+        metadata.set_value(value);
         cell.write(value);
 
         Ok(())
@@ -193,29 +495,127 @@ impl Segment {
     ///   succeeds, returning `Ok(())`.
     ///
     /// - If it does not, the function fails and returns `Err(Error::Contradiction)`.
+    ///
+    /// # Timing
+    ///
+    /// By default, comparing `value` with a known cell returns as soon as a difference is
+    /// found, which leaks how much of the two values matched through the time the comparison
+    /// takes. This is only a concern when the asserted values are secret, and an attacker can
+    /// measure the execution time of the virtual machine.
+    ///
+    /// With the `constant-time` feature, every byte of the two values is compared without
+    /// branching on their content. Whether the values are scalars or pointers, and whether the
+    /// cell is known, remain observable.
+    ///
+    /// This does not make the whole comparison constant-time: the field element library does
+    /// not expose the internal (Montgomery) limbs of a scalar, so both scalars are first
+    /// converted to their canonical bytes with [`Felt::to_bytes_le`], which makes no
+    /// constant-time guarantee. Only the comparison of the converted bytes is free of
+    /// data-dependent branches.
+    ///
+    /// The conversion and the byte-wise fold make asserting a known scalar cell about ten times
+    /// slower than the default comparison (see the `assert_known` benchmark of the `segment`
+    /// bench, run with and without the feature). Asserting unknown cells is not affected.
     pub fn assert_eq(&mut self, index: usize, value: ValueRef) -> Result<(), Error> {
         self.grow_for_index(index)?;
 
         // SAFETY:
         //  We just made sure that the index is in bounds of the segment's initialized length.
-        let (metadata, cell) = unsafe { self.get_unchecked_raw_mut(index) };
+        unsafe { self.assert_eq_unchecked(index, value)? };
+        Ok(())
+    }
+
+    /// Attempts to assert that a contiguous run of memory cells starting at `start` has the
+    /// provided values.
+    ///
+    /// This is equivalent to calling [`assert_eq`](Self::assert_eq) for each value, but the
+    /// segment only grows once to fit the whole run. The returned [`AssertedRun`] reports how
+    /// many of the cells were previously unknown, and how many were already known to hold the
+    /// provided value.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with [`Error::Contradiction`] if one of the cells was already known
+    /// to hold a different value. In that case, the cells preceding it have already been
+    /// asserted.
+    pub fn assert_run(&mut self, start: usize, values: &[Value]) -> Result<AssertedRun, Error> {
+        self.assert_run_with(start, values.iter().map(Value::as_ref))
+    }
+
+    /// Attempts to assert that a contiguous run of memory cells starting at `start` holds the
+    /// provided scalar values.
+    ///
+    /// This is [`assert_run`](Self::assert_run) for data made only of field elements, such as
+    /// program bytecode, which avoids wrapping each of them in a [`Value`].
+    ///
+    /// # Errors
+    ///
+    /// This function fails with [`Error::Contradiction`] if one of the cells was already known
+    /// to hold a different value. In that case, the cells preceding it have already been
+    /// asserted.
+    pub fn assert_felt_run(&mut self, start: usize, values: &[Felt]) -> Result<AssertedRun, Error> {
+        self.assert_run_with(start, values.iter().map(ValueRef::Scalar))
+    }
 
-        let known = match *metadata {
-            Metadata::Unknown => {
-                // The cell is unknown.
-                // We can assert it to take the provided value.
-                *metadata = Metadata::from_value_ref(value);
-                cell.write(value);
-                return Ok(());
+    /// Asserts the cells of a run starting at `start`, growing the segment once to fit all of
+    /// them.
+    fn assert_run_with<'a, I>(&mut self, start: usize, values: I) -> Result<AssertedRun, Error>
+    where
+        I: ExactSizeIterator<Item = ValueRef<'a>>,
+    {
+        let mut run = AssertedRun::default();
+
+        if values.len() == 0 {
+            return Ok(run);
+        }
+
+        let last = start
+            .checked_add(values.len() - 1)
+            .ok_or(Error::OutOfMemory)?;
+        self.grow_for_index(last)?;
+
+        for (index, value) in (start..).zip(values) {
+            // SAFETY:
+            //  We made sure that the whole run is in bounds of the segment's initialized
+            //  length.
+            if unsafe { self.assert_eq_unchecked(index, value)? } {
+                run.fresh += 1;
+            } else {
+                run.known += 1;
             }
-            Metadata::Pointer => ValueRef::Pointer(unsafe { &cell.pointer }),
-            Metadata::Scalar => ValueRef::Scalar(unsafe { &cell.scalar }),
+        }
+
+        Ok(run)
+    }
+
+    /// Asserts that a memory cell in the segment has a given value, without checking whether
+    /// the index is in bounds.
+    ///
+    /// Returns whether the cell was previously unknown.
+    ///
+    /// # Safety
+    ///
+    /// `index` must be less than the length of the segment.
+    unsafe fn assert_eq_unchecked(&mut self, index: usize, value: ValueRef) -> Result<bool, Error> {
+        // SAFETY:
+        //  The caller must make sure that the index is in bounds of the segment's initialized
+        //  length.
+        let (metadata, cell) = unsafe { self.get_unchecked_raw_mut(index) };
+
+        // SAFETY:
+        //  The metadata and its associated cell are guaranteed to be syncronized.
+        let Some(known) = (unsafe { metadata.read(cell) }) else {
+            // The cell is unknown.
+            // We can assert it to take the provided value.
+            metadata.set_value(value);
+            cell.write(value);
+            return Ok(true);
         };
 
-        if known != value {
+        if !cells_eq(known, value) {
             Err(Error::Contradiction)
         } else {
-            Ok(())
+            Ok(false)
         }
     }
 
@@ -225,11 +625,19 @@ impl Segment {
         // Ensure that the segment is big enough to store the requested index.
         if index >= self.capacity {
             // Attempt to amortize the cost of growing the segment by growing it by a factor of
-            // the current capacity.
+            // the current capacity, as dictated by the growth policy.
             // If the amortized growth is still too small, we grow the segment by the requested
             // index.
-            let amortized = self.capacity.saturating_add(4).saturating_mul(3) / 2;
+            //
+            // The amortized capacity is clamped to the largest representable capacity, ensuring
+            // that an over-eager amortization never fails an allocation that the requested
+            // index alone would allow. The requested index itself is never clamped: failing
+            // to represent it is reported as an error rather than under-allocating.
+            let amortized = self.growth.next_capacity(self.capacity);
             let min_capacity = index.checked_add(1).ok_or(Error::OutOfMemory)?;
+            if min_capacity > MAX_CAPACITY {
+                return Err(Error::OutOfMemory);
+            }
             let new_capacity = amortized.max(min_capacity);
 
             // SAFETY:
@@ -252,7 +660,7 @@ impl Segment {
                 self.metadata
                     .as_ptr()
                     .add(self.length)
-                    .write(Metadata::Unknown);
+                    .write(Metadata::empty());
             }
 
             self.length += 1;
@@ -261,92 +669,141 @@ impl Segment {
         Ok(())
     }
 
+    /// Reserves capacity for at least `additional` more cells past the current length of the
+    /// segment, in a single allocation.
+    ///
+    /// This does nothing if the capacity of the segment is already sufficient. The length of
+    /// the segment is left untouched.
+    pub fn reserve(&mut self, additional: usize) -> Result<(), Error> {
+        let required = self
+            .length
+            .checked_add(additional)
+            .ok_or(Error::OutOfMemory)?;
+
+        if required <= self.capacity {
+            return Ok(());
+        }
+
+        if required > MAX_CAPACITY {
+            return Err(Error::OutOfMemory);
+        }
+
+        // SAFETY:
+        //  We just made sure that the required capacity is strictly greater than the current
+        //  capacity.
+        unsafe { self.grow(required) }
+    }
+
+    /// Releases the capacity of the segment that is not used by any known cell.
+    ///
+    /// After this function returns, the capacity of the segment is equal to its length, unless
+    /// its buffers are still drawn from the arena of the [`Memory`](super::Memory) it belongs to.
+    pub fn shrink_to_fit(&mut self) -> Result<(), Error> {
+        if self.capacity == self.length || self.arena.is_some() {
+            // Buffers drawn from an arena are released along with it, shrinking them would
+            // only add an allocation.
+            return Ok(());
+        }
+
+        if self.length == 0 {
+            // Only the growth policy needs to be preserved, simply release the memory.
+            let growth = self.growth;
+            *self = Self::new();
+            self.growth = growth;
+            return Ok(());
+        }
+
+        // SAFETY:
+        //  We know that `self.length` is non-zero and no greater than the current capacity.
+        unsafe { self.reallocate(self.length) }
+    }
+
     /// Attmepts to grow the capacity of the segment to a given value.
     ///
     /// # Safety
     ///
     /// `new_capacity` must be strictly greater than the current capacity of the segment.
+    #[inline]
     unsafe fn grow(&mut self, new_capacity: usize) -> Result<(), Error> {
-        let new_metadata;
-        let new_cells;
+        // SAFETY:
+        //  The new capacity is strictly greater than the current capacity (and therefore
+        //  non-zero), which is itself greater or equal to the length of the segment.
+        unsafe { self.reallocate(new_capacity) }
+    }
+
+    /// Moves the content of the segment to new buffers with the given capacity.
+    ///
+    /// Fresh buffers are allocated (rather than reallocating the existing ones in place) to
+    /// ensure that the segment remains untouched if one of the two allocations fails.
+    ///
+    /// # Safety
+    ///
+    /// `new_capacity` must be non-zero, and no less than the length of the segment.
+    unsafe fn reallocate(&mut self, new_capacity: usize) -> Result<(), Error> {
+        let metadata_layout =
+            Layout::array::<Metadata>(new_capacity).map_err(|_| Error::OutOfMemory)?;
+        let cells_layout = Layout::array::<Felt>(new_capacity).map_err(|_| Error::OutOfMemory)?;
 
-        if self.capacity == 0 {
-            // The segment is currently empty. In that case, we need to allocate memory for
-            // the first time.
-            let metadata_layout =
-                Layout::array::<Metadata>(new_capacity).map_err(|_| Error::OutOfMemory)?;
-            let cells_layout =
-                Layout::array::<Felt>(new_capacity).map_err(|_| Error::OutOfMemory)?;
+        // SAFETY:
+        //  We know by requirements of the function that `new_capacity` is non-zero, ensuring
+        //  that both of those layouts have a strictly positive size.
+        let (new_metadata, new_cells) = unsafe {
+            (
+                alloc::alloc::alloc(metadata_layout),
+                alloc::alloc::alloc(cells_layout),
+            )
+        };
 
+        if new_metadata.is_null() || new_cells.is_null() {
             // SAFETY:
-            //  We know by requirements of the function that `new_capacity` is strictly greater
-            //  than our current capacity (which is zero), ensuring that it is at least strictly
-            //  positive. This ensures that both of those layouts have a strictly positive size.
+            //  The non-null pointers have been allocated previously in this function, using
+            //  those same layouts.
             unsafe {
-                new_metadata = std::alloc::alloc(metadata_layout);
-                new_cells = std::alloc::alloc(cells_layout);
+                if !new_metadata.is_null() {
+                    alloc::alloc::dealloc(new_metadata, metadata_layout);
+                }
+                if !new_cells.is_null() {
+                    alloc::alloc::dealloc(new_cells, cells_layout);
+                }
             }
-        } else {
-            // The segment is not currently empty. In that case, we actually need to *reallocate*
-            // the memory, moving it to a new location while preserving the existing data.
 
+            return Err(Error::OutOfMemory);
+        }
+
+        let new_metadata = new_metadata as *mut Metadata;
+        let new_cells = new_cells as *mut RawValue;
+
+        // SAFETY:
+        //  Both the old and the new buffers are large enough to hold `self.length` entries,
+        //  and they are distinct allocations. Copying cells that are not initialized is fine
+        //  because the copy is untyped.
+        unsafe {
+            ptr::copy_nonoverlapping(self.metadata.as_ptr(), new_metadata, self.length);
+            ptr::copy_nonoverlapping(self.cells.as_ptr(), new_cells, self.length);
+        }
+
+        // Release the previous buffers, if any. Buffers drawn from an arena are released along
+        // with their chunk.
+        if self.arena.take().is_none() && self.capacity != 0 {
             unsafe {
                 // SAFETY:
                 //  Both of those layouts are guaranteed to be valid because they have already
                 //  been previously constructed when allocating the memory in the first place.
-                let metadata_layout = Layout::from_size_align_unchecked(
+                let old_metadata_layout = Layout::from_size_align_unchecked(
                     size_of::<Metadata>().wrapping_mul(self.capacity),
                     align_of::<Metadata>(),
                 );
-                let cells_layout = Layout::from_size_align_unchecked(
+                let old_cells_layout = Layout::from_size_align_unchecked(
                     size_of::<Felt>().wrapping_mul(self.capacity),
                     align_of::<Felt>(),
                 );
 
-                new_metadata = std::alloc::realloc(
-                    self.metadata.as_ptr() as *mut u8,
-                    metadata_layout,
-                    new_capacity,
-                );
-                new_cells =
-                    std::alloc::realloc(self.cells.as_ptr() as *mut u8, cells_layout, new_capacity);
-            }
-        }
-
-        if new_metadata.is_null() || new_cells.is_null() {
-            if !new_cells.is_null() {
-                unsafe {
-                    // SAFETY:
-                    //  This layout has been used to allocate the memory in the first place,
-                    //  ensuring that it is valid.
-                    let layout = Layout::from_size_align_unchecked(
-                        size_of::<Felt>() * new_capacity,
-                        align_of::<Felt>(),
-                    );
-
-                    // SAFETY:
-                    //  We know that this pointer has been allocated previously in this function.
-                    std::alloc::dealloc(new_cells, layout);
-                }
-            }
-
-            if !new_metadata.is_null() {
-                unsafe {
-                    // SAFETY:
-                    //  This layout has been used to allocate the memory in the first place,
-                    //  ensuring that it is valid.
-                    let layout = Layout::from_size_align_unchecked(
-                        size_of::<Metadata>() * new_capacity,
-                        align_of::<Metadata>(),
-                    );
-
-                    // SAFETY:
-                    //  We know that this pointer has been allocated previously in this function.
-                    std::alloc::dealloc(new_metadata, layout);
-                }
+                // SAFETY:
+                //  The segment has a non-zero capacity, meaning that both pointers have been
+                //  allocated with the global allocator.
+                alloc::alloc::dealloc(self.metadata.as_ptr() as *mut u8, old_metadata_layout);
+                alloc::alloc::dealloc(self.cells.as_ptr() as *mut u8, old_cells_layout);
             }
-
-            return Err(Error::OutOfMemory);
         }
 
         // Everything worked out, we can now update the segment's state.
@@ -356,8 +813,8 @@ impl Segment {
         // SAFETY:
         //  We checked previously in the function that both those pointers were non-null.
         unsafe {
-            self.metadata = NonNull::new_unchecked(new_metadata as *mut Metadata);
-            self.cells = NonNull::new_unchecked(new_cells as *mut RawValue);
+            self.metadata = NonNull::new_unchecked(new_metadata);
+            self.cells = NonNull::new_unchecked(new_cells);
         }
 
         Ok(())
@@ -365,6 +822,11 @@ impl Segment {
 }
 
 /// A [`Value`] that does not know its disciminant.
+///
+/// This uses the C representation to guarantee that every field is located at offset zero,
+/// allowing an array of [`RawValue`]s holding scalars to be reinterpreted as an array of
+/// [`Felt`]s.
+#[repr(C)]
 union RawValue {
     /// A scalar with no provenance information.
     scalar: Felt,
@@ -385,33 +847,454 @@ impl RawValue {
     }
 }
 
-/// Some metadata kept along memory cells to avoid fragmentation within the array.
-///
-/// We need to keep metadata separated because a [`Felt`] has a huge alignment of `8` bytes
-/// and the metadata we're associating with it is only `1` byte (at least for now). We would
-/// be wasting 7 bytes per entry if we were to keep the metadata with the [`Felt`]s.
-#[derive(Clone, Debug)]
-enum Metadata {
-    /// The value of the memory cell is not yet known to the Cairo virtual machine.
-    Unknown,
-    /// The value of the memroy cell is known to be a pointer with an associated precedence.
-    Pointer,
-    /// The value of the memory cell is known to be a [`Felt`].
-    Scalar,
+bitflags! {
+    /// Some metadata kept along memory cells to avoid fragmentation within the array.
+    ///
+    /// We need to keep metadata separated because a [`Felt`] has a huge alignment of `8` bytes
+    /// and the metadata we're associating with it is only `1` byte. We would be wasting 7 bytes
+    /// per entry if we were to keep the metadata with the [`Felt`]s.
+    ///
+    /// A cell with no flag set is unknown. The remaining bits of the byte are available for
+    /// annotations that do not depend on the value of the cell.
+    #[derive(Clone, Copy, Debug)]
+    struct Metadata: u8 {
+        /// The value of the memory cell is known to the Cairo virtual machine.
+        const KNOWN = 1 << 0;
+        /// The value of the memory cell is a pointer with an associated provenance, rather
+        /// than a [`Felt`].
+        ///
+        /// Only meaningful when [`KNOWN`](Self::KNOWN) is set.
+        const IS_POINTER = 1 << 1;
+        /// The memory cell is part of the public memory of the program.
+        const PUBLIC = 1 << 2;
+        /// The memory cell has been accessed by the program.
+        const ACCESSED = 1 << 3;
+    }
 }
 
 impl Metadata {
-    /// Creates a new [`Metadata`] from the provided [`ValueRef`].
-    pub fn from_value_ref(v: ValueRef) -> Self {
-        match v {
-            ValueRef::Scalar(_) => Self::Scalar,
-            ValueRef::Pointer(_) => Self::Pointer,
+    /// Returns whether the value of the memory cell is known.
+    #[inline(always)]
+    fn is_known(self) -> bool {
+        self.contains(Self::KNOWN)
+    }
+
+    /// Returns whether the memory cell holds a pointer.
+    #[inline(always)]
+    fn is_pointer(self) -> bool {
+        self.contains(Self::IS_POINTER)
+    }
+
+    /// Marks the memory cell as known to hold the provided value, leaving the annotations of
+    /// the cell untouched.
+    #[inline(always)]
+    fn set_value(&mut self, value: ValueRef) {
+        self.insert(Self::KNOWN);
+        self.set(Self::IS_POINTER, matches!(value, ValueRef::Pointer(_)));
+    }
+
+    /// Reads the value of the memory cell associated with this [`Metadata`], if it is known.
+    ///
+    /// # Safety
+    ///
+    /// `cell` must be the memory cell associated with this [`Metadata`].
+    #[inline(always)]
+    unsafe fn read(self, cell: &RawValue) -> Option<ValueRef<'_>> {
+        if !self.is_known() {
+            return None;
+        }
+
+        // SAFETY:
+        //  The caller must ensure that `cell` is associated with this metadata, meaning that it
+        //  holds a value of the kind described by the flags.
+        unsafe {
+            if self.is_pointer() {
+                Some(ValueRef::Pointer(&cell.pointer))
+            } else {
+                Some(ValueRef::Scalar(&cell.scalar))
+            }
         }
     }
 }
 
+/// The strategy used by a [`Segment`] to pick its new capacity when a write lands past its
+/// current capacity.
+///
+/// Whatever the policy, the new capacity is always large enough to hold the written cell.
+/// See [`Segment::set_growth_policy`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GrowthPolicy {
+    /// Grows the capacity by a factor of 1.5 (plus a small constant).
+    ///
+    /// This is a good default when the final size of the segment is unknown.
+    #[default]
+    Amortized,
+    /// Doubles the capacity.
+    ///
+    /// This performs fewer reallocations than [`Amortized`](Self::Amortized) for segments that
+    /// keep growing, at the cost of wasting up to half of the capacity.
+    Double,
+    /// Grows the capacity just enough to hold the written cell.
+    ///
+    /// This never wastes any memory, but reallocates on every write past the end of the
+    /// segment. It is meant for segments whose size is known in advance, and that are
+    /// [reserved](Segment::reserve) or loaded in a single run.
+    Exact,
+}
+
+impl GrowthPolicy {
+    /// Returns the capacity a segment of capacity `capacity` should grow to, regardless of the
+    /// index being written.
+    ///
+    /// The result is clamped to the largest representable capacity.
+    fn next_capacity(self, capacity: usize) -> usize {
+        match self {
+            Self::Amortized => capacity
+                .checked_add(4)
+                .and_then(|c| c.checked_mul(3))
+                .map_or(MAX_CAPACITY, |c| c / 2)
+                .min(MAX_CAPACITY),
+            Self::Double => capacity.saturating_mul(2).min(MAX_CAPACITY),
+            Self::Exact => 0,
+        }
+    }
+}
+
+/// The outcome of asserting a run of cells, as returned by [`Segment::assert_run`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct AssertedRun {
+    /// The number of cells that were previously unknown, and have been written.
+    pub fresh: usize,
+    /// The number of cells that were already known to hold the asserted value.
+    ///
+    /// A non-zero count when loading data into fresh memory usually indicates that the data
+    /// overlaps with something that was loaded before.
+    pub known: usize,
+}
+
+impl AssertedRun {
+    /// Returns the total number of cells in the run.
+    #[inline(always)]
+    pub const fn len(&self) -> usize {
+        self.fresh + self.known
+    }
+
+    /// Returns whether the run was empty.
+    #[inline(always)]
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Returns whether the value of a known cell is equal to an asserted value.
+///
+/// This is a regular comparison, which may return as soon as a difference is found.
+#[cfg(not(feature = "constant-time"))]
+#[inline(always)]
+fn cells_eq(known: ValueRef, value: ValueRef) -> bool {
+    known == value
+}
+
+/// Returns whether the value of a known cell is equal to an asserted value.
+///
+/// The converted bytes of the values are compared without any data-dependent branch. The
+/// conversion itself is performed by the field element library, and is not guaranteed to be
+/// constant-time (see [`Segment::assert_eq`]).
+#[cfg(feature = "constant-time")]
+#[inline(never)]
+fn cells_eq(known: ValueRef, value: ValueRef) -> bool {
+    let diff = match (known, value) {
+        (ValueRef::Scalar(a), ValueRef::Scalar(b)) => {
+            let (a, b) = (a.to_bytes_le(), b.to_bytes_le());
+            a.iter().zip(&b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) as usize
+        }
+        (ValueRef::Pointer(a), ValueRef::Pointer(b)) => {
+            (a.segment ^ b.segment) | (a.offset ^ b.offset)
+        }
+        _ => return false,
+    };
+
+    // Prevent the compiler from turning the accumulation into an early exit.
+    core::hint::black_box(diff) == 0
+}
+
 impl fmt::Debug for Segment {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Segment").finish_non_exhaustive()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raw_felt_slice_requires_scalars() {
+        let felts = [Felt::ONE, Felt::from(7), Felt::MAX];
+        let mut segment = Segment::new();
+        assert_eq!(segment.raw_felt_slice(), Some(&[][..]));
+        segment.assert_felt_run(0, &felts).unwrap();
+        assert_eq!(segment.raw_felt_slice(), Some(&felts[..]));
+
+        // A gap below the highest known cell.
+        let mut gapped = segment.clone();
+        gapped.set(4, ValueRef::Scalar(&Felt::ONE)).unwrap();
+        assert_eq!(gapped.raw_felt_slice(), None);
+
+        let pointer = Pointer {
+            segment: 0,
+            offset: 1,
+        };
+        segment.set(3, ValueRef::Pointer(&pointer)).unwrap();
+        assert_eq!(segment.raw_felt_slice(), None);
+    }
+
+    #[test]
+    fn shrink_to_length() {
+        let mut segment = Segment::new();
+        for index in 0..100 {
+            let value = Felt::from(index);
+            segment.set(index, ValueRef::Scalar(&value)).unwrap();
+        }
+        assert!(segment.capacity() > 100);
+
+        segment.shrink_to_fit().unwrap();
+        assert_eq!(segment.capacity(), 100);
+        assert_eq!(segment.size(), 100);
+        for index in 0..100 {
+            let value = Felt::from(index);
+            assert_eq!(segment.get(index), Some(ValueRef::Scalar(&value)));
+        }
+
+        // Shrinking again is a no-op, and the segment can still grow.
+        segment.shrink_to_fit().unwrap();
+        assert_eq!(segment.capacity(), 100);
+        segment.set(100, ValueRef::Scalar(&Felt::ONE)).unwrap();
+        assert_eq!(segment.size(), 101);
+    }
+
+    #[test]
+    fn assert_run_matches_assert_eq() {
+        let pointer = Pointer {
+            segment: 1,
+            offset: 4,
+        };
+        let values = [
+            Value::Scalar(Felt::ONE),
+            Value::Pointer(pointer),
+            Value::Scalar(Felt::from(42)),
+            Value::Scalar(Felt::ZERO),
+            Value::Scalar(Felt::MAX),
+        ];
+
+        // Some of the cells are already known, and the run starts past the end of the segment.
+        let mut base = Segment::new();
+        base.set(3, values[1].as_ref()).unwrap();
+        base.set(5, values[3].as_ref()).unwrap();
+
+        let mut looped = base.clone();
+        for (index, value) in (2..).zip(&values) {
+            looped.assert_eq(index, value.as_ref()).unwrap();
+        }
+
+        let mut run = base.clone();
+        let outcome = run.assert_run(2, &values).unwrap();
+        assert_eq!(outcome, AssertedRun { fresh: 3, known: 2 });
+
+        assert_eq!(run.size(), looped.size());
+        for index in 0..run.size() {
+            assert_eq!(run.get(index), looped.get(index));
+        }
+    }
+
+    #[test]
+    fn assert_run_stops_at_contradiction() {
+        let mut segment = Segment::new();
+        segment.set(2, ValueRef::Scalar(&Felt::TWO)).unwrap();
+
+        let values = [Value::Scalar(Felt::ONE); 4];
+        assert!(matches!(
+            segment.assert_run(0, &values),
+            Err(Error::Contradiction)
+        ));
+
+        // The cells preceding the contradiction have been asserted, the others are untouched.
+        assert_eq!(segment.get(0), Some(ValueRef::Scalar(&Felt::ONE)));
+        assert_eq!(segment.get(1), Some(ValueRef::Scalar(&Felt::ONE)));
+        assert_eq!(segment.get(2), Some(ValueRef::Scalar(&Felt::TWO)));
+        assert_eq!(segment.get(3), None);
+    }
+
+    #[test]
+    fn growth_near_max_capacity() {
+        let mut segment = Segment::new();
+        segment.assert_eq(3, ValueRef::Scalar(&Felt::ONE)).unwrap();
+        let capacity = segment.capacity();
+
+        // None of those can be allocated, but they must fail gracefully.
+        for index in [
+            MAX_CAPACITY - 1,
+            MAX_CAPACITY,
+            usize::MAX / 2,
+            usize::MAX - 1,
+            usize::MAX,
+        ] {
+            assert!(matches!(
+                segment.assert_eq(index, ValueRef::Scalar(&Felt::ONE)),
+                Err(Error::OutOfMemory)
+            ));
+            assert_eq!(segment.capacity(), capacity);
+            assert_eq!(segment.size(), 4);
+            assert_eq!(segment.get(3), Some(ValueRef::Scalar(&Felt::ONE)));
+        }
+    }
+
+    #[test]
+    fn reserve_grows_capacity_only() {
+        let mut segment = Segment::new();
+        segment.set(2, ValueRef::Scalar(&Felt::ONE)).unwrap();
+        assert_eq!(segment.size(), 3);
+
+        segment.reserve(100).unwrap();
+        let capacity = segment.capacity();
+        assert!(capacity >= 103);
+        assert_eq!(segment.size(), 3);
+        assert_eq!(segment.get(2), Some(ValueRef::Scalar(&Felt::ONE)));
+
+        // The reserved cells can be written without growing again.
+        segment.set(102, ValueRef::Scalar(&Felt::TWO)).unwrap();
+        assert_eq!(segment.capacity(), capacity);
+        assert_eq!(segment.size(), 103);
+
+        // Nothing to do when the capacity already suffices.
+        segment.reserve(capacity - 103).unwrap();
+        assert_eq!(segment.capacity(), capacity);
+
+        assert!(matches!(
+            segment.reserve(MAX_CAPACITY),
+            Err(Error::OutOfMemory)
+        ));
+        assert!(matches!(
+            segment.reserve(usize::MAX),
+            Err(Error::OutOfMemory)
+        ));
+        assert_eq!(segment.capacity(), capacity);
+        assert_eq!(segment.size(), 103);
+    }
+
+    #[test]
+    fn assert_known_small_constants() {
+        let mut segment = Segment::new();
+        let two = Felt::TWO;
+        let pointer = Pointer {
+            segment: 0,
+            offset: 0,
+        };
+        segment.set(0, ValueRef::Scalar(&Felt::ZERO)).unwrap();
+        segment.set(1, ValueRef::Scalar(&Felt::ONE)).unwrap();
+        segment.set(2, ValueRef::Pointer(&pointer)).unwrap();
+
+        segment.assert_eq(0, ValueRef::Scalar(&Felt::ZERO)).unwrap();
+        segment.assert_eq(1, ValueRef::Scalar(&Felt::ONE)).unwrap();
+        for (index, value) in [
+            (0, ValueRef::Scalar(&Felt::ONE)),
+            (1, ValueRef::Scalar(&Felt::ZERO)),
+            (1, ValueRef::Scalar(&two)),
+            (2, ValueRef::Scalar(&Felt::ZERO)),
+            (2, ValueRef::Scalar(&Felt::ONE)),
+        ] {
+            assert!(matches!(
+                segment.assert_eq(index, value),
+                Err(Error::Contradiction)
+            ));
+        }
+    }
+
+    #[test]
+    fn mark_public_requires_known_cell() {
+        let mut segment = Segment::new();
+        segment.set(0, ValueRef::Scalar(&Felt::ONE)).unwrap();
+        segment.set(2, ValueRef::Scalar(&Felt::TWO)).unwrap();
+
+        assert!(matches!(
+            segment.mark_public(8),
+            Err(Error::InvalidPublicMemory)
+        ));
+        assert!(matches!(
+            segment.mark_public(1),
+            Err(Error::InvalidPublicMemory)
+        ));
+        assert_eq!(segment.size(), 3);
+
+        segment.mark_public(2).unwrap();
+        let public: alloc::vec::Vec<_> = segment.public_cells().collect();
+        assert_eq!(public, [(2, ValueRef::Scalar(&Felt::TWO))]);
+    }
+
+    #[test]
+    fn size_and_used_cells() {
+        let mut segment = Segment::new();
+        assert_eq!((segment.size(), segment.used()), (0, 0));
+
+        let pointer = Pointer {
+            segment: 1,
+            offset: 0,
+        };
+        segment.assert_eq(2, ValueRef::Scalar(&Felt::ONE)).unwrap();
+        segment.assert_eq(9, ValueRef::Pointer(&pointer)).unwrap();
+        segment.assert_eq(5, ValueRef::Scalar(&Felt::TWO)).unwrap();
+        assert_eq!(segment.size(), 10);
+        assert_eq!(segment.highest_known_cell(), segment.size());
+        assert_eq!(segment.used(), 3);
+
+        // Filling a hole does not change the size.
+        segment.assert_eq(0, ValueRef::Scalar(&Felt::ONE)).unwrap();
+        assert_eq!((segment.size(), segment.used()), (10, 4));
+
+        // Reserved capacity is neither part of the size nor used.
+        segment.reserve(100).unwrap();
+        assert_eq!((segment.size(), segment.used()), (10, 4));
+
+        let full = Segment::from_values(&[Value::Scalar(Felt::ONE), Value::Pointer(pointer)]);
+        assert_eq!((full.size(), full.used()), (2, 2));
+    }
+
+    #[test]
+    fn from_values_is_dense() {
+        let pointer = Pointer {
+            segment: 2,
+            offset: 7,
+        };
+        let values = [
+            Value::Scalar(Felt::ONE),
+            Value::Pointer(pointer),
+            Value::Scalar(Felt::TWO),
+        ];
+
+        let segment = Segment::from_values(&values);
+        assert_eq!(segment.size(), 3);
+        assert_eq!(segment.capacity(), 3);
+        assert_eq!(segment.used(), 3);
+        for (offset, value) in values.iter().enumerate() {
+            assert_eq!(segment.get(offset), Some(value.as_ref()));
+        }
+        assert_eq!(segment.get(3), None);
+
+        let empty = Segment::from_values(&[]);
+        assert_eq!((empty.size(), empty.capacity()), (0, 0));
+    }
+
+    #[test]
+    fn shrink_empty_segment_keeps_growth_policy() {
+        let mut segment = Segment::new();
+        segment.set_growth_policy(GrowthPolicy::Exact);
+        segment.reserve(16).unwrap();
+
+        segment.shrink_to_fit().unwrap();
+        assert_eq!(segment.capacity(), 0);
+        assert_eq!(segment.growth_policy(), GrowthPolicy::Exact);
+
+        segment.set(9, ValueRef::Scalar(&Felt::ONE)).unwrap();
+        assert_eq!(segment.capacity(), 10);
+    }
+}
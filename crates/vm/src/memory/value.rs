@@ -1,11 +1,15 @@
 //! Defines the [`Value`] type.
 
+use alloc::vec::Vec;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+
 use num_traits::{ToPrimitive, Zero};
 use starknet_types_core::felt::Felt;
 
 use crate::error::Error;
 
-use super::Pointer;
+use super::{Pointer, RelocationTable};
 
 /// A value that may be stored in a [`Memory`] segment.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -22,6 +26,9 @@ pub enum Value {
 
 impl Value {
     /// Attempts to subtract two [`Value`]s.
+    ///
+    /// Subtracting a scalar from a pointer fails with [`Error::PointerTooLarge`] if the
+    /// resulting offset would be negative.
     pub fn subtract(&self, other: &Self) -> Result<Self, Error> {
         match self {
             Self::Scalar(left) => match other {
@@ -29,10 +36,11 @@ impl Value {
                 Self::Pointer(_) => Err(Error::InvalidPointerArithmetic),
             },
             Self::Pointer(left) => match other {
-                Self::Scalar(right) => match right.to_usize() {
-                    Some(right) => Ok(left.wrapping_sub(right).into()),
-                    None => Err(Error::PointerTooLarge),
-                },
+                Self::Scalar(right) => right
+                    .to_usize()
+                    .and_then(|right| left.checked_sub(right))
+                    .map(Value::Pointer)
+                    .ok_or(Error::PointerTooLarge),
                 Self::Pointer(right) => left
                     .subtract(right)
                     .map(|dist| Value::Scalar(Felt::from(dist))),
@@ -41,19 +49,25 @@ impl Value {
     }
 
     /// Attempts to add two [`Value`]s.
+    ///
+    /// Adding a scalar to a pointer fails with [`Error::PointerTooLarge`] if the resulting
+    /// offset would exceed `isize::MAX`.
     pub fn add(&self, other: &Self) -> Result<Self, Error> {
         match self {
             Self::Scalar(left) => match other {
                 Self::Scalar(right) => Ok(Value::Scalar(left + right)),
-                Self::Pointer(right) => Ok(right
-                    .wrapping_add(left.to_usize().ok_or(Error::PointerTooLarge)?)
-                    .into()),
+                Self::Pointer(right) => left
+                    .to_usize()
+                    .and_then(|left| right.checked_add(left))
+                    .map(Value::Pointer)
+                    .ok_or(Error::PointerTooLarge),
             },
             Self::Pointer(left) => match other {
-                Self::Scalar(right) => match right.to_usize() {
-                    Some(right) => Ok(left.wrapping_add(right).into()),
-                    None => Err(Error::PointerTooLarge),
-                },
+                Self::Scalar(right) => right
+                    .to_usize()
+                    .and_then(|right| left.checked_add(right))
+                    .map(Value::Pointer)
+                    .ok_or(Error::PointerTooLarge),
                 Self::Pointer(_) => Err(Error::InvalidPointerArithmetic),
             },
         }
@@ -86,22 +100,151 @@ impl Value {
         }
     }
 
+    /// Attempts to raise this value to the power of `exp`.
+    ///
+    /// Only scalars can be exponentiated.
+    pub fn pow(&self, exp: u64) -> Result<Self, Error> {
+        let Self::Scalar(base) = self else {
+            return Err(Error::InvalidPointerArithmetic);
+        };
+
+        // Exponentiation by squaring.
+        let mut result = Felt::ONE;
+        let mut base = *base;
+        let mut exp = exp;
+        while exp != 0 {
+            if exp & 1 != 0 {
+                result *= base;
+            }
+            base = base * base;
+            exp >>= 1;
+        }
+
+        Ok(Value::Scalar(result))
+    }
+
+    /// Attempts to compute the multiplicative inverse of this value in the field.
+    ///
+    /// Only non-zero scalars have an inverse.
+    #[inline]
+    pub fn inverse(&self) -> Result<Self, Error> {
+        Value::Scalar(Felt::ONE).divide(self)
+    }
+
+    /// Applies a bitwise operation to the canonical representation of two scalars.
+    ///
+    /// Results that do not fit in the field are reduced modulo the field prime. This cannot
+    /// happen for operands that fit in 251 bits, which is what the bitwise builtin accepts.
+    fn bitwise(&self, other: &Self, op: impl Fn(u8, u8) -> u8) -> Result<Self, Error> {
+        match (self, other) {
+            (Self::Scalar(left), Self::Scalar(right)) => {
+                let left = left.to_bytes_le();
+                let right = right.to_bytes_le();
+                let bytes = core::array::from_fn(|i| op(left[i], right[i]));
+                Ok(Value::Scalar(Felt::from_bytes_le(&bytes)))
+            }
+            _ => Err(Error::InvalidPointerArithmetic),
+        }
+    }
+
+    /// Attempts to compute the bitwise AND of two values.
+    ///
+    /// Only scalars can be used in bitwise operations.
+    #[inline]
+    pub fn bit_and(&self, other: &Self) -> Result<Self, Error> {
+        self.bitwise(other, |a, b| a & b)
+    }
+
+    /// Attempts to compute the bitwise OR of two values.
+    ///
+    /// Only scalars can be used in bitwise operations.
+    #[inline]
+    pub fn bit_or(&self, other: &Self) -> Result<Self, Error> {
+        self.bitwise(other, |a, b| a | b)
+    }
+
+    /// Attempts to compute the bitwise XOR of two values.
+    ///
+    /// Only scalars can be used in bitwise operations.
+    #[inline]
+    pub fn bit_xor(&self, other: &Self) -> Result<Self, Error> {
+        self.bitwise(other, |a, b| a ^ b)
+    }
+
     /// Creates a [`ValueRef`] from this [`Value`].
     #[inline(always)]
-    pub fn as_ref(&self) -> ValueRef {
+    pub fn as_ref(&self) -> ValueRef<'_> {
         match self {
             Self::Scalar(value) => ValueRef::Scalar(value),
             Self::Pointer(pointer) => ValueRef::Pointer(pointer),
         }
     }
 
-    /// Returns whether this [`Value`] equals zero.
-    pub fn is_zero(&self) -> bool {
+    /// Attempts to extract the scalar held by this [`Value`].
+    ///
+    /// Fails with [`Error::ExpectedScalar`] if the value is a pointer.
+    #[inline]
+    pub fn try_into_felt(self) -> Result<Felt, Error> {
         match self {
-            Self::Scalar(value) => value.is_zero(),
-            Self::Pointer(_) => false,
+            Self::Scalar(value) => Ok(value),
+            Self::Pointer(_) => Err(Error::ExpectedScalar),
         }
     }
+
+    /// Attempts to extract the pointer held by this [`Value`].
+    ///
+    /// Fails with [`Error::ExpectedPointer`] if the value is a scalar.
+    #[inline]
+    pub fn try_into_pointer(self) -> Result<Pointer, Error> {
+        match self {
+            Self::Scalar(_) => Err(Error::ExpectedPointer),
+            Self::Pointer(pointer) => Ok(pointer),
+        }
+    }
+
+    /// Returns whether this [`Value`] equals zero.
+    ///
+    /// See [`ValueRef::is_zero`].
+    #[inline(always)]
+    pub fn is_zero(&self) -> bool {
+        self.as_ref().is_zero()
+    }
+
+    /// Returns whether this [`Value`] equals one.
+    ///
+    /// See [`ValueRef::is_one`].
+    #[inline(always)]
+    pub fn is_one(&self) -> bool {
+        self.as_ref().is_one()
+    }
+
+    /// Returns an object that displays this [`Value`] with its scalar rendered in hexadecimal.
+    ///
+    /// See [`HexValue`].
+    #[inline(always)]
+    pub fn display_hex(&self) -> HexValue<'_> {
+        HexValue(self.as_ref())
+    }
+
+    /// Serializes this [`Value`] as a field element, pushing it to `out`.
+    ///
+    /// Scalars are pushed as-is, while pointers are pushed as their absolute address in the
+    /// relocated address space described by `table`. Values serialized this way can be read
+    /// back using a [`FeltReader`].
+    ///
+    /// # Errors
+    ///
+    /// This function fails with [`Error::UnallocatedSegment`] if the value is a pointer to a
+    /// segment that is not part of `table`.
+    pub fn to_felts(&self, table: &RelocationTable, out: &mut Vec<Felt>) -> Result<(), Error> {
+        let felt = match self {
+            Self::Scalar(value) => *value,
+            Self::Pointer(pointer) => Felt::from(table.relocate(*pointer)?),
+        };
+
+        out.push(felt);
+        Ok(())
+    }
 }
 
 impl From<Felt> for Value {
@@ -136,6 +279,37 @@ impl PartialEq<Felt> for Value {
     }
 }
 
+impl<'a> PartialEq<ValueRef<'a>> for Value {
+    /// Compares a value with a reference to a value, without copying the referenced field
+    /// element.
+    #[inline]
+    fn eq(&self, other: &ValueRef<'a>) -> bool {
+        self.as_ref() == *other
+    }
+}
+
+impl<'a> PartialEq<Value> for ValueRef<'a> {
+    /// Compares a reference to a value with a value, without copying the referenced field
+    /// element.
+    #[inline]
+    fn eq(&self, other: &Value) -> bool {
+        *self == other.as_ref()
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.as_ref(), f)
+    }
+}
+
+impl Hash for Value {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_ref().hash(state);
+    }
+}
+
 /// A reference to a [`Value`] that holds the discriminant inline.
 ///
 /// # Notes
@@ -181,4 +355,429 @@ impl<'a> ValueRef<'a> {
             Self::Pointer(pointer) => Some(pointer),
         }
     }
+
+    /// Returns whether the referenced value is the scalar zero.
+    ///
+    /// Pointers are never zero.
+    #[inline]
+    pub fn is_zero(self) -> bool {
+        match self {
+            Self::Scalar(value) => value.is_zero(),
+            Self::Pointer(_) => false,
+        }
+    }
+
+    /// Returns whether the referenced value is the scalar one.
+    ///
+    /// Pointers are never one.
+    #[inline]
+    pub fn is_one(self) -> bool {
+        match self {
+            Self::Scalar(value) => *value == Felt::ONE,
+            Self::Pointer(_) => false,
+        }
+    }
+
+    /// Attempts to extract the scalar referenced by this [`ValueRef`].
+    ///
+    /// Fails with [`Error::ExpectedScalar`] if the value is a pointer.
+    #[inline]
+    pub fn try_into_felt(self) -> Result<&'a Felt, Error> {
+        self.scalar().ok_or(Error::ExpectedScalar)
+    }
+
+    /// Attempts to extract the pointer referenced by this [`ValueRef`].
+    ///
+    /// Fails with [`Error::ExpectedPointer`] if the value is a scalar.
+    #[inline]
+    pub fn try_into_pointer(self) -> Result<&'a Pointer, Error> {
+        self.pointer().ok_or(Error::ExpectedPointer)
+    }
+
+    /// Returns an object that displays the referenced value with its scalar rendered in
+    /// hexadecimal.
+    ///
+    /// See [`HexValue`].
+    #[inline(always)]
+    pub const fn display_hex(self) -> HexValue<'a> {
+        HexValue(self)
+    }
+}
+
+/// Scalars are displayed in decimal by default. The alternate flag (`{:#}`) renders them in
+/// hexadecimal instead, like [`HexValue`].
+impl<'a> fmt::Display for ValueRef<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Scalar(value) if f.alternate() => write_hex(value, f),
+            Self::Scalar(value) => fmt::Display::fmt(value, f),
+            Self::Pointer(pointer) => fmt::Display::fmt(pointer, f),
+        }
+    }
+}
+
+/// Displays a [`ValueRef`] with its scalar rendered as a `0x`-prefixed hexadecimal number
+/// (with no leading zeros), as in `0x2a`.
+///
+/// Pointers are displayed as usual, as `segment:offset`. This is mostly useful to read
+/// addresses and hashes when debugging, which are hard to make sense of in decimal.
+///
+/// Created by [`Value::display_hex`] and [`ValueRef::display_hex`].
+#[derive(Debug, Clone, Copy)]
+pub struct HexValue<'a>(pub ValueRef<'a>);
+
+impl<'a> fmt::Display for HexValue<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            ValueRef::Scalar(value) => write_hex(value, f),
+            ValueRef::Pointer(pointer) => fmt::Display::fmt(pointer, f),
+        }
+    }
+}
+
+/// Writes the provided field element as a `0x`-prefixed hexadecimal number.
+fn write_hex(value: &Felt, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let bytes = value.to_bytes_be();
+
+    f.write_str("0x")?;
+    match bytes.iter().position(|&b| b != 0) {
+        Some(first) => {
+            write!(f, "{:x}", bytes[first])?;
+            bytes[first + 1..]
+                .iter()
+                .try_for_each(|byte| write!(f, "{byte:02x}"))
+        }
+        None => f.write_str("0"),
+    }
+}
+
+impl<'a> Hash for ValueRef<'a> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // `Value` delegates to this implementation, ensuring that a value and a reference to
+        // it always produce the same hash.
+        match self {
+            Self::Scalar(value) => {
+                state.write_u8(0);
+                state.write(&value.to_bytes_le());
+            }
+            Self::Pointer(pointer) => {
+                state.write_u8(1);
+                pointer.hash(state);
+            }
+        }
+    }
+}
+
+/// A cursor reading [`Value`]s from a flat array of field elements.
+///
+/// This is the counterpart of [`Value::to_felts`]. Because field elements do not carry any
+/// provenance information, the caller must know whether the next value is a scalar or a
+/// pointer.
+#[derive(Debug, Clone)]
+pub struct FeltReader<'a> {
+    /// The field elements that have not been read yet.
+    felts: &'a [Felt],
+    /// The table used to convert absolute addresses back into pointers.
+    table: &'a RelocationTable,
+}
+
+impl<'a> FeltReader<'a> {
+    /// Creates a new [`FeltReader`] over the provided field elements.
+    ///
+    /// `table` must be the [`RelocationTable`] that was used to serialize the pointers.
+    #[inline(always)]
+    pub fn new(felts: &'a [Felt], table: &'a RelocationTable) -> Self {
+        Self { felts, table }
+    }
+
+    /// Returns the field elements that have not been read yet.
+    #[inline(always)]
+    pub fn remaining(&self) -> &'a [Felt] {
+        self.felts
+    }
+
+    /// Returns whether all the field elements have been read.
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.felts.is_empty()
+    }
+
+    /// Reads the next field element as a scalar.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with [`Error::UnexpectedEndOfInput`] if no field element remains.
+    pub fn read_scalar(&mut self) -> Result<Felt, Error> {
+        let (first, rest) = self
+            .felts
+            .split_first()
+            .ok_or(Error::UnexpectedEndOfInput)?;
+        self.felts = rest;
+        Ok(*first)
+    }
+
+    /// Reads the next field element as a pointer.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with [`Error::UnexpectedEndOfInput`] if no field element remains,
+    /// and with [`Error::UnallocatedSegment`] if the address does not belong to any segment
+    /// of the relocation table.
+    pub fn read_pointer(&mut self) -> Result<Pointer, Error> {
+        let address = self.read_scalar()?;
+
+        address
+            .to_usize()
+            .and_then(|address| self.table.unrelocate(address))
+            .ok_or(Error::UnallocatedSegment)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn hash_as_map_key() {
+        use std::collections::HashSet;
+        use std::hash::BuildHasher;
+
+        let pointer = Pointer {
+            segment: 1,
+            offset: 2,
+        };
+        let values = [
+            Value::Scalar(Felt::ONE),
+            Value::Scalar(Felt::from(2)),
+            Value::Pointer(pointer),
+            Value::Pointer(Pointer {
+                segment: 2,
+                offset: 1,
+            }),
+        ];
+
+        let mut set = HashSet::new();
+        for value in values.iter().chain(&values) {
+            set.insert(*value);
+        }
+        assert_eq!(set.len(), values.len());
+        assert!(set.contains(&Value::Pointer(pointer)));
+        assert!(!set.contains(&Value::Scalar(Felt::ZERO)));
+
+        // A value and a reference to it hash the same way.
+        let state = std::hash::RandomState::new();
+        for value in &values {
+            assert_eq!(state.hash_one(value), state.hash_one(value.as_ref()));
+        }
+    }
+
+    /// Returns a scalar value.
+    fn scalar(value: u64) -> Value {
+        Value::Scalar(Felt::from(value))
+    }
+
+    #[test]
+    fn pow_and_inverse() {
+        let pointer = Value::Pointer(Pointer {
+            segment: 1,
+            offset: 0,
+        });
+
+        assert_eq!(scalar(3).pow(5).unwrap(), scalar(243));
+        assert_eq!(scalar(7).pow(0).unwrap(), scalar(1));
+        assert_eq!(scalar(0).pow(0).unwrap(), scalar(1));
+        assert_eq!(
+            scalar(2).pow(64).unwrap(),
+            Value::Scalar(Felt::from(1u128 << 64))
+        );
+        // (-1)^(2k) = 1
+        assert_eq!(Value::Scalar(-Felt::ONE).pow(10).unwrap(), scalar(1));
+        assert!(matches!(
+            pointer.pow(2),
+            Err(Error::InvalidPointerArithmetic)
+        ));
+
+        // 2^-1 = (p + 1) / 2
+        let half =
+            Felt::from_hex("0x400000000000008800000000000000000000000000000000000000000000001")
+                .unwrap();
+        assert_eq!(scalar(2).inverse().unwrap(), Value::Scalar(half));
+        for value in [2, 3, 12345] {
+            let inverse = scalar(value).inverse().unwrap();
+            assert_eq!(inverse.multiply(&scalar(value)).unwrap(), scalar(1));
+        }
+        assert!(matches!(scalar(0).inverse(), Err(Error::DivideByZero)));
+        assert!(matches!(
+            pointer.inverse(),
+            Err(Error::InvalidPointerArithmetic)
+        ));
+    }
+
+    #[test]
+    fn bit_operations() {
+        let (a, b) = (scalar(0b1100), scalar(0b1010));
+        assert_eq!(a.bit_and(&b).unwrap(), scalar(0b1000));
+        assert_eq!(a.bit_or(&b).unwrap(), scalar(0b1110));
+        assert_eq!(a.bit_xor(&b).unwrap(), scalar(0b0110));
+
+        // Operands spanning several bytes and limbs.
+        let high = Value::Scalar(Felt::from(u128::MAX) * Felt::from(1u128 << 64));
+        let low = scalar(u64::MAX);
+        assert_eq!(high.bit_and(&low).unwrap(), scalar(0));
+        assert_eq!(
+            high.bit_or(&low).unwrap(),
+            Value::Scalar(Felt::from(u128::MAX) * Felt::from(1u128 << 64) + Felt::from(u64::MAX))
+        );
+        assert_eq!(high.bit_xor(&high).unwrap(), scalar(0));
+
+        let pointer = Value::Pointer(Pointer {
+            segment: 1,
+            offset: 0,
+        });
+        for result in [
+            a.bit_and(&pointer),
+            pointer.bit_or(&a),
+            pointer.bit_xor(&pointer),
+        ] {
+            assert!(matches!(result, Err(Error::InvalidPointerArithmetic)));
+        }
+    }
+
+    #[test]
+    fn pointer_offset_overflow() {
+        let max = isize::MAX as usize;
+        let near = Value::Pointer(Pointer {
+            segment: 1,
+            offset: max - 1,
+        });
+        let end = Value::Pointer(Pointer {
+            segment: 1,
+            offset: max,
+        });
+
+        assert_eq!(near.add(&scalar(1)).unwrap(), end);
+        assert_eq!(scalar(1).add(&near).unwrap(), end);
+        for result in [
+            near.add(&scalar(2)),
+            scalar(2).add(&near),
+            end.add(&Value::Scalar(Felt::from(usize::MAX))),
+            // Negative scalars are huge field elements.
+            end.add(&Value::Scalar(-Felt::ONE)),
+        ] {
+            assert!(matches!(result, Err(Error::PointerTooLarge)));
+        }
+
+        let start = Value::Pointer(Pointer {
+            segment: 1,
+            offset: 1,
+        });
+        assert_eq!(
+            start.subtract(&scalar(1)).unwrap(),
+            Value::Pointer(Pointer {
+                segment: 1,
+                offset: 0,
+            })
+        );
+        assert!(matches!(
+            start.subtract(&scalar(2)),
+            Err(Error::PointerTooLarge)
+        ));
+    }
+
+    #[test]
+    fn felts_round_trip() {
+        use crate::memory::Memory;
+
+        // An array `{ data: felt*, len: felt }`, along with the end of its buffer.
+        struct Array {
+            data: Pointer,
+            len: Felt,
+            end: Pointer,
+        }
+
+        let mut memory = Memory::default();
+        for _ in 0..3 {
+            let segment = memory.allocate_segment();
+            let pointer = Pointer { segment, offset: 3 };
+            memory
+                .assert_eq(pointer, ValueRef::Scalar(&Felt::ONE))
+                .unwrap();
+        }
+        let relocated = memory.relocate().unwrap();
+        let table = relocated.table();
+
+        let array = Array {
+            data: Pointer {
+                segment: 2,
+                offset: 1,
+            },
+            len: Felt::from(3),
+            end: Pointer {
+                segment: 2,
+                offset: 4,
+            },
+        };
+        let mut felts = Vec::new();
+        for value in [
+            Value::Pointer(array.data),
+            Value::Scalar(array.len),
+            Value::Pointer(array.end),
+        ] {
+            value.to_felts(table, &mut felts).unwrap();
+        }
+        assert_eq!(felts, [Felt::from(10), Felt::from(3), Felt::from(13)]);
+
+        let mut reader = FeltReader::new(&felts, table);
+        assert_eq!(reader.read_pointer().unwrap(), array.data);
+        assert_eq!(reader.read_scalar().unwrap(), array.len);
+        assert_eq!(reader.read_pointer().unwrap(), array.end);
+        assert!(reader.is_empty());
+        assert!(matches!(
+            reader.read_scalar(),
+            Err(Error::UnexpectedEndOfInput)
+        ));
+
+        let dangling = Value::Pointer(Pointer {
+            segment: 3,
+            offset: 0,
+        });
+        assert!(matches!(
+            dangling.to_felts(table, &mut felts),
+            Err(Error::UnallocatedSegment)
+        ));
+        let mut reader = FeltReader::new(&[Felt::ZERO], table);
+        assert!(matches!(
+            reader.read_pointer(),
+            Err(Error::UnallocatedSegment)
+        ));
+    }
+
+    #[test]
+    fn value_eq_value_ref() {
+        let (a, b) = (Felt::from(5), Felt::from(6));
+        let p = Pointer {
+            segment: 1,
+            offset: 2,
+        };
+        let q = Pointer {
+            segment: 1,
+            offset: 3,
+        };
+
+        assert_eq!(Value::Scalar(a), ValueRef::Scalar(&a));
+        assert_eq!(ValueRef::Scalar(&a), Value::Scalar(a));
+        assert_ne!(Value::Scalar(a), ValueRef::Scalar(&b));
+        assert_ne!(ValueRef::Scalar(&b), Value::Scalar(a));
+
+        assert_eq!(Value::Pointer(p), ValueRef::Pointer(&p));
+        assert_eq!(ValueRef::Pointer(&p), Value::Pointer(p));
+        assert_ne!(Value::Pointer(q), ValueRef::Pointer(&p));
+        assert_ne!(ValueRef::Pointer(&p), Value::Pointer(q));
+
+        // Scalars never equal pointers, even with a matching offset.
+        let offset = Felt::from(p.offset);
+        assert_ne!(Value::Pointer(p), ValueRef::Scalar(&offset));
+        assert_ne!(ValueRef::Scalar(&offset), Value::Pointer(p));
+    }
 }
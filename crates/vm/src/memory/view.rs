@@ -0,0 +1,98 @@
+//! Defines the [`MemoryView`] type.
+
+use std::collections::HashMap;
+
+use super::{Memory, Pointer, Value, ValueRef};
+
+/// A read-only view of a [`Memory`], with an overlay of additional values layered on top of it.
+///
+/// Reads are resolved from the overlay first, then from the underlying memory. This makes it
+/// possible to try candidate values for some memory cells (for example, a witness during
+/// speculative proving) without committing them to the memory itself.
+#[derive(Debug, Clone)]
+pub struct MemoryView<'a> {
+    /// The underlying memory.
+    memory: &'a Memory,
+    /// The values that shadow the cells of the underlying memory.
+    overlay: HashMap<Pointer, Value>,
+}
+
+impl<'a> MemoryView<'a> {
+    /// Creates a new [`MemoryView`] over the provided [`Memory`], with an empty overlay.
+    #[inline]
+    pub fn new(memory: &'a Memory) -> Self {
+        Self {
+            memory,
+            overlay: HashMap::new(),
+        }
+    }
+
+    /// Returns the underlying [`Memory`].
+    #[inline(always)]
+    pub fn memory(&self) -> &'a Memory {
+        self.memory
+    }
+
+    /// Returns the values that shadow the cells of the underlying memory.
+    #[inline(always)]
+    pub fn overlay(&self) -> &HashMap<Pointer, Value> {
+        &self.overlay
+    }
+
+    /// Sets the value of a cell in the overlay, shadowing the value of the underlying memory.
+    ///
+    /// The value previously stored in the overlay for that cell, if any, is returned.
+    #[inline]
+    pub fn insert(&mut self, pointer: Pointer, value: Value) -> Option<Value> {
+        self.overlay.insert(pointer, value)
+    }
+
+    /// Removes the value of a cell from the overlay, making the value of the underlying memory
+    /// visible again.
+    #[inline]
+    pub fn remove(&mut self, pointer: Pointer) -> Option<Value> {
+        self.overlay.remove(&pointer)
+    }
+
+    /// Returns the value of the memory cell referenced by the provided [`Pointer`], if it is
+    /// known either by the overlay or by the underlying memory.
+    pub fn get(&self, pointer: Pointer) -> Option<ValueRef<'_>> {
+        match self.overlay.get(&pointer) {
+            Some(value) => Some(value.as_ref()),
+            None => self.memory.get(pointer),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet_types_core::felt::Felt;
+
+    use super::*;
+
+    #[test]
+    fn overlay_shadows_memory() {
+        let mut memory = Memory::default();
+        let segment = memory.allocate_segment();
+        let known = Pointer { segment, offset: 0 };
+        let unknown = Pointer { segment, offset: 1 };
+        memory
+            .assert_eq(known, ValueRef::Scalar(&Felt::ONE))
+            .unwrap();
+
+        let mut view = MemoryView::new(&memory);
+        assert_eq!(view.get(unknown), None);
+
+        let witness = Value::Scalar(Felt::from(42));
+        assert_eq!(view.insert(unknown, witness), None);
+        assert_eq!(view.get(unknown), Some(witness.as_ref()));
+        assert_eq!(view.get(known), Some(ValueRef::Scalar(&Felt::ONE)));
+        assert_eq!(memory.get(unknown), None);
+
+        // Known cells can be shadowed as well, until the overlay value is removed.
+        view.insert(known, Value::Pointer(unknown));
+        assert_eq!(view.get(known), Some(ValueRef::Pointer(&unknown)));
+        assert_eq!(view.remove(known), Some(Value::Pointer(unknown)));
+        assert_eq!(view.get(known), Some(ValueRef::Scalar(&Felt::ONE)));
+    }
+}
@@ -0,0 +1,148 @@
+//! Defines the [`Program`] type, responsible for holding the bytecode of a compiled Cairo
+//! program.
+
+use alloc::vec::Vec;
+
+use num_traits::ToPrimitive;
+
+use crate::instr::{ApUpdate, Instruction, Op1Source, OpCode};
+use crate::memory::Value;
+
+/// The bytecode of a compiled Cairo program, ready to be loaded in the program segment of a
+/// [`CairoVM`](crate::CairoVM).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Program {
+    /// The cells of the program segment.
+    bytecode: Vec<Value>,
+}
+
+impl Program {
+    /// Creates a new [`Program`] from its bytecode.
+    #[inline(always)]
+    pub fn new(bytecode: Vec<Value>) -> Self {
+        Self { bytecode }
+    }
+
+    /// Returns the bytecode of the program.
+    #[inline(always)]
+    pub fn bytecode(&self) -> &[Value] {
+        &self.bytecode
+    }
+
+    /// Returns a rough estimate of the number of memory cells the program will use.
+    ///
+    /// This is the length of the bytecode, plus the number of cells the **Allocation Pointer**
+    /// would advance by if each instruction was executed exactly once. Loops, recursion and
+    /// data written by hints are not taken into account.
+    ///
+    /// The estimate is only a hint meant to preallocate memory, not a guarantee.
+    pub fn estimate_memory(&self) -> usize {
+        let mut working = 0usize;
+        let mut index = 0;
+
+        while let Some(cell) = self.bytecode.get(index) {
+            let Some(instruction) = cell_as_instruction(cell) else {
+                // Data embedded in the bytecode.
+                index += 1;
+                continue;
+            };
+
+            let immediate = match instruction.op1_source() {
+                Ok(Op1Source::PC) => self.bytecode.get(index + 1),
+                _ => None,
+            };
+
+            let allocated = match (instruction.op_code(), instruction.ap_update()) {
+                // `call` pushes the previous frame pointer and the return address.
+                (Ok(OpCode::Call), _) => 2,
+                (_, Ok(ApUpdate::Increment)) => 1,
+                // `ap += imm`, the most common way to allocate a block of cells.
+                (_, Ok(ApUpdate::AddResult)) => match immediate {
+                    Some(Value::Scalar(imm)) => imm.to_usize().unwrap_or(0),
+                    _ => 0,
+                },
+                _ => 0,
+            };
+
+            working = working.saturating_add(allocated);
+            index += instruction.size().unwrap_or(1);
+        }
+
+        self.bytecode.len().saturating_add(working)
+    }
+}
+
+impl From<Vec<Value>> for Program {
+    #[inline(always)]
+    fn from(bytecode: Vec<Value>) -> Self {
+        Self::new(bytecode)
+    }
+}
+
+/// Attempts to interpret the provided cell as an instruction.
+fn cell_as_instruction(cell: &Value) -> Option<Instruction> {
+    match cell {
+        Value::Scalar(word) => Instruction::try_from_felt(word).ok(),
+        Value::Pointer(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use starknet_types_core::felt::Felt;
+
+    use super::*;
+    use crate::instr::{InstructionBuilder, PcUpdate, ResultLogic};
+    use crate::memory::Pointer;
+
+    /// Encodes an instruction as a cell of the program.
+    fn word(instruction: InstructionBuilder) -> Value {
+        Value::Scalar(Felt::from(instruction.build().0))
+    }
+
+    #[test]
+    fn estimate_covers_bytecode() {
+        let imm = |value: u64| Value::Scalar(Felt::from(value));
+        let with_imm = InstructionBuilder::new()
+            .op1_source(Op1Source::PC)
+            .op1_offset(1);
+
+        let bytecode = vec![
+            // [ap] = 1; ap++
+            word(
+                with_imm
+                    .op_code(OpCode::AssertEq)
+                    .ap_update(ApUpdate::Increment),
+            ),
+            imm(1),
+            // ap += 10
+            word(with_imm.ap_update(ApUpdate::AddResult)),
+            imm(10),
+            // call rel 3
+            word(
+                with_imm
+                    .op_code(OpCode::Call)
+                    .op0_offset(1)
+                    .pc_update(PcUpdate::RelativeJump)
+                    .result_logic(ResultLogic::Op1),
+            ),
+            imm(3),
+            // Data embedded in the bytecode.
+            Value::Pointer(Pointer {
+                segment: 1,
+                offset: 0,
+            }),
+        ];
+        let len = bytecode.len();
+
+        let program = Program::new(bytecode);
+        assert!(program.estimate_memory() >= len);
+        assert_eq!(program.estimate_memory(), len + 1 + 10 + 2);
+
+        assert_eq!(Program::default().estimate_memory(), 0);
+        let data = Program::new(vec![Value::Scalar(Felt::MAX); 4]);
+        assert_eq!(data.estimate_memory(), 4);
+    }
+}
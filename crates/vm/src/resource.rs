@@ -0,0 +1,95 @@
+//! Defines the [`ResourceTracker`] trait, used to bound the work performed by a program.
+//!
+//! Limiting the number of steps (see [`RunOptions::max_steps`]) does not account for the fact
+//! that some steps are more expensive than others. A resource tracker is charged the cost of
+//! every step executed by [`CairoVM::run_metered`]: the cost of the instruction itself, plus the
+//! cost of each builtin that deduced one of its operands (see [`Builtin::cost`]).
+//!
+//! [`RunOptions::max_steps`]: crate::RunOptions::max_steps
+//! [`CairoVM::run_metered`]: crate::CairoVM::run_metered
+//! [`Builtin::cost`]: crate::builtin::Builtin::cost
+
+use crate::error::Error;
+
+/// The cost of executing a single instruction, charged by default on every step.
+pub const DEFAULT_INSTRUCTION_COST: u64 = 1;
+
+/// Keeps track of the resources consumed by a running program.
+pub trait ResourceTracker {
+    /// Returns the cost of executing a single instruction, regardless of the builtins it
+    /// involves.
+    ///
+    /// The default implementation returns [`DEFAULT_INSTRUCTION_COST`].
+    fn instruction_cost(&self) -> u64 {
+        DEFAULT_INSTRUCTION_COST
+    }
+
+    /// Consumes the resources required by a step costing `step_cost`.
+    ///
+    /// This function is called after each successful step. Returning an error stops the
+    /// execution of the program.
+    fn consume(&mut self, step_cost: u64) -> Result<(), Error>;
+}
+
+/// An implementation of [`ResourceTracker`] that never runs out of resources.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Unmetered;
+impl ResourceTracker for Unmetered {
+    #[inline(always)]
+    fn consume(&mut self, _step_cost: u64) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// A [`ResourceTracker`] that stops the execution once a fixed amount of gas has been consumed.
+#[derive(Debug, Clone)]
+pub struct GasMeter {
+    /// The total amount of gas that may be consumed.
+    budget: u64,
+    /// The amount of gas consumed so far.
+    consumed: u64,
+}
+
+impl GasMeter {
+    /// Creates a new [`GasMeter`] allowing up to `budget` gas to be consumed.
+    #[inline(always)]
+    pub const fn new(budget: u64) -> Self {
+        Self {
+            budget,
+            consumed: 0,
+        }
+    }
+
+    /// Returns the total amount of gas that may be consumed.
+    #[inline(always)]
+    pub const fn budget(&self) -> u64 {
+        self.budget
+    }
+
+    /// Returns the amount of gas consumed so far.
+    #[inline(always)]
+    pub const fn consumed(&self) -> u64 {
+        self.consumed
+    }
+
+    /// Returns the amount of gas that can still be consumed.
+    #[inline(always)]
+    pub const fn remaining(&self) -> u64 {
+        self.budget - self.consumed
+    }
+}
+
+impl ResourceTracker for GasMeter {
+    /// Consumes `step_cost` gas.
+    ///
+    /// Fails with [`Error::OutOfGas`] if the remaining gas is not sufficient, in which case no
+    /// gas is consumed.
+    fn consume(&mut self, step_cost: u64) -> Result<(), Error> {
+        if step_cost > self.remaining() {
+            return Err(Error::OutOfGas);
+        }
+
+        self.consumed += step_cost;
+        Ok(())
+    }
+}
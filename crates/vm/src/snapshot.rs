@@ -0,0 +1,270 @@
+//! Serialization of [`VmSnapshot`]s, enabled by the `serde` feature.
+//!
+//! Snapshots are serialized in a human-readable form. Registers and pointers are rendered as
+//! `segment:offset` (like in [`JsonTrace`](crate::trace::JsonTrace)), and scalars as
+//! hexadecimal strings prefixed with `0x`. Each segment of the memory is serialized as a list
+//! of cells, unknown cells being `null`.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use starknet_types_core::felt::Felt;
+
+use crate::cpu::Cpu;
+use crate::memory::{Memory, Pointer, Value, ValueRef};
+use crate::VmSnapshot;
+
+/// The serialized representation of a [`Cpu`].
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Registers {
+    /// The **Program Counter**.
+    pc: String,
+    /// The **Allocation Pointer**.
+    ap: String,
+    /// The **Frame Pointer**.
+    fp: String,
+}
+
+impl Registers {
+    /// Creates a new [`Registers`] from the state of a [`Cpu`].
+    pub(crate) fn new(cpu: &Cpu) -> Self {
+        Self {
+            pc: cpu.pc.to_string(),
+            ap: cpu.ap.to_string(),
+            fp: cpu.fp.to_string(),
+        }
+    }
+
+    /// Parses the registers back into a [`Cpu`].
+    pub(crate) fn parse<E: serde::de::Error>(&self) -> Result<Cpu, E> {
+        let register = |register: &str| {
+            parse_pointer(register)
+                .ok_or_else(|| E::custom(format!("invalid register `{register}`")))
+        };
+
+        Ok(Cpu {
+            pc: register(&self.pc)?,
+            ap: register(&self.ap)?,
+            fp: register(&self.fp)?,
+        })
+    }
+}
+
+/// The serialized representation of a [`VmSnapshot`].
+#[derive(Serialize, Deserialize)]
+struct Repr {
+    /// The registers when the snapshot was taken.
+    cpu: Registers,
+    /// The registers before the first step was executed.
+    initial: Registers,
+    /// The number of steps executed when the snapshot was taken.
+    steps: usize,
+    /// The cells of each segment of the memory.
+    memory: Vec<Vec<Option<String>>>,
+}
+
+impl Serialize for VmSnapshot {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let memory = self
+            .memory
+            .into_iter()
+            .map(|(_, segment)| {
+                (0..segment.highest_known_cell())
+                    .map(|offset| segment.get(offset).map(cell_to_string))
+                    .collect()
+            })
+            .collect();
+
+        Repr {
+            cpu: Registers::new(&self.cpu),
+            initial: Registers::new(&self.initial),
+            steps: self.steps,
+            memory,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for VmSnapshot {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = Repr::deserialize(deserializer)?;
+
+        let mut memory = Memory::default();
+        for cells in &repr.memory {
+            let segment = memory.allocate_segment();
+
+            for (offset, cell) in cells.iter().enumerate() {
+                let Some(cell) = cell else { continue };
+
+                let pointer = Pointer { segment, offset };
+                let value = parse_cell(cell)?;
+
+                memory
+                    .assert_eq(pointer, value.as_ref())
+                    .map_err(|err| D::Error::custom(format!("{err:?}")))?;
+            }
+        }
+
+        Ok(VmSnapshot {
+            cpu: repr.cpu.parse()?,
+            memory,
+            steps: repr.steps,
+            initial: repr.initial.parse()?,
+        })
+    }
+}
+
+/// Renders a memory cell as a string.
+pub(crate) fn cell_to_string(cell: ValueRef) -> String {
+    match cell {
+        ValueRef::Pointer(pointer) => pointer.to_string(),
+        ValueRef::Scalar(scalar) => {
+            let mut s = String::with_capacity(66);
+            s.push_str("0x");
+            for byte in scalar.to_bytes_be() {
+                s.push_str(&format!("{byte:02x}"));
+            }
+            s
+        }
+    }
+}
+
+/// Parses a memory cell rendered by [`cell_to_string`].
+pub(crate) fn parse_cell<E: serde::de::Error>(s: &str) -> Result<Value, E> {
+    match parse_pointer(s) {
+        Some(pointer) => Ok(Value::Pointer(pointer)),
+        None => parse_felt(s)
+            .map(Value::Scalar)
+            .ok_or_else(|| E::custom(format!("invalid cell `{s}`"))),
+    }
+}
+
+/// Parses a pointer rendered as `segment:offset`.
+pub(crate) fn parse_pointer(s: &str) -> Option<Pointer> {
+    let (segment, offset) = s.split_once(':')?;
+
+    Some(Pointer {
+        segment: segment.parse().ok()?,
+        offset: offset.parse().ok()?,
+    })
+}
+
+/// Parses a field element rendered as a `0x`-prefixed hexadecimal string of at most 64 digits.
+fn parse_felt(s: &str) -> Option<Felt> {
+    let digits = s.strip_prefix("0x")?;
+    if digits.is_empty() || digits.len() > 64 || !digits.is_ascii() {
+        return None;
+    }
+
+    let mut bytes = [0u8; 32];
+    for (i, digit) in digits.bytes().rev().enumerate() {
+        let value = (digit as char).to_digit(16)? as u8;
+        bytes[31 - i / 2] |= value << (4 * (i % 2));
+    }
+
+    Some(Felt::from_bytes_be(&bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hint::NoopHintProcessor;
+    use crate::instr::*;
+    use crate::trace::NoopTrace;
+    use crate::{CairoVM, RunOptions};
+
+    /// The number of instructions of the program run by the tests.
+    const STEPS: usize = 8;
+
+    /// Creates a virtual machine incrementing the last cell of the stack [`STEPS`] times, with
+    /// a pointer at the base of its frame.
+    fn counting_vm() -> CairoVM {
+        // [ap] = [ap - 1] + 1; ap++
+        let increment = InstructionBuilder::new()
+            .op_code(OpCode::AssertEq)
+            .op0_offset(-1)
+            .op1_source(Op1Source::PC)
+            .op1_offset(1)
+            .result_logic(ResultLogic::Add)
+            .ap_update(ApUpdate::Increment)
+            .build();
+        let program = [
+            Value::Scalar(Felt::from(increment.0)),
+            Value::Scalar(Felt::ONE),
+        ]
+        .repeat(STEPS);
+
+        let mut vm = CairoVM::new(&program, Vec::new()).unwrap();
+        let base = vm.ap();
+        vm.assert_eq(base, ValueRef::Pointer(&vm.pc())).unwrap();
+        vm.assert_eq(base.wrapping_add(1), ValueRef::Scalar(&Felt::ZERO))
+            .unwrap();
+        vm.set_cpu(Cpu {
+            pc: vm.pc(),
+            ap: base.wrapping_add(2),
+            fp: base.wrapping_add(1),
+        })
+        .unwrap();
+        vm
+    }
+
+    /// Runs `vm` until the end of its program.
+    fn finish(vm: &mut CairoVM) {
+        let end = Pointer {
+            segment: CairoVM::PROGRAM_SEGMENT,
+            offset: STEPS * 2,
+        };
+        vm.run(
+            end,
+            &RunOptions::default(),
+            &mut NoopHintProcessor,
+            &mut NoopTrace,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn resume_serialized_snapshot() {
+        let mut vm = counting_vm();
+        for _ in 0..3 {
+            vm.step(&mut NoopHintProcessor, &mut NoopTrace).unwrap();
+        }
+
+        let json = serde_json::to_string(&vm.save()).unwrap();
+        let snapshot: VmSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&snapshot).unwrap(), json);
+
+        let mut resumed = CairoVM::resume(snapshot, Vec::new()).unwrap();
+        assert_eq!(resumed.steps(), 3);
+        finish(&mut resumed);
+
+        finish(&mut vm);
+        assert_eq!(resumed.cpu(), vm.cpu());
+        assert_eq!(resumed.steps(), STEPS);
+        assert_eq!(resumed.initial_registers(), vm.initial_registers());
+        assert_eq!(resumed.memory().diff(vm.memory()), []);
+    }
+
+    #[test]
+    fn rejects_malformed_cells() {
+        let parse = |cell: serde_json::Value| {
+            let registers = serde_json::json!({ "pc": "0:0", "ap": "1:0", "fp": "1:0" });
+            serde_json::from_value::<VmSnapshot>(serde_json::json!({
+                "cpu": registers,
+                "initial": registers,
+                "steps": 0,
+                "memory": [[cell], []],
+            }))
+        };
+
+        assert!(parse(serde_json::json!("0x1f")).is_ok());
+        assert!(parse(serde_json::json!("2:3")).is_ok());
+        assert!(parse(serde_json::Value::Null).is_ok());
+        for cell in ["1f", "0x", "0xg", "1:x"] {
+            assert!(parse(serde_json::json!(cell)).is_err());
+        }
+    }
+}
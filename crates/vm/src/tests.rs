@@ -0,0 +1,1327 @@
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use starknet_types_core::felt::Felt;
+
+use crate::builtin::{
+    Builtin, CannotDeduce, Ecdsa, OutputBuiltin, RangeCheckBuiltin, Signature, SignatureVerifier,
+};
+use crate::cpu::Cpu;
+use crate::error::Error;
+use crate::hint::{HintProcessor, NoopHintProcessor};
+use crate::instr::*;
+use crate::memory::{Memory, Pointer, Segment, Value, ValueRef};
+use crate::resource::{GasMeter, ResourceTracker};
+use crate::trace::{NoopTrace, RegisterTrace, TraceRecord, UndoLog};
+use crate::{CairoVM, RunOptions};
+
+/// A builtin that deduces nothing, but reserves some capacity in its segment.
+struct Reserving(usize);
+
+impl Builtin for Reserving {
+    fn deduce(&self, _: usize, _: &Segment, _: &mut Value) -> Result<(), CannotDeduce> {
+        Err(CannotDeduce)
+    }
+
+    fn initial_segment_size(&self) -> usize {
+        self.0
+    }
+}
+
+/// Returns a pointer to `offset` in `segment`.
+fn ptr(segment: usize, offset: usize) -> Pointer {
+    Pointer { segment, offset }
+}
+
+/// Encodes an instruction as a cell of the program.
+fn word(instruction: InstructionBuilder) -> Value {
+    Value::Scalar(Felt::from(instruction.build().0))
+}
+
+/// Creates a virtual machine running `program`, without any builtin.
+fn vm_for(program: &[Value]) -> CairoVM {
+    CairoVM::new(program, Vec::new()).unwrap()
+}
+
+/// Executes a single step of `vm`, without hints.
+fn step(vm: &mut CairoVM) -> Result<crate::StepOutcome, Error> {
+    vm.step(&mut NoopHintProcessor, &mut NoopTrace)
+}
+
+/// `[ap] = [ap - 1] + 1; ap++`
+const INCREMENT: InstructionBuilder = InstructionBuilder::new()
+    .op_code(OpCode::AssertEq)
+    .op0_offset(-1)
+    .op1_source(Op1Source::PC)
+    .op1_offset(1)
+    .result_logic(ResultLogic::Add)
+    .ap_update(ApUpdate::Increment);
+
+/// Returns a program incrementing the last cell written to the stack `count` times.
+fn counting_program(count: usize) -> Vec<Value> {
+    let mut program = Vec::with_capacity(count * 2);
+    for _ in 0..count {
+        program.push(word(INCREMENT));
+        program.push(Value::Scalar(Felt::ONE));
+    }
+    program
+}
+
+/// Creates a virtual machine running [`counting_program`], with a zero on the stack.
+fn counting_vm(count: usize) -> CairoVM {
+    let mut vm = vm_for(&counting_program(count));
+    vm.assert_eq(ptr(1, 0), ValueRef::Scalar(&Felt::ZERO))
+        .unwrap();
+    let mut cpu = vm.cpu().clone();
+    cpu.ap = ptr(1, 1);
+    cpu.fp = ptr(1, 1);
+    vm.set_cpu(cpu).unwrap();
+    vm
+}
+
+#[test]
+fn new_lays_out_segments() {
+    let program = [Value::Scalar(Felt::ONE), Value::Scalar(Felt::TWO)];
+    let builtins: Vec<Box<dyn Builtin>> = vec![Box::new(Reserving(0)), Box::new(Reserving(64))];
+    let vm = CairoVM::new(&program, builtins).unwrap();
+
+    assert_eq!(vm.memory().num_segments(), 4);
+    assert_eq!(vm.pc(), ptr(CairoVM::PROGRAM_SEGMENT, 0));
+    assert_eq!(vm.ap(), ptr(CairoVM::EXECUTION_SEGMENT, 0));
+    assert_eq!(vm.fp(), ptr(CairoVM::EXECUTION_SEGMENT, 0));
+    assert_eq!(vm.initial_registers(), vm.cpu());
+
+    let bytecode = vm.memory().segment(CairoVM::PROGRAM_SEGMENT).unwrap();
+    assert_eq!(bytecode.size(), 2);
+    assert_eq!(bytecode.get(1), Some(program[1].as_ref()));
+
+    let execution = vm.memory().segment(CairoVM::EXECUTION_SEGMENT).unwrap();
+    assert_eq!(execution.size(), 0);
+
+    let reserved = vm
+        .memory()
+        .segment(CairoVM::FIRST_BUILTIN_SEGMENT + 1)
+        .unwrap();
+    assert!(reserved.capacity() >= 64);
+    assert_eq!(reserved.size(), 0);
+}
+
+/// `jmp rel [ap + 1] if [ap] != 0`
+const JNZ: InstructionBuilder = InstructionBuilder::new()
+    .op1_source(Op1Source::AP)
+    .op1_offset(1)
+    .pc_update(PcUpdate::ConditionalJump);
+
+#[test]
+fn jnz_pointer_dst_takes_jump() {
+    let mut vm = vm_for(&[word(JNZ)]);
+    let ap = vm.ap();
+    vm.assert_eq(ap, ValueRef::Pointer(&ptr(0, 0))).unwrap();
+    vm.assert_eq(ap.wrapping_add(1), ValueRef::Scalar(&Felt::from(7)))
+        .unwrap();
+
+    step(&mut vm).unwrap();
+    assert_eq!(vm.pc(), ptr(0, 7));
+}
+
+#[test]
+fn jnz_zero_dst_falls_through() {
+    let mut vm = vm_for(&[word(JNZ)]);
+    let ap = vm.ap();
+    vm.assert_eq(ap, ValueRef::Scalar(&Felt::ZERO)).unwrap();
+    vm.assert_eq(ap.wrapping_add(1), ValueRef::Scalar(&Felt::from(7)))
+        .unwrap();
+
+    step(&mut vm).unwrap();
+    assert_eq!(vm.pc(), ptr(0, 1));
+}
+
+#[test]
+fn jnz_unknown_dst() {
+    let mut vm = vm_for(&[word(JNZ)]);
+    let ap = vm.ap();
+    vm.assert_eq(ap.wrapping_add(1), ValueRef::Scalar(&Felt::from(7)))
+        .unwrap();
+
+    assert!(matches!(step(&mut vm), Err(Error::CantDeduceDst)));
+    assert_eq!(vm.pc(), ptr(0, 0));
+}
+
+#[test]
+fn jnz_unknown_op1() {
+    let mut vm = vm_for(&[word(JNZ)]);
+    let ap = vm.ap();
+    vm.assert_eq(ap, ValueRef::Scalar(&Felt::ONE)).unwrap();
+
+    assert!(matches!(step(&mut vm), Err(Error::CantDeduceOp1)));
+    assert_eq!(vm.pc(), ptr(0, 0));
+}
+
+#[test]
+fn jnz_zero_dst_ignores_unknown_offset() {
+    let mut vm = vm_for(&[word(JNZ)]);
+    let ap = vm.ap();
+    vm.assert_eq(ap, ValueRef::Scalar(&Felt::ZERO)).unwrap();
+
+    step(&mut vm).unwrap();
+    assert_eq!(vm.pc(), ptr(0, 1));
+    assert_eq!(vm.memory().get(ap.wrapping_add(1)), None);
+}
+
+#[test]
+fn asserted_fast_path_matches_deduction() {
+    let mut deduced = counting_vm(16);
+    let initial = deduced.cpu().clone();
+    let mut expected = Vec::new();
+    while deduced.pc() != ptr(0, 32) {
+        expected.push(step(&mut deduced).unwrap().cpu);
+    }
+
+    // Every operand is known the second time around.
+    let mut asserted = CairoVM::from_parts(deduced.memory().clone(), initial).unwrap();
+    for cpu in &expected {
+        let outcome = step(&mut asserted).unwrap();
+        assert_eq!(&outcome.cpu, cpu);
+        assert_eq!(outcome.dst.origin, crate::OperandOrigin::Asserted);
+    }
+    assert_eq!(asserted.memory().diff(deduced.memory()), []);
+
+    // A contradiction is still caught by the fast path.
+    let mut tampered = counting_vm(16);
+    tampered
+        .assert_eq(ptr(1, 1), ValueRef::Scalar(&Felt::TWO))
+        .unwrap();
+    assert!(matches!(
+        tampered.run(
+            ptr(0, 32),
+            &RunOptions::default(),
+            &mut NoopHintProcessor,
+            &mut NoopTrace
+        ),
+        Err(Error::Contradiction)
+    ));
+}
+
+#[test]
+fn resume_mid_run() {
+    let builtins = || -> Vec<Box<dyn Builtin>> { vec![Box::new(Reserving(64))] };
+    let end = ptr(0, 16);
+
+    let mut vm = CairoVM::new(&counting_program(8), builtins()).unwrap();
+    vm.assert_eq(vm.ap(), ValueRef::Scalar(&Felt::ZERO))
+        .unwrap();
+    let mut cpu = vm.cpu().clone();
+    cpu.ap = ptr(1, 1);
+    cpu.fp = ptr(1, 1);
+    vm.set_cpu(cpu).unwrap();
+
+    for _ in 0..3 {
+        step(&mut vm).unwrap();
+    }
+    let snapshot = vm.snapshot();
+    vm.run(
+        end,
+        &RunOptions::default(),
+        &mut NoopHintProcessor,
+        &mut NoopTrace,
+    )
+    .unwrap();
+
+    let mut resumed = CairoVM::resume(snapshot, builtins()).unwrap();
+    assert_eq!(resumed.steps(), 3);
+    let reserved = resumed.memory().segment(CairoVM::FIRST_BUILTIN_SEGMENT);
+    assert!(reserved.unwrap().capacity() >= 64);
+
+    resumed
+        .run(
+            end,
+            &RunOptions::default(),
+            &mut NoopHintProcessor,
+            &mut NoopTrace,
+        )
+        .unwrap();
+    assert_eq!(resumed.cpu(), vm.cpu());
+    assert_eq!(resumed.steps(), vm.steps());
+    assert_eq!(resumed.initial_registers(), vm.initial_registers());
+    assert_eq!(resumed.memory().diff(vm.memory()), []);
+}
+
+/// A hint processor running a single hint, which does nothing, before the instruction at
+/// `target`.
+struct CountedHint {
+    target: Pointer,
+}
+
+impl HintProcessor for CountedHint {
+    fn execute(&mut self, _vm: &mut CairoVM, _pc: Pointer) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn hint_count(&self, pc: Pointer) -> usize {
+        usize::from(pc == self.target)
+    }
+}
+
+#[test]
+fn hint_count_in_loop() {
+    const ITERATIONS: u64 = 12;
+
+    // 0: [ap] = [ap - 1] + (-1); ap++
+    // 2: jmp rel -2 if [ap - 1] != 0
+    let program = [
+        word(INCREMENT),
+        Value::Scalar(-Felt::ONE),
+        word(JNZ.dst_offset(-1).op1_source(Op1Source::PC)),
+        Value::Scalar(-Felt::TWO),
+    ];
+    let mut vm = vm_for(&program);
+    vm.assert_eq(ptr(1, 0), ValueRef::Scalar(&Felt::from(ITERATIONS)))
+        .unwrap();
+    let mut cpu = vm.cpu().clone();
+    cpu.ap = ptr(1, 1);
+    cpu.fp = ptr(1, 1);
+    vm.set_cpu(cpu).unwrap();
+
+    let mut hints = CountedHint { target: ptr(0, 0) };
+    vm.run(
+        ptr(0, 4),
+        &RunOptions::default(),
+        &mut hints,
+        &mut NoopTrace,
+    )
+    .unwrap();
+
+    assert_eq!(vm.steps() as u64, ITERATIONS * 2);
+    assert_eq!(vm.hint_count() as u64, ITERATIONS);
+    assert_eq!(vm.hint_stats().count_at(ptr(0, 0)) as u64, ITERATIONS);
+    assert_eq!(vm.hint_stats().count_at(ptr(0, 2)), 0);
+    assert_eq!(
+        vm.hint_stats().iter().collect::<Vec<_>>(),
+        [(ptr(0, 0), ITERATIONS as usize)]
+    );
+}
+
+/// Returns the value of the cell at `addr` in the memory of `vm`, if it is known.
+fn cell_at(vm: &CairoVM, addr: Pointer) -> Option<Value> {
+    vm.memory().get(addr).map(ValueRef::copied)
+}
+
+#[test]
+fn operands_resolve_to_memory_cells() {
+    use crate::{StepContext, StepContextFlags};
+
+    let mut vm = vm_for(&[word(INCREMENT), Value::Scalar(Felt::from(9))]);
+    for (offset, value) in [
+        (0, Value::Pointer(ptr(1, 3))),
+        (1, Value::Scalar(Felt::from(5))),
+        (3, Value::Scalar(Felt::from(7))),
+        (5, Value::Pointer(ptr(1, 0))),
+    ] {
+        vm.assert_eq(ptr(1, offset), value.as_ref()).unwrap();
+    }
+    let mut cpu = vm.cpu().clone();
+    cpu.ap = ptr(1, 3);
+    cpu.fp = ptr(1, 2);
+    vm.set_cpu(cpu).unwrap();
+    let immediate = cell_at(&vm, ptr(0, 1));
+
+    let sources = [Op1Source::Op0, Op1Source::PC, Op1Source::FP, Op1Source::AP];
+    for (dst_register, op0_register) in [
+        (DstRegister::AP, Op0Register::FP),
+        (DstRegister::FP, Op0Register::AP),
+    ] {
+        for offset in -2..=2 {
+            for op1_source in sources {
+                let instruction = DecodedInstruction {
+                    dst_offset: offset,
+                    op0_offset: -offset,
+                    op1_offset: if op1_source == Op1Source::PC {
+                        offset.rem_euclid(2)
+                    } else {
+                        offset
+                    },
+                    dst_register,
+                    op0_register,
+                    op1_source,
+                    ..INCREMENT.build().decode().unwrap()
+                };
+                let mut ctx = StepContext::initial(instruction);
+
+                let (dst_base, op0_base) = match dst_register {
+                    DstRegister::AP => (vm.ap(), vm.fp()),
+                    DstRegister::FP => (vm.fp(), vm.ap()),
+                };
+
+                crate::compute_dst(&mut ctx, &vm);
+                assert_eq!(ctx.dst_addr, dst_base.add_signed(offset as isize));
+                let dst = ctx.flags.contains(StepContextFlags::DST_ASSERTED);
+                assert_eq!(cell_at(&vm, ctx.dst_addr), dst.then_some(ctx.dst));
+
+                crate::compute_op0(&mut ctx, &vm);
+                assert_eq!(ctx.op0_addr, op0_base.add_signed(-offset as isize));
+                let op0 = ctx.flags.contains(StepContextFlags::OP0_ASSERTED);
+                assert_eq!(cell_at(&vm, ctx.op0_addr), op0.then_some(ctx.op0));
+
+                let result = crate::compute_op1(&mut ctx, &vm, immediate);
+                match (op1_source, cell_at(&vm, ctx.op0_addr)) {
+                    (Op1Source::Op0, None) => {
+                        assert!(matches!(result, Err(Error::CantDeduceOp0)));
+                        continue;
+                    }
+                    (Op1Source::Op0, Some(Value::Scalar(_))) => {
+                        assert!(matches!(result, Err(Error::ExpectedPointer)));
+                        continue;
+                    }
+                    _ => result.unwrap(),
+                }
+                let op1 = ctx.flags.contains(StepContextFlags::OP1_ASSERTED);
+                assert_eq!(cell_at(&vm, ctx.op1_addr), op1.then_some(ctx.op1));
+            }
+        }
+    }
+}
+
+#[test]
+fn finalize_range_check() {
+    let builtins: Vec<Box<dyn Builtin>> = vec![Box::new(RangeCheckBuiltin::new())];
+    let mut vm = CairoVM::new(&[], builtins).unwrap();
+    let segment = CairoVM::FIRST_BUILTIN_SEGMENT;
+
+    let max = Felt::from(u128::MAX);
+    vm.assert_eq(ptr(segment, 0), ValueRef::Scalar(&max))
+        .unwrap();
+    vm.assert_eq(ptr(segment, 2), ValueRef::Scalar(&Felt::ZERO))
+        .unwrap();
+    vm.finalize().unwrap();
+
+    let out_of_range = max + Felt::ONE;
+    vm.assert_eq(ptr(segment, 3), ValueRef::Scalar(&out_of_range))
+        .unwrap();
+    assert!(matches!(vm.finalize(), Err(Error::Builtin)));
+}
+
+#[test]
+fn walk_list_three_nodes() {
+    let mut vm = vm_for(&[]);
+    let nodes = [ptr(1, 0), ptr(1, 4), ptr(1, 8)];
+    for (index, &node) in nodes.iter().enumerate() {
+        let value = Felt::from(index);
+        vm.assert_eq(node, ValueRef::Scalar(&value)).unwrap();
+        if let Some(next) = nodes.get(index + 1) {
+            vm.assert_eq(node.wrapping_add(1), ValueRef::Pointer(next))
+                .unwrap();
+        }
+    }
+    // The last node is terminated by a scalar.
+    vm.assert_eq(nodes[2].wrapping_add(1), ValueRef::Scalar(&Felt::ZERO))
+        .unwrap();
+
+    assert_eq!(vm.walk_list(nodes[0], 1, 10).unwrap(), nodes);
+    assert_eq!(vm.walk_list(nodes[0], 1, 2).unwrap(), nodes[..2]);
+    assert_eq!(vm.walk_list(nodes[1], 1, 10).unwrap(), nodes[1..]);
+    assert!(vm.walk_list(Pointer::SENTINEL, 1, 10).unwrap().is_empty());
+    assert!(matches!(
+        vm.walk_list(ptr(9, 0), 1, 10),
+        Err(Error::UnallocatedSegment)
+    ));
+}
+
+#[test]
+fn explicit_entry_registers() {
+    let mut memory = Memory::default();
+    let program = memory.allocate_segment();
+    let frame = memory.allocate_segment();
+    memory
+        .load_data(ptr(program, 0), &counting_program(1))
+        .unwrap();
+    memory
+        .assert_eq(ptr(frame, 0), ValueRef::Scalar(&Felt::from(5)))
+        .unwrap();
+
+    let entry = Cpu {
+        pc: ptr(program, 0),
+        ap: ptr(frame, 1),
+        fp: ptr(frame, 1),
+    };
+    let mut vm = CairoVM::from_parts(memory.clone(), entry.clone()).unwrap();
+    step(&mut vm).unwrap();
+
+    assert_eq!(vm.pc(), ptr(program, 2));
+    assert_eq!(vm.ap(), ptr(frame, 2));
+    assert_eq!(vm.fp(), ptr(frame, 1));
+    let six = Felt::from(6);
+    assert_eq!(vm.memory().get(ptr(frame, 1)), Some(ValueRef::Scalar(&six)));
+
+    for cpu in [
+        Cpu {
+            ap: ptr(2, 0),
+            ..entry.clone()
+        },
+        Cpu {
+            fp: ptr(2, 0),
+            ..entry
+        },
+    ] {
+        assert!(matches!(
+            CairoVM::from_parts(memory.clone(), cpu),
+            Err(Error::UnallocatedSegment)
+        ));
+    }
+}
+
+/// `jmp rel <imm>`
+const JMP_REL: InstructionBuilder = InstructionBuilder::new()
+    .op1_source(Op1Source::PC)
+    .op1_offset(1)
+    .pc_update(PcUpdate::RelativeJump);
+
+/// Runs `vm` until the end of its program, without hints.
+fn run(vm: &mut CairoVM, end: Pointer, options: &RunOptions) -> Result<(), Error> {
+    vm.run(end, options, &mut NoopHintProcessor, &mut NoopTrace)
+}
+
+#[test]
+fn self_jump_is_infinite_loop() {
+    let program = [word(JMP_REL), Value::Scalar(Felt::ZERO)];
+
+    let mut vm = vm_for(&program);
+    let options = RunOptions {
+        max_steps: Some(100),
+        loop_detection: Some(3),
+    };
+    assert!(matches!(
+        run(&mut vm, ptr(0, 2), &options),
+        Err(Error::InfiniteLoop)
+    ));
+    assert_eq!(vm.steps(), 3);
+
+    // Without detection, only the step limit stops the program.
+    let mut vm = vm_for(&program);
+    let options = RunOptions {
+        loop_detection: None,
+        ..options
+    };
+    assert!(matches!(
+        run(&mut vm, ptr(0, 2), &options),
+        Err(Error::StepLimitReached)
+    ));
+    assert_eq!(vm.steps(), 100);
+}
+
+#[test]
+fn disassemble_ahead_of_pc() {
+    let mut program = counting_program(2);
+    program.extend([word(JMP_REL), Value::Scalar(Felt::from(-4))]);
+    program.push(Value::Pointer(ptr(1, 0)));
+    let vm = vm_for(&program);
+
+    let lines = vm.disassemble_current(3);
+    let expected = [
+        (ptr(0, 0), "[ap] = [ap - 1] + [pc + 1]; ap++"),
+        (ptr(0, 2), "[ap] = [ap - 1] + [pc + 1]; ap++"),
+        (ptr(0, 4), "jmp 0:0"),
+    ];
+    assert_eq!(lines.len(), 3);
+    for ((pc, line), (expected_pc, expected_line)) in lines.iter().zip(expected) {
+        assert_eq!(*pc, expected_pc);
+        assert_eq!(line, expected_line);
+    }
+
+    // Disassembly stops at the pointer following the jump.
+    assert_eq!(vm.disassemble_current(10), lines);
+}
+
+#[test]
+fn fetch_pointer_or_unknown_cell() {
+    let mut vm = vm_for(&[Value::Pointer(ptr(1, 0))]);
+    assert!(matches!(step(&mut vm), Err(Error::InstructionIsPointer)));
+
+    // The cell following the program is unknown.
+    let mut vm = vm_for(&counting_program(1));
+    vm.set_cpu(Cpu {
+        pc: ptr(0, 2),
+        ..vm.cpu().clone()
+    })
+    .unwrap();
+    assert!(matches!(step(&mut vm), Err(Error::ProgramCounterLost)));
+    assert_eq!(vm.steps(), 0);
+}
+
+#[test]
+fn snapshot_and_restore() {
+    let mut vm = counting_vm(3);
+    step(&mut vm).unwrap();
+
+    let snapshot = vm.snapshot();
+    let cpu = vm.cpu().clone();
+    step(&mut vm).unwrap();
+    assert_ne!(vm.cpu(), &cpu);
+    assert!(vm.memory().get(ptr(1, 2)).is_some());
+
+    vm.restore(snapshot).unwrap();
+    assert_eq!(vm.cpu(), &cpu);
+    assert_eq!(vm.steps(), 1);
+    assert_eq!(vm.memory().get(ptr(1, 2)), None);
+
+    // The restored state executes the same way.
+    step(&mut vm).unwrap();
+    let two = Felt::TWO;
+    assert_eq!(vm.memory().get(ptr(1, 2)), Some(ValueRef::Scalar(&two)));
+}
+
+#[test]
+fn register_accessors() {
+    let mut vm = counting_vm(2);
+    step(&mut vm).unwrap();
+
+    assert_eq!(vm.pc(), ptr(0, 2));
+    assert_eq!(vm.ap(), ptr(1, 2));
+    assert_eq!(vm.fp(), ptr(1, 1));
+    assert_eq!(vm.pc(), vm.cpu().pc);
+    assert_eq!(vm.ap(), vm.cpu().ap);
+    assert_eq!(vm.fp(), vm.cpu().fp);
+}
+
+#[test]
+fn assertion_aborts_run() {
+    let mut vm = counting_vm(10);
+    let bound = ptr(1, 4);
+
+    let result = vm.run_with_assertions(
+        ptr(0, 20),
+        &RunOptions::default(),
+        &mut NoopHintProcessor,
+        &mut NoopTrace,
+        |vm| match vm.ap().partial_cmp(&bound) {
+            Some(core::cmp::Ordering::Greater) => Err(format!("ap went past {bound}")),
+            _ => Ok(()),
+        },
+    );
+
+    match result {
+        Err(Error::AssertionFailed(message)) => assert_eq!(message, "ap went past 1:4"),
+        _ => panic!("the assertion should have failed"),
+    }
+    // The step moving `ap` past the bound has been executed.
+    assert_eq!(vm.steps(), 4);
+    assert_eq!(vm.ap(), ptr(1, 5));
+}
+
+#[test]
+fn verify_recorded_steps() {
+    let mut vm = counting_vm(3);
+    let mut trace = RegisterTrace::new();
+    vm.run(
+        ptr(0, 6),
+        &RunOptions::default(),
+        &mut NoopHintProcessor,
+        &mut trace,
+    )
+    .unwrap();
+    let memory = vm.memory();
+
+    // Steps can be verified in any order.
+    let records: Vec<TraceRecord> = trace.records().collect();
+    assert_eq!(records.len(), 2);
+    for record in records.iter().rev() {
+        vm.verify_step(record, memory).unwrap();
+    }
+
+    let mut tampered = records[1].clone();
+    tampered.next.ap = tampered.next.ap.wrapping_add(1);
+    assert!(matches!(
+        vm.verify_step(&tampered, memory),
+        Err(Error::InvalidTraceRecord)
+    ));
+
+    // `[1:2] = [1:1] + 1` no longer holds.
+    let mut cells = Memory::default();
+    cells.push_segment(memory.segment(0).unwrap().clone());
+    let stack = [0, 1, 5, 3].map(|value| Value::Scalar(Felt::from(value)));
+    cells.push_segment(Segment::from_values(&stack));
+    assert!(matches!(
+        vm.verify_step(&records[1], &cells),
+        Err(Error::Contradiction)
+    ));
+}
+
+/// `ret`
+const RET: InstructionBuilder = InstructionBuilder::new()
+    .op_code(OpCode::Ret)
+    .dst_register(DstRegister::FP)
+    .dst_offset(-2)
+    .op0_register(Op0Register::FP)
+    .op0_offset(-1)
+    .op1_source(Op1Source::FP)
+    .op1_offset(-1)
+    .pc_update(PcUpdate::AbsoluteJump);
+
+/// Creates a virtual machine about to run `ret` with `fp` at `1:fp`, after writing the
+/// provided cells to the execution segment.
+fn ret_vm(fp: usize, cells: &[(usize, Value)]) -> CairoVM {
+    let mut vm = vm_for(&[word(RET)]);
+    for (offset, value) in cells {
+        vm.assert_eq(ptr(1, *offset), value.as_ref()).unwrap();
+    }
+    let mut cpu = vm.cpu().clone();
+    cpu.ap = ptr(1, fp);
+    cpu.fp = ptr(1, fp);
+    vm.set_cpu(cpu).unwrap();
+    vm
+}
+
+#[test]
+fn ret_validates_frame() {
+    let caller_fp = Value::Pointer(ptr(1, 0));
+    let return_pc = Value::Pointer(ptr(0, 0));
+
+    let mut vm = ret_vm(2, &[(0, caller_fp), (1, return_pc)]);
+    step(&mut vm).unwrap();
+    assert_eq!(vm.fp(), ptr(1, 0));
+    assert_eq!(vm.pc(), ptr(0, 0));
+
+    for mut vm in [
+        // `fp - 2` would underflow the segment.
+        ret_vm(0, &[]),
+        ret_vm(1, &[(0, return_pc)]),
+        // `[fp - 2]` or `[fp - 1]` is unknown.
+        ret_vm(2, &[(1, return_pc)]),
+        ret_vm(2, &[(0, caller_fp)]),
+        // The frame pointer of the caller is not a pointer.
+        ret_vm(2, &[(0, Value::Scalar(Felt::ONE)), (1, return_pc)]),
+    ] {
+        let cpu = vm.cpu().clone();
+        assert!(matches!(step(&mut vm), Err(Error::InvalidReturn)));
+        assert_eq!(vm.cpu(), &cpu);
+    }
+}
+
+#[test]
+fn disassemble_program_segment() {
+    let mut program = counting_program(1);
+    program.extend([
+        word(JMP_REL),
+        Value::Scalar(Felt::from(-2)),
+        Value::Scalar(Felt::TWO.pow(70u32)),
+        Value::Pointer(ptr(1, 0)),
+        Value::Scalar(Felt::from(u64::MAX >> 1)),
+    ]);
+    let mut vm = vm_for(&program);
+    // Leave a gap, followed by an instruction whose immediate is unknown.
+    vm.assert_eq(ptr(0, 8), word(INCREMENT).as_ref()).unwrap();
+
+    let lines = vm.disassemble(CairoVM::PROGRAM_SEGMENT).unwrap();
+    let expected = [
+        (0, "[ap] = [ap - 1] + [pc + 1]; ap++ // imm = 1".into()),
+        (2, format!("jmp 0:0 // imm = {}", Felt::from(-2))),
+        (4, format!("dw {}", Felt::TWO.pow(70u32))),
+        (5, "dw 1:0".into()),
+        (6, format!("dw {}", u64::MAX >> 1)),
+        (8, "[ap] = [ap - 1] + [pc + 1]; ap++ // imm = ?".into()),
+    ];
+    assert_eq!(lines, expected);
+
+    assert!(matches!(
+        vm.disassemble(vm.memory().num_segments()),
+        Err(Error::UnallocatedSegment)
+    ));
+}
+
+#[test]
+fn initial_and_final_registers() {
+    let mut vm = counting_vm(3);
+    let initial = Cpu {
+        pc: ptr(0, 0),
+        ap: ptr(1, 1),
+        fp: ptr(1, 1),
+    };
+    assert_eq!(vm.initial_registers(), &initial);
+    assert_eq!(vm.final_registers(), &initial);
+
+    let end = ptr(0, 6);
+    run(&mut vm, end, &RunOptions::default()).unwrap();
+    assert_eq!(vm.steps(), 3);
+    assert_eq!(vm.initial_registers(), &initial);
+    assert_eq!(
+        vm.final_registers(),
+        &Cpu {
+            pc: end,
+            ap: ptr(1, 4),
+            fp: ptr(1, 1),
+        }
+    );
+
+    // Moving the registers once the program has started leaves the initial state untouched.
+    vm.set_cpu(initial.clone()).unwrap();
+    assert_eq!(vm.initial_registers(), &initial);
+    assert_eq!(vm.final_registers(), &initial);
+}
+
+/// `jmp abs [ap]`
+const JMP_ABS: InstructionBuilder = InstructionBuilder::new()
+    .op1_source(Op1Source::AP)
+    .pc_update(PcUpdate::AbsoluteJump);
+
+#[test]
+fn absolute_jump_targets() {
+    let jump_to = |target: Value| {
+        let mut vm = vm_for(&[word(JMP_ABS)]);
+        let ap = vm.ap();
+        vm.assert_eq(ap, target.as_ref()).unwrap();
+        let cpu = vm.cpu().clone();
+        (step(&mut vm).map(|_| ()), vm, cpu)
+    };
+
+    let (result, vm, _) = jump_to(Value::Pointer(ptr(1, 5)));
+    assert!(result.is_ok());
+    assert_eq!(vm.pc(), ptr(1, 5));
+
+    // Both fail at jump time, leaving the registers untouched.
+    let (result, vm, cpu) = jump_to(Value::Scalar(Felt::from(5)));
+    assert!(matches!(result, Err(Error::InvalidAbsoluteJump)));
+    assert_eq!(vm.cpu(), &cpu);
+    assert_eq!(vm.steps(), 0);
+
+    let (result, vm, cpu) = jump_to(Value::Pointer(ptr(2, 0)));
+    assert_eq!(vm.memory().num_segments(), 2);
+    assert!(matches!(result, Err(Error::ProgramCounterLost)));
+    assert_eq!(vm.cpu(), &cpu);
+    assert_eq!(vm.steps(), 0);
+}
+
+#[test]
+fn accessed_cells() {
+    let mut vm = counting_vm(3);
+    // A dead write, never read by the program.
+    vm.assert_eq(ptr(1, 10), ValueRef::Scalar(&Felt::TWO))
+        .unwrap();
+
+    step(&mut vm).unwrap();
+    step(&mut vm).unwrap();
+
+    let program = vm.memory().segment(0).unwrap();
+    assert!((0..4).all(|offset| program.is_accessed(offset)));
+    let unaccessed: Vec<usize> = program
+        .unaccessed_known_cells()
+        .map(|(offset, _)| offset)
+        .collect();
+    assert_eq!(unaccessed, [4, 5]);
+
+    // The operands of each step are accessed, whether they were read or deduced.
+    let execution = vm.memory().segment(1).unwrap();
+    assert!((0..3).all(|offset| execution.is_accessed(offset)));
+    assert!(!execution.is_accessed(3));
+    assert!(!execution.is_accessed(100));
+    let unaccessed: Vec<(usize, ValueRef)> = execution.unaccessed_known_cells().collect();
+    assert_eq!(unaccessed, [(10, ValueRef::Scalar(&Felt::TWO))]);
+}
+
+/// `call rel [pc + 1]`
+const CALL_REL: InstructionBuilder = InstructionBuilder::new()
+    .op_code(OpCode::Call)
+    .op0_offset(1)
+    .op1_source(Op1Source::PC)
+    .op1_offset(1)
+    .pc_update(PcUpdate::RelativeJump);
+
+/// `ap += [pc + 1]`
+const AP_ADD: InstructionBuilder = InstructionBuilder::new()
+    .dst_offset(-1)
+    .op0_offset(-1)
+    .op1_source(Op1Source::PC)
+    .op1_offset(1)
+    .ap_update(ApUpdate::AddResult);
+
+#[test]
+fn ap_updates() {
+    // A call pushes two cells, without any explicit update.
+    let mut vm = vm_for(&[word(CALL_REL), Value::Scalar(Felt::from(2))]);
+    let ap = vm.ap();
+    step(&mut vm).unwrap();
+    assert_eq!(vm.ap(), ap.wrapping_add(2));
+
+    let mut vm = vm_for(&[
+        word(CALL_REL.ap_update(ApUpdate::Increment)),
+        Value::Scalar(Felt::from(2)),
+    ]);
+    let cpu = vm.cpu().clone();
+    assert!(matches!(step(&mut vm), Err(Error::UndefinedApUpdateInCall)));
+    assert_eq!(vm.cpu(), &cpu);
+
+    // Other instructions add their result, or one.
+    for (delta, expected) in [(4, 9), (-3, 2)] {
+        let mut vm = vm_for(&[word(AP_ADD), Value::Scalar(Felt::from(delta))]);
+        let cpu = Cpu {
+            ap: ptr(1, 5),
+            ..vm.cpu().clone()
+        };
+        vm.set_cpu(cpu).unwrap();
+        step(&mut vm).unwrap();
+        assert_eq!(vm.ap(), ptr(1, expected));
+        assert_eq!(vm.pc(), ptr(0, 2));
+    }
+
+    let mut vm = counting_vm(1);
+    step(&mut vm).unwrap();
+    assert_eq!(vm.ap(), ptr(1, 2));
+
+    // `ap++` does not wrap around at the end of the address space.
+    let mut vm = vm_for(&[
+        word(JMP_REL.ap_update(ApUpdate::Increment)),
+        Value::Scalar(Felt::ZERO),
+    ]);
+    let cpu = Cpu {
+        ap: ptr(1, usize::MAX),
+        ..vm.cpu().clone()
+    };
+    vm.set_cpu(cpu.clone()).unwrap();
+    assert!(matches!(step(&mut vm), Err(Error::PointerTooLarge)));
+    assert_eq!(vm.cpu(), &cpu);
+}
+
+#[test]
+fn call_saves_frame() {
+    let call_abs = CALL_REL
+        .op1_source(Op1Source::AP)
+        .op1_offset(2)
+        .pc_update(PcUpdate::AbsoluteJump);
+
+    // The absolute call has no immediate, and returns right after its single cell.
+    for (call, immediate, return_pc) in [
+        (word(CALL_REL), Felt::from(3), ptr(0, 2)),
+        (word(call_abs), Felt::ZERO, ptr(0, 1)),
+    ] {
+        let filler = Value::Scalar(Felt::ZERO);
+        let mut vm = vm_for(&[call, Value::Scalar(immediate), filler, word(RET)]);
+        let caller = Cpu {
+            ap: ptr(1, 4),
+            fp: ptr(1, 1),
+            ..vm.cpu().clone()
+        };
+        vm.set_cpu(caller.clone()).unwrap();
+        // Only read by the absolute call.
+        vm.assert_eq(ptr(1, 6), ValueRef::Pointer(&ptr(0, 3)))
+            .unwrap();
+
+        step(&mut vm).unwrap();
+        assert_eq!(
+            vm.cpu(),
+            &Cpu {
+                pc: ptr(0, 3),
+                ap: ptr(1, 6),
+                fp: ptr(1, 6),
+            }
+        );
+        assert_eq!(
+            vm.memory().get(ptr(1, 4)),
+            Some(ValueRef::Pointer(&caller.fp))
+        );
+        assert_eq!(
+            vm.memory().get(ptr(1, 5)),
+            Some(ValueRef::Pointer(&return_pc))
+        );
+
+        // Returning restores the frame of the caller.
+        step(&mut vm).unwrap();
+        assert_eq!(vm.pc(), return_pc);
+        assert_eq!(vm.fp(), caller.fp);
+    }
+
+    // The saved frame must agree with the cells already on the stack.
+    let mut vm = vm_for(&[word(CALL_REL), Value::Scalar(Felt::from(3))]);
+    let ap = vm.ap();
+    vm.assert_eq(ap, ValueRef::Pointer(&ptr(1, 7))).unwrap();
+    assert!(matches!(step(&mut vm), Err(Error::Contradiction)));
+}
+
+#[test]
+fn builtin_count_mismatch() {
+    let reserving = |count: usize| -> Vec<Box<dyn Builtin>> {
+        (0..count)
+            .map(|_| Box::new(Reserving(0)) as Box<dyn Builtin>)
+            .collect()
+    };
+
+    let vm = CairoVM::new(&counting_program(1), reserving(1)).unwrap();
+    assert_eq!(vm.memory().num_segments(), 3);
+
+    // More builtins than segments allocated for them.
+    assert!(matches!(
+        CairoVM::resume(vm.snapshot(), reserving(2)),
+        Err(Error::UnallocatedSegment)
+    ));
+    assert!(CairoVM::resume(vm.snapshot(), reserving(1)).is_ok());
+
+    let memory = vm.memory();
+    assert!(matches!(
+        crate::BuiltinManager::new(CairoVM::FIRST_BUILTIN_SEGMENT, reserving(2), memory),
+        Err(Error::UnallocatedSegment)
+    ));
+    assert!(matches!(
+        crate::BuiltinManager::new(usize::MAX, reserving(1), memory),
+        Err(Error::UnallocatedSegment)
+    ));
+
+    let manager =
+        crate::BuiltinManager::new(CairoVM::FIRST_BUILTIN_SEGMENT, reserving(1), memory).unwrap();
+    assert!(manager.get_runner(CairoVM::FIRST_BUILTIN_SEGMENT).is_some());
+    assert!(manager
+        .get_runner(CairoVM::FIRST_BUILTIN_SEGMENT + 1)
+        .is_none());
+}
+
+#[test]
+fn builtin_segment_reserved_capacity() {
+    let builtins: Vec<Box<dyn Builtin>> = vec![Box::new(Reserving(100))];
+    let mut vm = CairoVM::new(&[], builtins).unwrap();
+    let segment = CairoVM::FIRST_BUILTIN_SEGMENT;
+
+    let reserved = vm.memory().segment(segment).unwrap();
+    let capacity = reserved.capacity();
+    assert!(capacity >= 100);
+    assert_eq!(reserved.size(), 0);
+
+    // Filling the reserved cells does not reallocate the segment.
+    for offset in 0..100 {
+        let value = Felt::from(offset);
+        vm.assert_eq(ptr(segment, offset), ValueRef::Scalar(&value))
+            .unwrap();
+    }
+    let filled = vm.memory().segment(segment).unwrap();
+    assert_eq!(filled.size(), 100);
+    assert_eq!(filled.capacity(), capacity);
+
+    // The capacity is reserved again when resuming from a snapshot.
+    let builtins: Vec<Box<dyn Builtin>> = vec![Box::new(Reserving(200))];
+    let resumed = CairoVM::resume(vm.snapshot(), builtins).unwrap();
+    let segment = resumed.memory().segment(segment).unwrap();
+    assert!(segment.capacity() >= 200);
+    assert_eq!(segment.size(), 100);
+}
+
+#[test]
+fn undo_rewinds_registers() {
+    let mut vm = counting_vm(2);
+    let initial = vm.cpu().clone();
+    let mut log = UndoLog::new();
+
+    vm.step(&mut NoopHintProcessor, &mut log).unwrap();
+    let middle = vm.cpu().clone();
+    vm.step(&mut NoopHintProcessor, &mut log).unwrap();
+    let last = vm.cpu().clone();
+    assert_eq!(log.len(), 2);
+
+    // The cell following the program is unknown: the failed step is not recorded.
+    assert!(vm.step(&mut NoopHintProcessor, &mut log).is_err());
+    assert_eq!(log.len(), 2);
+
+    vm.undo(&mut log).unwrap();
+    assert_eq!(vm.cpu(), &middle);
+    assert_eq!(vm.steps(), 1);
+    assert_eq!(log.stale_cells(), [ptr(1, 2)]);
+
+    vm.undo(&mut log).unwrap();
+    assert_eq!(vm.cpu(), &initial);
+    assert_eq!(vm.steps(), 0);
+    assert_eq!(log.stale_cells(), [ptr(1, 2), ptr(1, 1)]);
+    assert!(matches!(vm.undo(&mut log), Err(Error::NothingToUndo)));
+
+    // The stale cells keep their value, and executing the steps again is consistent.
+    assert_eq!(
+        vm.memory().get(ptr(1, 2)),
+        Some(ValueRef::Scalar(&Felt::TWO))
+    );
+    log.clear_stale_cells();
+    step(&mut vm).unwrap();
+    step(&mut vm).unwrap();
+    assert_eq!(vm.cpu(), &last);
+    assert!(log.stale_cells().is_empty());
+}
+
+/// A [`GasMeter`] charging three units per instruction.
+struct Expensive(GasMeter);
+
+impl ResourceTracker for Expensive {
+    fn instruction_cost(&self) -> u64 {
+        3
+    }
+
+    fn consume(&mut self, step_cost: u64) -> Result<(), Error> {
+        self.0.consume(step_cost)
+    }
+}
+
+#[test]
+fn loop_exhausts_gas() {
+    let program = [word(JMP_REL), Value::Scalar(Felt::ZERO)];
+    let options = RunOptions {
+        max_steps: None,
+        loop_detection: None,
+    };
+    let run_metered = |resources: &mut dyn ResourceTracker| {
+        let mut vm = vm_for(&program);
+        let end = ptr(0, 2);
+        let result = vm.run_metered(
+            end,
+            &options,
+            &mut NoopHintProcessor,
+            &mut NoopTrace,
+            resources,
+        );
+        assert!(matches!(result, Err(Error::OutOfGas)));
+        vm.steps()
+    };
+
+    // The step running out of gas is executed before being charged.
+    let mut gas = GasMeter::new(10);
+    assert_eq!(run_metered(&mut gas), 11);
+    assert_eq!(gas.consumed(), 10);
+    assert_eq!(gas.remaining(), 0);
+
+    let mut gas = Expensive(GasMeter::new(10));
+    assert_eq!(run_metered(&mut gas), 4);
+    assert_eq!(gas.0.consumed(), 9);
+}
+
+/// `[ap] = [pc + 1]; ap++`, with `op0` read from `[fp - 1]`.
+const PUSH: InstructionBuilder = InstructionBuilder::new()
+    .op_code(OpCode::AssertEq)
+    .op0_register(Op0Register::FP)
+    .op0_offset(-1)
+    .op1_source(Op1Source::PC)
+    .op1_offset(1)
+    .ap_update(ApUpdate::Increment);
+
+#[test]
+fn output_three_values() {
+    let outputs = [Felt::from(10), Felt::from(20), Felt::from(30)];
+    let program: Vec<Value> = outputs
+        .iter()
+        .flat_map(|&output| [word(PUSH), Value::Scalar(output)])
+        .collect();
+    let builtins: Vec<Box<dyn Builtin>> = vec![Box::new(OutputBuiltin)];
+    let mut vm = CairoVM::new(&program, builtins).unwrap();
+
+    // Push the outputs directly to the segment of the builtin.
+    let output = ptr(CairoVM::FIRST_BUILTIN_SEGMENT, 0);
+    vm.assert_eq(ptr(1, 0), ValueRef::Scalar(&Felt::ZERO))
+        .unwrap();
+    vm.set_cpu(Cpu {
+        pc: ptr(0, 0),
+        ap: output,
+        fp: ptr(1, 1),
+    })
+    .unwrap();
+    run(&mut vm, ptr(0, 6), &RunOptions::default()).unwrap();
+    assert_eq!(vm.output().unwrap(), outputs);
+
+    // Gaps are rejected, and so are pointers once the gap is filled.
+    vm.assert_eq(output.wrapping_add(4), ValueRef::Scalar(&Felt::ONE))
+        .unwrap();
+    assert!(matches!(vm.output(), Err(Error::Builtin)));
+    vm.assert_eq(output.wrapping_add(3), ValueRef::Pointer(&output))
+        .unwrap();
+    assert!(matches!(vm.output(), Err(Error::Builtin)));
+
+    assert!(matches!(
+        vm_for(&program).output(),
+        Err(Error::MissingBuiltin)
+    ));
+}
+
+/// `[ap] = [[fp - 1] + 1]; ap++`
+const DOUBLE_DEREF: InstructionBuilder = InstructionBuilder::new()
+    .op_code(OpCode::AssertEq)
+    .op0_register(Op0Register::FP)
+    .op0_offset(-1)
+    .op1_source(Op1Source::Op0)
+    .op1_offset(1)
+    .ap_update(ApUpdate::Increment);
+
+#[test]
+fn double_indirect_op1() {
+    // Returns a virtual machine whose `[fp - 1]` holds `op0`, and `[ap]` holds `dst`.
+    let vm_with = |op0: Value, dst: Option<Value>| {
+        let mut vm = vm_for(&[word(DOUBLE_DEREF)]);
+        let data = vm.allocate_segment();
+        assert_eq!(data, 2);
+        vm.assert_eq(ptr(1, 0), op0.as_ref()).unwrap();
+        if let Some(dst) = dst {
+            vm.assert_eq(ptr(1, 1), dst.as_ref()).unwrap();
+        }
+        vm.set_cpu(Cpu {
+            pc: ptr(0, 0),
+            ap: ptr(1, 1),
+            fp: ptr(1, 1),
+        })
+        .unwrap();
+        vm
+    };
+
+    // `op1` is read through the pointer held by `op0`, not next to `op0` itself.
+    let mut vm = vm_with(Value::Pointer(ptr(2, 0)), None);
+    vm.assert_eq(ptr(2, 1), ValueRef::Scalar(&Felt::from(42)))
+        .unwrap();
+    let outcome = step(&mut vm).unwrap();
+    assert_eq!(outcome.op1.address, ptr(2, 1));
+    assert_eq!(
+        vm.memory().get(ptr(1, 1)),
+        Some(ValueRef::Scalar(&Felt::from(42)))
+    );
+
+    // The target cell can be deduced from `dst` as well.
+    let mut vm = vm_with(
+        Value::Pointer(ptr(2, 0)),
+        Some(Value::Scalar(Felt::from(7))),
+    );
+    step(&mut vm).unwrap();
+    assert_eq!(
+        vm.memory().get(ptr(2, 1)),
+        Some(ValueRef::Scalar(&Felt::from(7)))
+    );
+
+    let mut vm = vm_with(Value::Scalar(Felt::from(3)), None);
+    let cpu = vm.cpu().clone();
+    assert!(matches!(step(&mut vm), Err(Error::ExpectedPointer)));
+    assert_eq!(vm.cpu(), &cpu);
+}
+
+#[test]
+fn pointer_immediate_is_invalid() {
+    let mut vm = vm_for(&[word(INCREMENT), Value::Pointer(ptr(0, 0))]);
+    vm.assert_eq(ptr(1, 0), ValueRef::Scalar(&Felt::ONE))
+        .unwrap();
+    vm.set_cpu(Cpu {
+        pc: ptr(0, 0),
+        ap: ptr(1, 1),
+        fp: ptr(1, 1),
+    })
+    .unwrap();
+
+    let cpu = vm.cpu().clone();
+    assert!(matches!(step(&mut vm), Err(Error::InvalidImmediate)));
+    assert_eq!(vm.cpu(), &cpu);
+    assert_eq!(vm.memory().get(ptr(1, 1)), None);
+
+    // The same instruction runs fine with a scalar immediate.
+    let mut vm = counting_vm(1);
+    step(&mut vm).unwrap();
+    assert_eq!(
+        vm.memory().get(ptr(1, 1)),
+        Some(ValueRef::Scalar(&Felt::ONE))
+    );
+}
+
+#[test]
+fn alloc_segment_pointers_are_distinct() {
+    let mut vm = vm_for(&[]);
+    let segments = vm.memory().num_segments();
+
+    let first = vm.alloc_segment_pointer();
+    let second = vm.alloc_segment_pointer();
+    assert_ne!(first.segment, second.segment);
+    assert_eq!(first, ptr(segments, 0));
+    assert_eq!(second, ptr(segments + 1, 0));
+    assert_eq!(vm.memory().num_segments(), segments + 2);
+
+    // The base of a new segment can be written to memory, and written through.
+    let ap = vm.ap();
+    vm.assert_eq(ap, ValueRef::Pointer(&first)).unwrap();
+    vm.assert_eq(second.wrapping_add(2), ValueRef::Scalar(&Felt::ONE))
+        .unwrap();
+    assert_eq!(vm.memory().get(ap), Some(ValueRef::Pointer(&first)));
+}
+
+#[test]
+fn call_frame_overflow() {
+    for offset in [usize::MAX - 1, usize::MAX] {
+        let mut vm = vm_for(&[word(CALL_REL), Value::Scalar(Felt::ZERO)]);
+        let cpu = Cpu {
+            ap: ptr(1, offset),
+            fp: ptr(1, offset),
+            ..vm.cpu().clone()
+        };
+        vm.set_cpu(cpu.clone()).unwrap();
+
+        assert!(matches!(step(&mut vm), Err(Error::PointerTooLarge)));
+        assert_eq!(vm.cpu(), &cpu);
+        assert_eq!(vm.steps(), 0);
+    }
+}
+
+/// A verifier accepting the signatures whose `r` is the message and `s` the public key.
+struct Echo;
+
+impl SignatureVerifier for Echo {
+    fn verify(&self, public_key: &Felt, message: &Felt, signature: &Signature) -> bool {
+        signature.r == *message && signature.s == *public_key
+    }
+}
+
+/// A hint processor signing the first instance of the `ecdsa` builtin before the instruction
+/// at `target`.
+struct SigningHint {
+    target: Pointer,
+}
+
+impl HintProcessor for SigningHint {
+    fn execute(&mut self, vm: &mut CairoVM, pc: Pointer) -> Result<(), Error> {
+        if pc == self.target {
+            let ecdsa = vm
+                .builtin_mut::<Ecdsa<Echo>>()
+                .ok_or(Error::MissingBuiltin)?;
+            ecdsa.add_signature(0, Felt::from(20), Felt::from(10));
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn hint_adds_signature() {
+    // Push an instance of the builtin, made of a public key and a message.
+    let program = [
+        word(PUSH),
+        Value::Scalar(Felt::from(10)),
+        word(PUSH),
+        Value::Scalar(Felt::from(20)),
+    ];
+    let signed_vm = || {
+        let builtins: Vec<Box<dyn Builtin>> = vec![Box::new(Ecdsa::new(Echo))];
+        let mut vm = CairoVM::new(&program, builtins).unwrap();
+        vm.assert_eq(ptr(1, 0), ValueRef::Scalar(&Felt::ZERO))
+            .unwrap();
+        vm.set_cpu(Cpu {
+            pc: ptr(0, 0),
+            ap: ptr(CairoVM::FIRST_BUILTIN_SEGMENT, 0),
+            fp: ptr(1, 1),
+        })
+        .unwrap();
+        vm
+    };
+
+    let mut vm = signed_vm();
+    assert!(vm.builtin::<RangeCheckBuiltin>().is_none());
+    assert!(vm.builtin::<Ecdsa<Echo>>().unwrap().signature(0).is_none());
+    vm.run(
+        ptr(0, 4),
+        &RunOptions::default(),
+        &mut SigningHint { target: ptr(0, 2) },
+        &mut NoopTrace,
+    )
+    .unwrap();
+    assert_eq!(
+        vm.builtin::<Ecdsa<Echo>>().unwrap().signature(0),
+        Some(&Signature {
+            r: Felt::from(20),
+            s: Felt::from(10),
+        })
+    );
+    vm.finalize().unwrap();
+
+    // Without the hint, the instance is left unsigned.
+    let mut vm = signed_vm();
+    run(&mut vm, ptr(0, 4), &RunOptions::default()).unwrap();
+    assert!(matches!(vm.finalize(), Err(Error::Builtin)));
+}
@@ -0,0 +1,169 @@
+//! Defines the [`CfgTrace`] type.
+
+use alloc::vec::Vec;
+use std::collections::HashMap;
+
+use crate::instr::{Instruction, OpCode, PcUpdate};
+use crate::memory::Pointer;
+
+use super::Trace;
+
+/// The way control flowed from one basic block to another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EdgeKind {
+    /// A conditional jump was not taken, and execution continued with the next instruction.
+    FallThrough,
+    /// A jump (absolute, relative, or a taken conditional jump) was performed.
+    Jump,
+    /// A subroutine was called.
+    Call,
+    /// A subroutine returned to its caller.
+    Return,
+}
+
+/// An edge of the control-flow graph, leading to the basic block starting at `target`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CfgEdge {
+    /// The address of the first instruction of the basic block the edge leads to.
+    pub target: Pointer,
+    /// The way control flowed to the target block.
+    pub kind: EdgeKind,
+}
+
+/// An implementation of [`Trace`] that recovers the control-flow graph of the executed program.
+///
+/// Basic blocks are delimited by control-flow instructions (jumps, calls and returns) and are
+/// identified by the address of their first instruction. Only the edges that were actually
+/// taken during execution are recorded, meaning that both successors of a branch only appear
+/// once both paths have been exercised.
+#[derive(Debug, Default, Clone)]
+pub struct CfgTrace {
+    /// The successors of each basic block, keyed by the address of its first instruction.
+    edges: HashMap<Pointer, Vec<CfgEdge>>,
+    /// The address of the first instruction of the basic block being executed.
+    block: Option<Pointer>,
+    /// The last decoded instruction, along with its address.
+    last: Option<(Pointer, Instruction)>,
+}
+
+impl CfgTrace {
+    /// Creates a new empty [`CfgTrace`].
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the adjacency lists of the recovered control-flow graph, keyed by the address of
+    /// the first instruction of each basic block.
+    #[inline(always)]
+    pub fn edges(&self) -> &HashMap<Pointer, Vec<CfgEdge>> {
+        &self.edges
+    }
+
+    /// Returns the observed successors of the basic block starting at `block`.
+    #[inline]
+    pub fn successors(&self, block: Pointer) -> &[CfgEdge] {
+        self.edges.get(&block).map_or(&[], Vec::as_slice)
+    }
+
+    /// Records an edge between two basic blocks, unless it was already known.
+    fn add_edge(&mut self, from: Pointer, edge: CfgEdge) {
+        let successors = self.edges.entry(from).or_default();
+        if !successors.contains(&edge) {
+            successors.push(edge);
+        }
+    }
+}
+
+/// Returns how the provided instruction transfers control, or `None` if it simply falls
+/// through to the next instruction.
+fn classify(instruction: Instruction) -> Option<EdgeKind> {
+    match instruction.op_code() {
+        Ok(OpCode::Call) => return Some(EdgeKind::Call),
+        Ok(OpCode::Ret) => return Some(EdgeKind::Return),
+        _ => (),
+    }
+
+    match instruction.pc_update() {
+        Ok(PcUpdate::Regular) | Err(_) => None,
+        Ok(_) => Some(EdgeKind::Jump),
+    }
+}
+
+impl Trace for CfgTrace {
+    fn on_decode(&mut self, pc: Pointer, instruction: Instruction) {
+        let block = *self.block.get_or_insert(pc);
+
+        if let Some((last_pc, last)) = self.last {
+            if let Some(mut kind) = classify(last) {
+                // A conditional jump that landed on the next instruction was not taken. When
+                // its offset is the size of the instruction, both successors are the same.
+                let conditional = matches!(last.pc_update(), Ok(PcUpdate::ConditionalJump));
+                let next = last.size().ok().map(|size| last_pc.wrapping_add(size));
+                if conditional && next == Some(pc) {
+                    kind = EdgeKind::FallThrough;
+                }
+
+                self.add_edge(block, CfgEdge { target: pc, kind });
+                self.block = Some(pc);
+            }
+        }
+
+        self.last = Some((pc, instruction));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instr::{InstructionBuilder, Op1Source};
+
+    /// Returns a pointer to `offset` in the program segment.
+    fn pc(offset: usize) -> Pointer {
+        Pointer { segment: 0, offset }
+    }
+
+    /// `jmp rel <imm> if [ap] != 0`
+    const JNZ: InstructionBuilder = InstructionBuilder::new()
+        .op1_source(Op1Source::PC)
+        .op1_offset(1)
+        .pc_update(PcUpdate::ConditionalJump);
+
+    /// `jmp rel <imm>`
+    const JMP: InstructionBuilder = InstructionBuilder::new()
+        .op1_source(Op1Source::PC)
+        .op1_offset(1)
+        .pc_update(PcUpdate::RelativeJump);
+
+    #[test]
+    fn branch_successors() {
+        // 0: jmp rel 4 if [ap] != 0
+        // 2: jmp rel 2
+        // 4: jmp rel -4
+        let mut trace = CfgTrace::new();
+
+        // The branch is not taken, then taken on the next iteration.
+        for offset in [0, 2, 4, 0, 4] {
+            let instruction = match offset {
+                0 => JNZ,
+                _ => JMP,
+            };
+            trace.on_decode(pc(offset), instruction.build());
+        }
+        trace.on_decode(pc(0), JNZ.build());
+
+        let jump = |offset| CfgEdge {
+            target: pc(offset),
+            kind: EdgeKind::Jump,
+        };
+        let fall_through = CfgEdge {
+            target: pc(2),
+            kind: EdgeKind::FallThrough,
+        };
+
+        assert_eq!(trace.successors(pc(0)), [fall_through, jump(4)]);
+        // An unconditional jump to the next instruction is still a jump.
+        assert_eq!(trace.successors(pc(2)), [jump(4)]);
+        assert_eq!(trace.successors(pc(4)), [jump(0)]);
+    }
+}
@@ -0,0 +1,172 @@
+//! Defines the [`JsonTrace`] type.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use std::io::{self, Write};
+
+use serde::Serialize;
+
+use crate::cpu::Cpu;
+use crate::error::Error;
+
+use super::Trace;
+
+/// A single line written by a [`JsonTrace`].
+#[derive(Serialize)]
+struct Record {
+    /// The **Program Counter**, rendered as `segment:offset`.
+    pc: String,
+    /// The **Allocation Pointer**, rendered as `segment:offset`.
+    ap: String,
+    /// The **Frame Pointer**, rendered as `segment:offset`.
+    fp: String,
+    /// The error that caused the step to fail, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl Record {
+    /// Creates a new [`Record`] from the state of the registers.
+    fn new(cpu: &Cpu, error: Option<&Error>) -> Self {
+        Self {
+            pc: cpu.pc.to_string(),
+            ap: cpu.ap.to_string(),
+            fp: cpu.fp.to_string(),
+            error: error.map(|err| format!("{err:?}")),
+        }
+    }
+}
+
+/// An implementation of [`Trace`] that writes one JSON object per line (NDJSON) to a writer.
+///
+/// A record is written at the beginning of each step, holding the state of the registers:
+///
+/// ```json
+/// {"pc":"0:5","ap":"1:10","fp":"1:3"}
+/// ```
+///
+/// When a step fails, an additional record is written, with an `error` field describing the
+/// error.
+///
+/// Because the callbacks of [`Trace`] cannot fail, the first error produced by the writer is
+/// stored and returned by [`into_inner`](Self::into_inner). No record is written after it.
+#[derive(Debug)]
+pub struct JsonTrace<W> {
+    /// The writer the records are written to.
+    writer: W,
+    /// The first error produced by the writer, if any.
+    error: Option<io::Error>,
+}
+
+impl<W: Write> JsonTrace<W> {
+    /// Creates a new [`JsonTrace`] writing to the provided writer.
+    #[inline(always)]
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            error: None,
+        }
+    }
+
+    /// Returns the underlying writer.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with the first error produced by the writer, if any.
+    pub fn into_inner(self) -> io::Result<W> {
+        match self.error {
+            Some(err) => Err(err),
+            None => Ok(self.writer),
+        }
+    }
+
+    /// Writes a record to the underlying writer, unless a previous write failed.
+    fn write_record(&mut self, record: &Record) {
+        if self.error.is_some() {
+            return;
+        }
+
+        let result = serde_json::to_writer(&mut self.writer, record)
+            .map_err(io::Error::from)
+            .and_then(|()| self.writer.write_all(b"\n"));
+
+        if let Err(err) = result {
+            self.error = Some(err);
+        }
+    }
+}
+
+impl<W: Write> Trace for JsonTrace<W> {
+    fn on_step(&mut self, cpu: &Cpu) {
+        self.write_record(&Record::new(cpu, None));
+    }
+
+    fn on_error(&mut self, cpu: &Cpu, error: &Error) {
+        self.write_record(&Record::new(cpu, Some(error)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use starknet_types_core::felt::Felt;
+
+    use super::*;
+    use crate::hint::NoopHintProcessor;
+    use crate::instr::{ApUpdate, InstructionBuilder, Op1Source, OpCode, ResultLogic};
+    use crate::memory::{Value, ValueRef};
+    use crate::CairoVM;
+
+    #[test]
+    fn ndjson_records() {
+        // [ap] = [ap - 1] + 42; ap++
+        let instruction = InstructionBuilder::new()
+            .op_code(OpCode::AssertEq)
+            .op0_offset(-1)
+            .result_logic(ResultLogic::Add)
+            .op1_source(Op1Source::PC)
+            .op1_offset(1)
+            .ap_update(ApUpdate::Increment)
+            .build();
+        let program = [
+            Value::Scalar(Felt::from(instruction.0)),
+            Value::Scalar(Felt::from(42)),
+        ];
+        let mut vm = CairoVM::new(&program, Vec::new()).unwrap();
+        let base = vm.ap();
+        vm.assert_eq(base, ValueRef::Scalar(&Felt::ZERO)).unwrap();
+        vm.set_cpu(Cpu {
+            pc: vm.pc(),
+            ap: base.wrapping_add(1),
+            fp: base.wrapping_add(1),
+        })
+        .unwrap();
+
+        let mut trace = JsonTrace::new(Vec::new());
+        vm.step(&mut NoopHintProcessor, &mut trace).unwrap();
+        // The cell following the program is unknown.
+        assert!(vm.step(&mut NoopHintProcessor, &mut trace).is_err());
+
+        let output = trace.into_inner().unwrap();
+        let records: Vec<serde_json::Value> = output
+            .split(|&byte| byte == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect();
+
+        assert_eq!(
+            records,
+            [
+                serde_json::json!({ "pc": "0:0", "ap": "1:1", "fp": "1:1" }),
+                serde_json::json!({ "pc": "0:2", "ap": "1:2", "fp": "1:1" }),
+                serde_json::json!({
+                    "pc": "0:2",
+                    "ap": "1:2",
+                    "fp": "1:1",
+                    "error": "ProgramCounterLost",
+                }),
+            ]
+        );
+    }
+}
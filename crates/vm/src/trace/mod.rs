@@ -0,0 +1,138 @@
+//! Defines the [`Trace`] trait, used to gather information about the execution of a Cairo
+//! program within the virtual machine.
+//!
+//! # Example
+//!
+//! A trace keeping track of the depth of the call stack over execution only needs to
+//! implement the control-flow callbacks:
+//!
+//! ```
+//! use rayquaza_vm::memory::Pointer;
+//! use rayquaza_vm::trace::Trace;
+//!
+//! #[derive(Default)]
+//! struct CallDepth {
+//!     depth: usize,
+//!     max_depth: usize,
+//! }
+//!
+//! impl Trace for CallDepth {
+//!     fn on_call(&mut self, _target: Pointer, _return_to: Pointer) {
+//!         self.depth += 1;
+//!         self.max_depth = self.max_depth.max(self.depth);
+//!     }
+//!
+//!     fn on_ret(&mut self, _to: Pointer) {
+//!         self.depth = self.depth.saturating_sub(1);
+//!     }
+//! }
+//! ```
+//!
+//! For quick debugging, [`PrintTrace`] writes the registers and the disassembly of each
+//! executed instruction to any [`Write`](std::io::Write) implementation (requires the `std`
+//! feature):
+//!
+//! ```
+//! # #[cfg(feature = "std")]
+//! # {
+//! use rayquaza_vm::hint::NoopHintProcessor;
+//! use rayquaza_vm::instr::*;
+//! use rayquaza_vm::memory::{Value, ValueRef};
+//! use rayquaza_vm::trace::PrintTrace;
+//! use rayquaza_vm::CairoVM;
+//! use starknet_types_core::felt::Felt;
+//!
+//! // [ap + 1] = [ap + 0] + 2; ap++
+//! let instruction = DecodedInstruction {
+//!     dst_offset: 1,
+//!     op0_offset: 0,
+//!     op1_offset: 1,
+//!     dst_register: DstRegister::AP,
+//!     op0_register: Op0Register::AP,
+//!     op1_source: Op1Source::PC,
+//!     result_logic: ResultLogic::Add,
+//!     pc_update: PcUpdate::Regular,
+//!     ap_update: ApUpdate::Increment,
+//!     op_code: OpCode::AssertEq,
+//! }
+//! .encode();
+//! let program = [Value::Scalar(Felt::from(instruction.0)), Value::Scalar(Felt::TWO)];
+//!
+//! let mut vm = CairoVM::new(&program, Vec::new()).unwrap();
+//! vm.assert_eq(vm.ap(), ValueRef::Scalar(&Felt::THREE)).unwrap();
+//!
+//! let mut trace = PrintTrace::new(Vec::new());
+//! vm.step(&mut NoopHintProcessor, &mut trace).unwrap();
+//!
+//! let log = String::from_utf8(trace.into_inner().unwrap()).unwrap();
+//! assert_eq!(log, "pc=0:0 ap=1:0 fp=1:0 | [ap + 1] = [ap] + [pc + 1]; ap++\n");
+//! # }
+//! ```
+
+#[cfg(feature = "std")]
+mod cfg;
+#[cfg(feature = "serde")]
+mod json;
+#[cfg(feature = "std")]
+mod print;
+mod register;
+mod replay;
+mod undo;
+
+#[cfg(feature = "std")]
+pub use self::cfg::*;
+#[cfg(feature = "serde")]
+pub use self::json::*;
+#[cfg(feature = "std")]
+pub use self::print::*;
+pub use self::register::*;
+pub use self::replay::*;
+pub use self::undo::*;
+
+use crate::cpu::Cpu;
+use crate::error::Error;
+use crate::instr::Instruction;
+use crate::memory::Pointer;
+
+/// A collection of callbacks to be called during the execution of a Cairo program.
+#[allow(unused_variables)]
+pub trait Trace {
+    /// Called at the beginning of each step, before the current instruction is executed.
+    ///
+    /// `cpu` holds the state of the registers at that point.
+    fn on_step(&mut self, cpu: &Cpu) {}
+
+    /// Called once the instruction referenced by the **Program Counter** has been fetched, and
+    /// before it is executed.
+    ///
+    /// `pc` is the address of the instruction.
+    fn on_decode(&mut self, pc: Pointer, instruction: Instruction) {}
+
+    /// Called once a `call` instruction has been executed.
+    ///
+    /// `target` is the address of the first instruction of the called function, and
+    /// `return_to` is the address execution will resume at once it returns.
+    fn on_call(&mut self, target: Pointer, return_to: Pointer) {}
+
+    /// Called once a `ret` instruction has been executed.
+    ///
+    /// `to` is the address execution resumes at.
+    fn on_ret(&mut self, to: Pointer) {}
+
+    /// Called once a step has written a memory cell that was previously unknown.
+    ///
+    /// This happens when one of the operands of the instruction is deduced. Cells written by
+    /// hints are not reported.
+    fn on_write(&mut self, pointer: Pointer) {}
+
+    /// Called when a step fails.
+    ///
+    /// `cpu` holds the state of the registers at that point, which is usually the state they
+    /// had at the beginning of the failed step.
+    fn on_error(&mut self, cpu: &Cpu, error: &Error) {}
+}
+
+/// An implementation of [`Trace`] that does nothing.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoopTrace;
+impl Trace for NoopTrace {}
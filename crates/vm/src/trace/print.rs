@@ -0,0 +1,92 @@
+//! Defines the [`PrintTrace`] type.
+
+use std::io::{self, Write};
+
+use crate::cpu::Cpu;
+use crate::error::Error;
+use crate::instr::Instruction;
+use crate::memory::Pointer;
+
+use super::Trace;
+
+/// An implementation of [`Trace`] that writes a human-readable line per step to a writer.
+///
+/// Each line holds the state of the registers at the beginning of the step, followed by the
+/// disassembly of the executed instruction:
+///
+/// ```text
+/// pc=0:5 ap=1:10 fp=1:3 | [ap] = [fp - 1] + [ap - 2]
+/// ```
+///
+/// When a step fails, an additional line is written, holding the error instead of the
+/// instruction.
+///
+/// Because the callbacks of [`Trace`] cannot fail, the first error produced by the writer is
+/// stored and returned by [`into_inner`](Self::into_inner). No line is written after it.
+#[derive(Debug)]
+pub struct PrintTrace<W> {
+    /// The writer the lines are written to.
+    writer: W,
+    /// The state of the registers at the beginning of the current step.
+    cpu: Option<Cpu>,
+    /// The first error produced by the writer, if any.
+    error: Option<io::Error>,
+}
+
+impl<W: Write> PrintTrace<W> {
+    /// Creates a new [`PrintTrace`] writing to the provided writer.
+    #[inline(always)]
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            cpu: None,
+            error: None,
+        }
+    }
+
+    /// Returns the underlying writer.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with the first error produced by the writer, if any.
+    pub fn into_inner(self) -> io::Result<W> {
+        match self.error {
+            Some(err) => Err(err),
+            None => Ok(self.writer),
+        }
+    }
+
+    /// Writes a line to the underlying writer, unless a previous write failed.
+    fn write_line(&mut self, cpu: &Cpu, suffix: core::fmt::Arguments) {
+        if self.error.is_some() {
+            return;
+        }
+
+        let result = writeln!(
+            self.writer,
+            "pc={} ap={} fp={} | {}",
+            cpu.pc, cpu.ap, cpu.fp, suffix,
+        );
+
+        if let Err(err) = result {
+            self.error = Some(err);
+        }
+    }
+}
+
+impl<W: Write> Trace for PrintTrace<W> {
+    fn on_step(&mut self, cpu: &Cpu) {
+        self.cpu = Some(cpu.clone());
+    }
+
+    fn on_decode(&mut self, _pc: Pointer, instruction: Instruction) {
+        if let Some(cpu) = self.cpu.take() {
+            self.write_line(&cpu, format_args!("{instruction}"));
+        }
+    }
+
+    fn on_error(&mut self, cpu: &Cpu, error: &Error) {
+        self.cpu = None;
+        self.write_line(cpu, format_args!("error: {error:?}"));
+    }
+}
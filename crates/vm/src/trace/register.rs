@@ -0,0 +1,150 @@
+//! Defines the [`RegisterTrace`] type.
+
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io::{self, Write};
+
+use crate::cpu::Cpu;
+#[cfg(feature = "std")]
+use crate::memory::RelocationTable;
+
+use super::Trace;
+
+/// A single step of an execution trace.
+///
+/// See [`CairoVM::verify_step`](crate::CairoVM::verify_step).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceRecord {
+    /// The state of the registers before the step.
+    pub cpu: Cpu,
+    /// The state of the registers after the step.
+    pub next: Cpu,
+}
+
+/// An implementation of [`Trace`] that records the state of the registers at every step.
+#[derive(Debug, Default, Clone)]
+pub struct RegisterTrace {
+    /// The state of the registers before each executed step.
+    entries: Vec<Cpu>,
+}
+
+impl RegisterTrace {
+    /// Creates a new empty [`RegisterTrace`].
+    #[inline(always)]
+    pub const fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Returns the state of the registers before each executed step, in execution order.
+    #[inline(always)]
+    pub fn entries(&self) -> &[Cpu] {
+        &self.entries
+    }
+
+    /// Returns an iterator over the steps recorded by the trace, as [`TraceRecord`]s.
+    ///
+    /// The registers are only recorded before each step, meaning that the last recorded step
+    /// has no known outcome and is not part of the iterator.
+    pub fn records(&self) -> impl '_ + Iterator<Item = TraceRecord> {
+        self.entries.windows(2).map(|pair| TraceRecord {
+            cpu: pair[0].clone(),
+            next: pair[1].clone(),
+        })
+    }
+
+    /// Writes the register trace to the provided writer, using the binary `trace.bin` format
+    /// expected by the Cairo prover.
+    ///
+    /// For each step, the relocated addresses of **AP**, **FP** and **PC** are written (in
+    /// that order) as 8-byte little-endian integers. `relocation` must be the table returned
+    /// alongside the relocated memory by [`Memory::relocate`](crate::memory::Memory::relocate).
+    ///
+    /// # Errors
+    ///
+    /// Aside from errors produced by the writer, this function fails with
+    /// [`io::ErrorKind::InvalidInput`] if one of the registers cannot be relocated using the
+    /// provided table.
+    ///
+    /// This function is only available with the `std` feature.
+    #[cfg(feature = "std")]
+    pub fn write_bin<W>(&self, relocation: &RelocationTable, w: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        for cpu in &self.entries {
+            for register in [cpu.ap, cpu.fp, cpu.pc] {
+                let address = relocation.relocate(register).map_err(|_| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "register references a segment missing from the relocation table",
+                    )
+                })?;
+
+                w.write_all(&(address as u64).to_le_bytes())?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Trace for RegisterTrace {
+    fn on_step(&mut self, cpu: &Cpu) {
+        self.entries.push(cpu.clone());
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use starknet_types_core::felt::Felt;
+
+    use super::*;
+    use crate::memory::{Memory, Pointer, ValueRef};
+
+    /// Returns a pointer to `offset` in `segment`.
+    fn ptr(segment: usize, offset: usize) -> Pointer {
+        Pointer { segment, offset }
+    }
+
+    #[test]
+    fn write_bin_relocates_registers() {
+        // The program segment spans 3 cells, and the execution segment 2.
+        let mut memory = Memory::default();
+        for (segment, len) in [
+            (memory.allocate_segment(), 3),
+            (memory.allocate_segment(), 2),
+        ] {
+            memory
+                .assert_eq(ptr(segment, len - 1), ValueRef::Scalar(&Felt::ONE))
+                .unwrap();
+        }
+        let table = memory.relocate().unwrap().table().clone();
+
+        let mut trace = RegisterTrace::new();
+        for (pc, ap, fp) in [(0, 0, 0), (2, 1, 0)] {
+            trace.on_step(&Cpu {
+                pc: ptr(0, pc),
+                ap: ptr(1, ap),
+                fp: ptr(1, fp),
+            });
+        }
+
+        let mut bin = Vec::new();
+        trace.write_bin(&table, &mut bin).unwrap();
+        let words: Vec<u64> = bin
+            .chunks_exact(8)
+            .map(|word| u64::from_le_bytes(word.try_into().unwrap()))
+            .collect();
+        assert_eq!(words, [4, 4, 1, 5, 4, 3]);
+
+        trace.on_step(&Cpu {
+            pc: ptr(0, 0),
+            ap: ptr(2, 0),
+            fp: ptr(1, 0),
+        });
+        let error = trace.write_bin(&table, &mut Vec::new()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+}
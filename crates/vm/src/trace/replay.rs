@@ -0,0 +1,376 @@
+//! Defines the [`ReplayTrace`] type.
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::cpu::Cpu;
+use crate::error::Error;
+use crate::hint::HintProcessor;
+use crate::memory::{Pointer, Value};
+use crate::CairoVM;
+
+/// A single step recorded by a [`ReplayTrace`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayStep {
+    /// The state of the registers at the beginning of the step.
+    pub cpu: Cpu,
+    /// The number of segments allocated by the hints of the step.
+    pub new_segments: usize,
+    /// The cells written by the hints of the step, in the order they were written.
+    pub writes: Vec<(Pointer, Value)>,
+}
+
+/// A record of the non-determinism of an execution, allowing it to be executed again with
+/// [`CairoVM::replay`] without running any hint.
+///
+/// The instructions of a Cairo program are deterministic: the only information a replay needs
+/// is what hints provided. For each step, the trace holds the state of the registers (used to
+/// detect diverging executions), as well as the segments allocated and the cells written by
+/// the hints of the step.
+///
+/// # Recording
+///
+/// A trace is recorded by wrapping the hint processor of an execution with
+/// [`record`](Self::record):
+///
+/// ```
+/// use rayquaza_vm::error::Error;
+/// use rayquaza_vm::hint::HintProcessor;
+/// use rayquaza_vm::instr::*;
+/// use rayquaza_vm::memory::{Pointer, Value, ValueRef};
+/// use rayquaza_vm::trace::{NoopTrace, ReplayTrace};
+/// use rayquaza_vm::CairoVM;
+/// use starknet_types_core::felt::Felt;
+///
+/// // Writes `3` to `[ap]` before each step.
+/// struct WriteThree;
+///
+/// impl HintProcessor for WriteThree {
+///     fn execute(&mut self, vm: &mut CairoVM, _pc: Pointer) -> Result<(), Error> {
+///         let ap = vm.ap();
+///         vm.assert_eq(ap, ValueRef::Scalar(&Felt::THREE))
+///     }
+/// }
+///
+/// // [ap + 1] = [ap + 0] + 2; ap++
+/// let instruction = DecodedInstruction {
+///     dst_offset: 1,
+///     op0_offset: 0,
+///     op1_offset: 1,
+///     dst_register: DstRegister::AP,
+///     op0_register: Op0Register::AP,
+///     op1_source: Op1Source::PC,
+///     result_logic: ResultLogic::Add,
+///     pc_update: PcUpdate::Regular,
+///     ap_update: ApUpdate::Increment,
+///     op_code: OpCode::AssertEq,
+/// }
+/// .encode();
+/// let program = [Value::Scalar(Felt::from(instruction.0)), Value::Scalar(Felt::TWO)];
+///
+/// let mut vm = CairoVM::new(&program, Vec::new()).unwrap();
+/// let end = vm.pc().wrapping_add(2);
+///
+/// let mut replay = ReplayTrace::new();
+/// vm.run(end, &Default::default(), &mut replay.record(&mut WriteThree), &mut NoopTrace)
+///     .unwrap();
+/// assert_eq!(replay.len(), 1);
+///
+/// // The hint is not needed anymore.
+/// let mut other = CairoVM::new(&program, Vec::new()).unwrap();
+/// other.replay(&replay).unwrap();
+/// assert_eq!(other.memory().diff(vm.memory()), []);
+/// ```
+///
+/// Only the segments allocated through the virtual machine and the cells written through
+/// [`CairoVM::assert_eq`] are recorded.
+///
+/// # Serialization
+///
+/// With the `serde` feature, the trace is serialized as a list of steps. Registers and pointers
+/// are rendered as `segment:offset`, and scalars as hexadecimal strings prefixed with `0x`
+/// (like in [`VmSnapshot`](crate::VmSnapshot)). Each written cell is an `[address, value]`
+/// pair. For example, a step whose hint allocated a segment and wrote its base to `[ap]`:
+///
+/// ```
+/// # #[cfg(feature = "serde")]
+/// # {
+/// use rayquaza_vm::memory::{Pointer, Value};
+/// use rayquaza_vm::trace::ReplayTrace;
+///
+/// let json = r#"{"steps":[{"cpu":{"pc":"0:0","ap":"1:4","fp":"1:4"},"new_segments":1,"writes":[["1:4","2:0"]]}]}"#;
+///
+/// let trace: ReplayTrace = serde_json::from_str(json).unwrap();
+/// let step = &trace.steps()[0];
+/// assert_eq!(step.new_segments, 1);
+/// assert_eq!(
+///     step.writes,
+///     [(
+///         Pointer { segment: 1, offset: 4 },
+///         Value::Pointer(Pointer { segment: 2, offset: 0 }),
+///     )],
+/// );
+///
+/// assert_eq!(serde_json::to_string(&trace).unwrap(), json);
+/// # }
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ReplayTrace {
+    /// The recorded steps, in execution order.
+    steps: Vec<ReplayStep>,
+}
+
+impl ReplayTrace {
+    /// Creates a new empty [`ReplayTrace`].
+    #[inline(always)]
+    pub const fn new() -> Self {
+        Self { steps: Vec::new() }
+    }
+
+    /// Returns the recorded steps, in execution order.
+    #[inline(always)]
+    pub fn steps(&self) -> &[ReplayStep] {
+        &self.steps
+    }
+
+    /// Returns the number of recorded steps.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    /// Returns whether no step has been recorded.
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Returns a [`HintProcessor`] running the hints of `hints`, and recording the steps they
+    /// are run for to this trace.
+    #[inline(always)]
+    pub fn record<'a, H>(&'a mut self, hints: &'a mut H) -> ReplayRecorder<'a, H>
+    where
+        H: ?Sized + HintProcessor,
+    {
+        ReplayRecorder { trace: self, hints }
+    }
+}
+
+/// A [`HintProcessor`] recording the non-determinism of an execution to a [`ReplayTrace`].
+///
+/// See [`ReplayTrace::record`].
+pub struct ReplayRecorder<'a, H: ?Sized> {
+    /// The trace the steps are recorded to.
+    trace: &'a mut ReplayTrace,
+    /// The hint processor actually running the hints.
+    hints: &'a mut H,
+}
+
+impl<'a, H> HintProcessor for ReplayRecorder<'a, H>
+where
+    H: ?Sized + HintProcessor,
+{
+    fn execute(&mut self, vm: &mut CairoVM, pc: Pointer) -> Result<(), Error> {
+        let cpu = vm.cpu().clone();
+        let num_segments = vm.memory().num_segments();
+
+        vm.start_journal();
+        let result = self.hints.execute(vm, pc);
+        let writes = vm.take_journal();
+
+        // A failing hint aborts the step, which can't be replayed.
+        result?;
+
+        self.trace.steps.push(ReplayStep {
+            cpu,
+            new_segments: vm.memory().num_segments() - num_segments,
+            writes,
+        });
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn hint_count(&self, pc: Pointer) -> usize {
+        self.hints.hint_count(pc)
+    }
+}
+
+impl<'a, H: ?Sized> fmt::Debug for ReplayRecorder<'a, H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReplayRecorder")
+            .field("trace", &self.trace)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serialization {
+    use alloc::string::{String, ToString};
+    use alloc::vec::Vec;
+
+    use serde::de::Error as _;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::snapshot::{cell_to_string, parse_cell, parse_pointer, Registers};
+
+    use super::{ReplayStep, ReplayTrace};
+
+    /// The serialized representation of a [`ReplayStep`].
+    #[derive(Serialize, Deserialize)]
+    struct StepRepr {
+        /// The registers at the beginning of the step.
+        cpu: Registers,
+        /// The number of segments allocated by the hints of the step.
+        new_segments: usize,
+        /// The cells written by the hints of the step, as `(address, value)` pairs.
+        writes: Vec<(String, String)>,
+    }
+
+    /// The serialized representation of a [`ReplayTrace`].
+    #[derive(Serialize, Deserialize)]
+    struct Repr {
+        /// The recorded steps.
+        steps: Vec<StepRepr>,
+    }
+
+    impl Serialize for ReplayTrace {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let steps = self
+                .steps
+                .iter()
+                .map(|step| StepRepr {
+                    cpu: Registers::new(&step.cpu),
+                    new_segments: step.new_segments,
+                    writes: step
+                        .writes
+                        .iter()
+                        .map(|(pointer, value)| {
+                            (pointer.to_string(), cell_to_string(value.as_ref()))
+                        })
+                        .collect(),
+                })
+                .collect();
+
+            Repr { steps }.serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for ReplayTrace {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let repr = Repr::deserialize(deserializer)?;
+
+            let mut steps = Vec::with_capacity(repr.steps.len());
+            for step in repr.steps {
+                let mut writes = Vec::with_capacity(step.writes.len());
+                for (address, value) in &step.writes {
+                    let pointer = parse_pointer(address).ok_or_else(|| {
+                        D::Error::custom(alloc::format!("invalid address `{address}`"))
+                    })?;
+                    writes.push((pointer, parse_cell(value)?));
+                }
+
+                steps.push(ReplayStep {
+                    cpu: step.cpu.parse()?,
+                    new_segments: step.new_segments,
+                    writes,
+                });
+            }
+
+            Ok(ReplayTrace { steps })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::ValueRef;
+
+    /// A hint allocating a segment, writing its base to `[ap]`, then failing if asked to.
+    struct AllocThenFail(bool);
+
+    impl HintProcessor for AllocThenFail {
+        fn execute(&mut self, vm: &mut CairoVM, _pc: Pointer) -> Result<(), Error> {
+            let base = vm.alloc_segment_pointer();
+            vm.assert_eq(vm.ap(), ValueRef::Pointer(&base))?;
+
+            if self.0 {
+                return Err(Error::Builtin);
+            }
+
+            Ok(())
+        }
+    }
+
+    /// Returns a pointer to `offset` in `segment`.
+    fn ptr(segment: usize, offset: usize) -> Pointer {
+        Pointer { segment, offset }
+    }
+
+    #[test]
+    fn failing_hint_is_not_recorded() {
+        let mut vm = CairoVM::new(&[], Vec::new()).unwrap();
+        let mut trace = ReplayTrace::new();
+
+        let pc = vm.pc();
+        assert!(trace
+            .record(&mut AllocThenFail(false))
+            .execute(&mut vm, pc)
+            .is_ok());
+        assert!(trace
+            .record(&mut AllocThenFail(true))
+            .execute(&mut vm, pc)
+            .is_err());
+
+        assert_eq!(trace.len(), 1);
+        assert_eq!(trace.steps()[0].new_segments, 1);
+        assert_eq!(
+            trace.steps()[0].writes,
+            [(ptr(1, 0), Value::Pointer(ptr(2, 0)))]
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        use alloc::vec;
+
+        use starknet_types_core::felt::Felt;
+
+        let trace = ReplayTrace {
+            steps: vec![
+                ReplayStep {
+                    cpu: Cpu {
+                        pc: ptr(0, 0),
+                        ap: ptr(1, 2),
+                        fp: ptr(1, 2),
+                    },
+                    new_segments: 0,
+                    writes: vec![(ptr(1, 2), Value::Scalar(Felt::from(42)))],
+                },
+                ReplayStep {
+                    cpu: Cpu {
+                        pc: ptr(0, 2),
+                        ap: ptr(1, 3),
+                        fp: ptr(1, 2),
+                    },
+                    new_segments: 2,
+                    writes: vec![
+                        (ptr(1, 3), Value::Pointer(ptr(2, 0))),
+                        (ptr(1, 4), Value::Pointer(ptr(3, 0))),
+                    ],
+                },
+            ],
+        };
+
+        let json = serde_json::to_value(&trace).unwrap();
+        assert_eq!(json["steps"][0]["cpu"]["ap"], "1:2");
+        assert_eq!(json["steps"][1]["new_segments"], 2);
+        assert_eq!(json["steps"][1]["writes"][1][0], "1:4");
+        assert_eq!(json["steps"][1]["writes"][1][1], "3:0");
+
+        let parsed: ReplayTrace = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, trace);
+    }
+}
@@ -0,0 +1,127 @@
+//! Defines the [`UndoLog`] type.
+
+use alloc::vec::Vec;
+
+use crate::cpu::Cpu;
+use crate::error::Error;
+use crate::memory::Pointer;
+
+use super::Trace;
+
+/// A single step recorded by an [`UndoLog`].
+#[derive(Debug, Clone)]
+struct UndoEntry {
+    /// The state of the registers before the step.
+    cpu: Cpu,
+    /// The index of the first cell written by the step in [`UndoLog::written`].
+    first_write: usize,
+}
+
+/// An implementation of [`Trace`] that records enough information to step the virtual machine
+/// backwards, using [`CairoVM::undo`](crate::CairoVM::undo).
+///
+/// For each step, the log keeps the state of the registers before the step, as well as the
+/// memory cells that the step turned from unknown to known.
+///
+/// # Limitations
+///
+/// The memory of the virtual machine is write-once: a cell that has been asserted to a value
+/// can't become unknown again. Undoing a step only rewinds the registers. The cells written
+/// by the undone step keep their value, and are reported by
+/// [`stale_cells`](Self::stale_cells) instead.
+///
+/// Executing the undone step again is always consistent, as it deduces the same values.
+/// However, stale cells may hide a contradiction that a modified execution would otherwise
+/// have encountered.
+///
+/// Cells written outside of the instructions themselves (for example by hints) are not
+/// recorded.
+#[derive(Debug, Default, Clone)]
+pub struct UndoLog {
+    /// The steps that can be undone, in execution order.
+    entries: Vec<UndoEntry>,
+    /// The cells written by the recorded steps, in execution order.
+    written: Vec<Pointer>,
+    /// The cells written by the steps that have been undone.
+    stale: Vec<Pointer>,
+}
+
+impl UndoLog {
+    /// Creates a new empty [`UndoLog`].
+    #[inline(always)]
+    pub const fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            written: Vec::new(),
+            stale: Vec::new(),
+        }
+    }
+
+    /// Returns the number of steps that can be undone.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether no step can be undone.
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the cells written by the steps that have been undone.
+    ///
+    /// Those cells would have to be reset for the memory to match the state of the rewound
+    /// registers, which the virtual machine cannot do.
+    #[inline(always)]
+    pub fn stale_cells(&self) -> &[Pointer] {
+        &self.stale
+    }
+
+    /// Forgets about the cells reported by [`stale_cells`](Self::stale_cells).
+    #[inline]
+    pub fn clear_stale_cells(&mut self) {
+        self.stale.clear();
+    }
+
+    /// Removes the last recorded step from the log, returning the state of the registers
+    /// before it.
+    ///
+    /// The cells written by the step are moved to the [stale cells](Self::stale_cells).
+    ///
+    /// # Errors
+    ///
+    /// This function fails with [`Error::NothingToUndo`] if no step has been recorded.
+    pub(crate) fn pop(&mut self) -> Result<Cpu, Error> {
+        let entry = self.entries.pop().ok_or(Error::NothingToUndo)?;
+        self.stale.extend(self.written.drain(entry.first_write..));
+        Ok(entry.cpu)
+    }
+}
+
+impl Trace for UndoLog {
+    fn on_step(&mut self, cpu: &Cpu) {
+        self.entries.push(UndoEntry {
+            cpu: cpu.clone(),
+            first_write: self.written.len(),
+        });
+    }
+
+    fn on_write(&mut self, pointer: Pointer) {
+        self.written.push(pointer);
+    }
+
+    fn on_error(&mut self, cpu: &Cpu, _error: &Error) {
+        // A failed step leaves the registers untouched, there is nothing to undo. Steps can
+        // fail before being recorded (e.g. in a hint), so the last entry is only discarded if
+        // it belongs to the failed step: failed steps never report any write.
+        let failed = self
+            .entries
+            .last()
+            .is_some_and(|entry| entry.cpu == *cpu && entry.first_write == self.written.len());
+
+        if failed {
+            self.entries.pop();
+        }
+    }
+}
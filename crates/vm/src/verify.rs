@@ -0,0 +1,178 @@
+//! Verification of individual steps of an execution trace, independently of each other.
+
+use num_traits::ToPrimitive;
+use starknet_types_core::felt::Felt;
+
+use crate::cpu::Cpu;
+use crate::error::Error;
+use crate::instr::{
+    ApUpdate, DecodedInstruction, DstRegister, Instruction, Op0Register, Op1Source, OpCode,
+    PcUpdate,
+};
+use crate::memory::{Memory, Pointer, Value, ValueRef};
+use crate::trace::TraceRecord;
+use crate::{compute_res, CairoVM};
+
+impl CairoVM {
+    /// Verifies that a single step of an execution trace is consistent with the final state of
+    /// the memory.
+    ///
+    /// The instruction at `record.cpu.pc` is decoded, its operands are read from `memory`, and
+    /// the function checks that they satisfy the instruction (e.g. that `dst` equals the result
+    /// of an `AssertEq`), and that the registers were updated to `record.next` accordingly.
+    ///
+    /// Because every memory cell is known once the program has finished running, each step
+    /// can be verified without executing the ones before it. This enables verifying a trace
+    /// out of order, or in parallel.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with:
+    ///
+    /// - [`Error::ProgramCounterLost`], [`Error::InstructionIsPointer`] or one of the
+    ///   `Undefined*` errors if the instruction cannot be fetched or decoded.
+    ///
+    /// - One of the `CantDeduce*` errors if an operand is unknown in `memory`.
+    ///
+    /// - [`Error::ExpectedPointer`] if `op1` is relative to `op0`, but `op0` is a scalar.
+    ///
+    /// - [`Error::InvalidImmediate`] if the immediate value of the instruction is a pointer.
+    ///
+    /// - [`Error::Contradiction`] if the operands do not satisfy the instruction.
+    ///
+    /// - [`Error::InvalidTraceRecord`] if the registers were not updated as the instruction
+    ///   requires.
+    pub fn verify_step(&self, record: &TraceRecord, memory: &Memory) -> Result<(), Error> {
+        let cpu = &record.cpu;
+
+        let word = match memory.get(cpu.pc) {
+            Some(ValueRef::Scalar(word)) => word,
+            Some(ValueRef::Pointer(_)) => return Err(Error::InstructionIsPointer),
+            None => return Err(Error::ProgramCounterLost),
+        };
+        let instruction = DecodedInstruction::new(Instruction::try_from_felt(word)?)?;
+
+        let dst_addr = match instruction.dst_register {
+            DstRegister::AP => cpu.ap,
+            DstRegister::FP => cpu.fp,
+        };
+        let dst_addr = offset_by(dst_addr, instruction.dst_offset as isize)?;
+        let op0_addr = match instruction.op0_register {
+            Op0Register::AP => cpu.ap,
+            Op0Register::FP => cpu.fp,
+        };
+        let op0_addr = offset_by(op0_addr, instruction.op0_offset as isize)?;
+        let dst = memory.get(dst_addr).ok_or(Error::CantDeduceDst)?.copied();
+        let op0 = memory.get(op0_addr).ok_or(Error::CantDeduceOp0)?.copied();
+
+        let op1_addr = match instruction.op1_source {
+            Op1Source::Op0 => match op0 {
+                Value::Pointer(p) => p,
+                Value::Scalar(_) => return Err(Error::ExpectedPointer),
+            },
+            Op1Source::PC => cpu.pc,
+            Op1Source::FP => cpu.fp,
+            Op1Source::AP => cpu.ap,
+        };
+        let op1_addr = offset_by(op1_addr, instruction.op1_offset as isize)?;
+        let op1 = memory.get(op1_addr).ok_or(Error::CantDeduceOp1)?.copied();
+        if instruction.op1_source == Op1Source::PC
+            && instruction.op1_offset == 1
+            && matches!(op1, Value::Pointer(_))
+        {
+            return Err(Error::InvalidImmediate);
+        }
+
+        // The result is not meaningful for every instruction (e.g. it cannot be computed for
+        // conditional jumps), so it is only computed when needed.
+        let res = || compute_res(instruction.result_logic, &op0, &op1);
+
+        let next_fp = match instruction.op_code {
+            OpCode::Call => {
+                if dst != Value::Pointer(cpu.fp) {
+                    return Err(Error::Contradiction);
+                }
+                if op0 != Value::Pointer(cpu.pc.wrapping_add(instruction.size())) {
+                    return Err(Error::Contradiction);
+                }
+                cpu.ap.wrapping_add(2)
+            }
+            OpCode::AssertEq => {
+                if res()? != dst {
+                    return Err(Error::Contradiction);
+                }
+                cpu.fp
+            }
+            OpCode::Ret => dst.try_into_pointer().map_err(|_| Error::InvalidReturn)?,
+            OpCode::None => cpu.fp,
+        };
+
+        let next_ap = match instruction.ap_update {
+            ApUpdate::None if instruction.op_code == OpCode::Call => cpu.ap.wrapping_add(2),
+            ApUpdate::None => cpu.ap,
+            ApUpdate::AddResult => {
+                let delta = res()?
+                    .try_into_felt()
+                    .map_err(|_| Error::UndefinedApUpdate)?;
+                offset_by(cpu.ap, felt_to_offset(&delta)?)?
+            }
+            ApUpdate::Increment => cpu.ap.wrapping_add(1),
+        };
+
+        let next_pc = match instruction.pc_update {
+            PcUpdate::Regular => cpu.pc.wrapping_add(instruction.size()),
+            PcUpdate::AbsoluteJump => res()?
+                .try_into_pointer()
+                .map_err(|_| Error::InvalidAbsoluteJump)?,
+            PcUpdate::RelativeJump => {
+                let delta = res()?
+                    .try_into_felt()
+                    .map_err(|_| Error::InvalidRelativeJump)?;
+                offset_by(cpu.pc, felt_to_offset(&delta)?)?
+            }
+            PcUpdate::ConditionalJump if dst.is_zero() => cpu.pc.wrapping_add(instruction.size()),
+            PcUpdate::ConditionalJump => {
+                let delta = op1
+                    .try_into_felt()
+                    .map_err(|_| Error::InvalidRelativeJump)?;
+                offset_by(cpu.pc, felt_to_offset(&delta)?)?
+            }
+        };
+
+        let expected = Cpu {
+            pc: next_pc,
+            ap: next_ap,
+            fp: next_fp,
+        };
+
+        if expected != record.next {
+            return Err(Error::InvalidTraceRecord);
+        }
+
+        Ok(())
+    }
+}
+
+/// Offsets the provided pointer by a signed amount.
+fn offset_by(pointer: Pointer, delta: isize) -> Result<Pointer, Error> {
+    if delta >= 0 {
+        pointer.checked_add(delta as usize)
+    } else {
+        pointer.checked_sub(delta.unsigned_abs())
+    }
+    .ok_or(Error::PointerTooLarge)
+}
+
+/// Interprets a field element as a signed offset.
+///
+/// Field elements in the upper half of the field represent negative values.
+pub(crate) fn felt_to_offset(felt: &Felt) -> Result<isize, Error> {
+    if let Some(offset) = felt.to_isize() {
+        return Ok(offset);
+    }
+
+    (Felt::ZERO - *felt)
+        .to_isize()
+        .map(|offset| -offset)
+        .ok_or(Error::PointerTooLarge)
+}